@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS missed_slots_root_cause_idx;
+
+ALTER TABLE missed_slots
+    DROP COLUMN root_cause_evidence,
+    DROP COLUMN root_cause;
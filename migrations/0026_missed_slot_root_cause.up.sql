@@ -0,0 +1,5 @@
+ALTER TABLE missed_slots
+    ADD COLUMN root_cause text,
+    ADD COLUMN root_cause_evidence jsonb;
+
+CREATE INDEX missed_slots_root_cause_idx ON missed_slots (root_cause, inserted_at);
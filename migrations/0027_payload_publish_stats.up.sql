@@ -0,0 +1,15 @@
+CREATE TABLE payload_publish_stats (
+    slot_number bigint NOT NULL,
+    geo text NOT NULL,
+    block_hash character varying(66) NOT NULL,
+    delivered_at timestamptz NOT NULL,
+    decoded_at_slot_age_ms bigint NOT NULL,
+    pre_publish_duration_ms bigint NOT NULL,
+    publish_duration_ms bigint NOT NULL,
+    request_download_duration_ms bigint NOT NULL,
+    publish_done_ms_into_slot bigint NOT NULL,
+    inserted_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (slot_number, geo)
+);
+
+CREATE INDEX payload_publish_stats_delivered_at_idx ON payload_publish_stats (delivered_at);
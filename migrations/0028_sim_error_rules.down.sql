@@ -0,0 +1 @@
+DROP TABLE IF EXISTS sim_error_rules;
@@ -0,0 +1,22 @@
+CREATE TABLE sim_error_rules (
+    id serial PRIMARY KEY,
+    inserted_at timestamptz NOT NULL DEFAULT now(),
+    priority integer NOT NULL DEFAULT 0,
+    match_type text NOT NULL CHECK (match_type IN ('prefix', 'regex')),
+    pattern text NOT NULL,
+    category text NOT NULL CHECK (
+        category IN ('ignore', 'promotable', 'trusted_promotable', 'alert')
+    )
+);
+
+INSERT INTO sim_error_rules (priority, match_type, pattern, category) VALUES
+    (0, 'prefix', 'Post "http://prio-load-balancer:80": context deadline exceeded (Client.Timeout exceeded while awaiting headers)', 'ignore'),
+    (0, 'prefix', 'json error: request timeout hit before processing', 'ignore'),
+    (0, 'prefix', 'simulation failed: unknown ancestor', 'ignore'),
+    (0, 'prefix', 'simulation queue timed out', 'ignore'),
+    (10, 'prefix', 'HTTP status server error (500 Internal Server Error) for url (http://prio-load-balancer/)', 'promotable'),
+    (10, 'prefix', 'simulation failed: parent block not found', 'promotable'),
+    (10, 'prefix', 'simulation failed: incorrect gas limit set', 'promotable'),
+    (10, 'prefix', 'parent_beacon_block_root is None for V3 request', 'promotable'),
+    (20, 'prefix', 'simulation failed: could not apply tx', 'trusted_promotable'),
+    (20, 'prefix', 'simulation failed: invalid merkle root', 'trusted_promotable');
@@ -0,0 +1,18 @@
+CREATE TABLE builder_contacts (
+    builder_id text PRIMARY KEY,
+    inserted_at timestamptz NOT NULL DEFAULT now(),
+    telegram_chat_id text,
+    email text,
+    webhook_url text,
+    notify_demotions boolean NOT NULL DEFAULT true,
+    notify_promotions boolean NOT NULL DEFAULT true,
+    notify_missed_slots boolean NOT NULL DEFAULT false,
+    opted_in boolean NOT NULL DEFAULT false
+);
+
+INSERT INTO builder_contacts (builder_id, telegram_chat_id, opted_in) VALUES
+    ('titan', '-1002036721274', true),
+    ('beaverbuild', '-614386130', true),
+    ('beaverbuild-staging', '-614386130', true),
+    ('flashbots', '-4827360620', true),
+    ('buildernet', '-4827360620', true);
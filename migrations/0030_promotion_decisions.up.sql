@@ -0,0 +1,18 @@
+CREATE TABLE promotion_decisions (
+    id bigserial PRIMARY KEY,
+    decided_at timestamptz NOT NULL DEFAULT now(),
+    builder_id text NOT NULL,
+    demotions jsonb NOT NULL,
+    missed_slots_checked bigint[] NOT NULL,
+    -- Set for eligible builders, the rule which allowed the promotion.
+    rule text CHECK (rule IN ('promotable_errors', 'trusted_builder_errors')),
+    -- Set for rejected builders.
+    rejection text CHECK (rejection IN ('missed_slot', 'non_promotable_error')),
+    outcome text NOT NULL CHECK (
+        outcome IN ('promoted', 'not_updated', 'shadow', 'rejected')
+    ),
+    shadow boolean NOT NULL
+);
+
+CREATE INDEX promotion_decisions_builder_id_decided_at_idx
+    ON promotion_decisions (builder_id, decided_at);
@@ -0,0 +1,6 @@
+ALTER TABLE promotion_decisions DROP COLUMN IF EXISTS risk;
+ALTER TABLE promotion_decisions DROP CONSTRAINT promotion_decisions_rejection_check;
+ALTER TABLE promotion_decisions ADD CONSTRAINT promotion_decisions_rejection_check CHECK (
+    rejection IN ('missed_slot', 'non_promotable_error')
+);
+DROP TABLE IF EXISTS builder_promotion_policies;
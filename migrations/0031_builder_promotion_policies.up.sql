@@ -0,0 +1,21 @@
+-- Per builder overrides of the default promotion policy, null columns use the default.
+CREATE TABLE builder_promotion_policies (
+    builder_id text PRIMARY KEY,
+    inserted_at timestamptz NOT NULL DEFAULT now(),
+    max_over_collateral_share double precision,
+    max_missed_slots integer,
+    cooldown_demotions integer,
+    cooldown_minutes integer
+);
+
+ALTER TABLE promotion_decisions DROP CONSTRAINT promotion_decisions_rejection_check;
+ALTER TABLE promotion_decisions ADD CONSTRAINT promotion_decisions_rejection_check CHECK (
+    rejection IN (
+        'missed_slot',
+        'non_promotable_error',
+        'over_collateral',
+        'miss_history',
+        'cooldown'
+    )
+);
+ALTER TABLE promotion_decisions ADD COLUMN risk jsonb;
@@ -0,0 +1 @@
+DROP TABLE IF EXISTS promotion_token_log;
@@ -0,0 +1,13 @@
+-- Lifecycle of the promotion tokens in the relay database.
+CREATE TABLE promotion_token_log (
+    token text PRIMARY KEY,
+    builder_id text NOT NULL,
+    issued_at timestamptz NOT NULL DEFAULT now(),
+    expires_at timestamptz NOT NULL,
+    revoked_at timestamptz,
+    revoke_reason text,
+    used_at timestamptz,
+    used_by text
+);
+
+CREATE INDEX promotion_token_log_builder_id_idx ON promotion_token_log (builder_id);
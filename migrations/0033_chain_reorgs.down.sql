@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS chain_reorg_slots;
+DROP TABLE IF EXISTS chain_reorgs;
@@ -0,0 +1,22 @@
+-- Reorgs reported by our beacon nodes.
+CREATE TABLE chain_reorgs (
+    id bigserial PRIMARY KEY,
+    slot bigint NOT NULL,
+    depth bigint NOT NULL,
+    old_head_block text NOT NULL,
+    new_head_block text NOT NULL,
+    detected_at timestamptz NOT NULL DEFAULT now(),
+    UNIQUE (slot, old_head_block, new_head_block)
+);
+
+CREATE INDEX chain_reorgs_detected_at_idx ON chain_reorgs (detected_at);
+
+-- Slots replaced by a reorg, with the payloads we delivered for them from `payload_delivered` in
+-- the relay database.
+CREATE TABLE chain_reorg_slots (
+    reorg_id bigint NOT NULL REFERENCES chain_reorgs (id) ON DELETE CASCADE,
+    slot bigint NOT NULL,
+    canonical_block_hash text,
+    delivered_block_hashes text[] NOT NULL DEFAULT '{}',
+    PRIMARY KEY (reorg_id, slot)
+);
@@ -0,0 +1,10 @@
+DROP TABLE IF EXISTS monitor_checkpoint_history;
+
+DELETE FROM monitor_checkpoints WHERE timestamp IS NULL;
+
+ALTER TABLE monitor_checkpoints
+    DROP CONSTRAINT monitor_checkpoints_position_check,
+    DROP COLUMN updated_at,
+    DROP COLUMN paused,
+    DROP COLUMN slot,
+    ALTER COLUMN timestamp SET NOT NULL;
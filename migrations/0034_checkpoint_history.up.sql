@@ -0,0 +1,21 @@
+-- Checkpoints hold either a slot or a timestamp, and can be paused from the admin interface.
+ALTER TABLE monitor_checkpoints
+    ALTER COLUMN timestamp DROP NOT NULL,
+    ADD COLUMN slot bigint,
+    ADD COLUMN paused boolean NOT NULL DEFAULT false,
+    ADD COLUMN updated_at timestamptz NOT NULL DEFAULT now(),
+    ADD CONSTRAINT monitor_checkpoints_position_check CHECK ((timestamp IS NULL) <> (slot IS NULL));
+
+-- Every change to a checkpoint, by the monitors or through the admin interface.
+CREATE TABLE monitor_checkpoint_history (
+    id bigserial PRIMARY KEY,
+    monitor_id text NOT NULL,
+    action text NOT NULL,
+    timestamp timestamptz,
+    slot bigint,
+    items_processed bigint,
+    created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX monitor_checkpoint_history_monitor_id_idx
+    ON monitor_checkpoint_history (monitor_id, created_at);
@@ -0,0 +1,8 @@
+-- Misses can be detected by concurrent checks and by the event driven monitor, keep one row each.
+DELETE FROM missed_slots a
+USING missed_slots b
+WHERE a.slot_number = b.slot_number
+  AND a.relayed_block_hash = b.relayed_block_hash
+  AND a.id > b.id;
+
+CREATE UNIQUE INDEX missed_slots_slot_relayed_idx ON missed_slots (slot_number, relayed_block_hash);
@@ -0,0 +1 @@
+ALTER TABLE promotion_token_log DROP COLUMN id;
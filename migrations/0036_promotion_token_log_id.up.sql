@@ -0,0 +1,2 @@
+-- Tokens are listed by id, their values never leave the database.
+ALTER TABLE promotion_token_log ADD COLUMN id bigint GENERATED ALWAYS AS IDENTITY UNIQUE;
@@ -0,0 +1 @@
+ALTER TABLE missed_slots DROP COLUMN reported;
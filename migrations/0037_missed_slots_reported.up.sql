@@ -0,0 +1,3 @@
+-- Whether the miss was alerted on. Misses stored before this column were.
+ALTER TABLE missed_slots ADD COLUMN reported boolean NOT NULL DEFAULT true;
+ALTER TABLE missed_slots ALTER COLUMN reported SET DEFAULT false;
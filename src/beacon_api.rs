@@ -1,8 +1,34 @@
-use anyhow::anyhow;
+mod block;
+#[cfg(test)]
+pub mod mock;
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context};
 use rand::seq::SliceRandom;
 use reqwest::{StatusCode, Url};
 use serde::Deserialize;
-use tracing::debug;
+use tokio::{sync::mpsc, time::timeout};
+use tracing::{debug, warn};
+
+pub use block::{BeaconBlock, ExecutionPayload};
+
+/// Head events arrive every slot, a connection that stays silent for much longer has stalled.
+const EVENT_STREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+/// Weight of the latest request in the per node latency and error rate averages.
+const HEALTH_WEIGHT: f64 = 0.2;
+/// Nodes failing more often than this are only asked when no other node is left.
+const MAX_HEALTHY_ERROR_RATE: f64 = 0.5;
+/// Unhealthy nodes are asked first again once their last failure is this old, so a node which
+/// recovered gets a chance to show it.
+const RETRY_FAILED_NODE_AFTER: Duration = Duration::from_secs(60);
 
 #[derive(Deserialize)]
 struct BeaconResponse<T> {
@@ -14,32 +40,213 @@ struct Validator {
     index: String,
 }
 
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProposerDuty {
+    pub pubkey: String,
+    #[serde(deserialize_with = "parse_i64_from_string")]
+    pub validator_index: i64,
+    #[serde(deserialize_with = "parse_i64_from_string")]
+    pub slot: i64,
+}
+
 #[derive(Deserialize)]
 pub struct SyncStatus {
     pub is_syncing: bool,
 }
 
-#[derive(Deserialize, Clone)]
-pub struct ExecutionPayload {
-    pub block_hash: String,
+#[derive(Deserialize)]
+struct HeaderMessage {
+    #[serde(deserialize_with = "parse_i64_from_string")]
+    slot: i64,
+    parent_root: String,
+}
+
+#[derive(Deserialize)]
+struct SignedHeader {
+    message: HeaderMessage,
+}
+
+#[derive(Deserialize)]
+struct HeaderResponse {
+    root: String,
+    header: SignedHeader,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockHeader {
+    pub root: String,
+    pub slot: i64,
+    pub parent_root: String,
+}
+
+impl From<HeaderResponse> for BlockHeader {
+    fn from(response: HeaderResponse) -> Self {
+        Self {
+            root: response.root,
+            slot: response.header.message.slot,
+            parent_root: response.header.message.parent_root,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Checkpoint {
     #[serde(deserialize_with = "parse_i64_from_string")]
-    pub block_number: i64,
+    pub epoch: i64,
+    pub root: String,
 }
 
 #[derive(Deserialize)]
-pub struct BlockBody {
-    execution_payload: ExecutionPayload,
+pub struct FinalityCheckpoints {
+    pub finalized: Checkpoint,
 }
 
 #[derive(Deserialize)]
-pub struct BlockMessage {
-    body: BlockBody,
+struct SlotEvent {
+    #[serde(deserialize_with = "parse_i64_from_string")]
+    slot: i64,
 }
 
-#[allow(dead_code)]
 #[derive(Deserialize)]
-pub struct BlockResponse {
-    message: BlockMessage,
+struct ChainReorgEvent {
+    #[serde(deserialize_with = "parse_i64_from_string")]
+    slot: i64,
+    #[serde(deserialize_with = "parse_i64_from_string")]
+    depth: i64,
+    old_head_block: String,
+    new_head_block: String,
+}
+
+/// Events from the beacon node event stream, see `/eth/v1/events`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BeaconEvent {
+    Head {
+        slot: i64,
+    },
+    Block {
+        slot: i64,
+    },
+    /// `slot` is the slot of the new head, `depth` the number of slots reorged.
+    ChainReorg {
+        slot: i64,
+        depth: i64,
+        old_head_block: String,
+        new_head_block: String,
+    },
+}
+
+impl BeaconEvent {
+    fn parse(event: &str, data: &str) -> anyhow::Result<Option<Self>> {
+        let event = match event {
+            "head" => {
+                let SlotEvent { slot } = serde_json::from_str(data)?;
+                Some(BeaconEvent::Head { slot })
+            }
+            "block" => {
+                let SlotEvent { slot } = serde_json::from_str(data)?;
+                Some(BeaconEvent::Block { slot })
+            }
+            "chain_reorg" => {
+                let ChainReorgEvent {
+                    slot,
+                    depth,
+                    old_head_block,
+                    new_head_block,
+                } = serde_json::from_str(data)?;
+                Some(BeaconEvent::ChainReorg {
+                    slot,
+                    depth,
+                    old_head_block,
+                    new_head_block,
+                })
+            }
+            _ => None,
+        };
+        Ok(event)
+    }
+}
+
+/// Take all complete server-sent events out of the buffer, leaving any partial event in place.
+/// Returns (event, data) pairs.
+fn drain_sse_events(buffer: &mut String) -> Vec<(String, String)> {
+    let mut events = Vec::new();
+
+    while let Some(end) = buffer.find("\n\n") {
+        let raw_event = buffer[..end].to_string();
+        buffer.drain(..end + 2);
+
+        let mut event = String::new();
+        let mut data = Vec::new();
+        for line in raw_event.lines() {
+            if let Some(value) = line.strip_prefix("event:") {
+                event = value.trim().to_string();
+            } else if let Some(value) = line.strip_prefix("data:") {
+                data.push(value.trim());
+            }
+        }
+
+        if !event.is_empty() {
+            events.push((event, data.join("\n")));
+        }
+    }
+
+    events
+}
+
+/// What we've seen of a node through the requests made to it.
+#[derive(Debug, Clone, Default)]
+pub struct NodeHealth {
+    /// Moving average of the response time of recent requests.
+    pub latency: Option<Duration>,
+    /// Moving average of the share of recent requests which failed.
+    pub error_rate: f64,
+    /// Sync state as of the last sync status check, if any.
+    pub is_syncing: Option<bool>,
+    /// Error of the last request, if it failed.
+    pub last_error: Option<String>,
+    /// When the last failed request was made, if any did.
+    pub failed_at: Option<Instant>,
+}
+
+impl NodeHealth {
+    fn record(&mut self, latency: Duration, error: Option<String>, now: Instant) {
+        self.latency = Some(match self.latency {
+            Some(average) => average.mul_f64(1.0 - HEALTH_WEIGHT) + latency.mul_f64(HEALTH_WEIGHT),
+            None => latency,
+        });
+        let failed = if error.is_some() { 1.0 } else { 0.0 };
+        self.error_rate = self.error_rate * (1.0 - HEALTH_WEIGHT) + failed * HEALTH_WEIGHT;
+        if error.is_some() {
+            self.failed_at = Some(now);
+        }
+        self.last_error = error;
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.is_syncing != Some(true)
+            && self.last_error.is_none()
+            && self.error_rate <= MAX_HEALTHY_ERROR_RATE
+    }
+
+    /// Whether the node is unhealthy because of failed requests, but hasn't failed for long
+    /// enough that it should be tried again.
+    fn is_due_retry(&self, now: Instant) -> bool {
+        !self.is_healthy()
+            && self
+                .failed_at
+                .is_some_and(|failed_at| now.duration_since(failed_at) >= RETRY_FAILED_NODE_AFTER)
+    }
+
+    /// Short description of what's wrong with the node, if anything, going by the last request.
+    pub fn problem(&self) -> Option<&'static str> {
+        if self.last_error.is_some() {
+            Some("unreachable")
+        } else if self.is_syncing == Some(true) {
+            Some("syncing")
+        } else {
+            None
+        }
+    }
 }
 
 fn parse_i64_from_string<'de, D>(deserializer: D) -> Result<i64, D::Error>
@@ -54,6 +261,8 @@ where
 pub struct BeaconApi {
     node_hosts: Vec<Url>,
     client: reqwest::Client,
+    /// Shared between clones, so every user of the api learns from each request.
+    health: Arc<Mutex<HashMap<Url, NodeHealth>>>,
 }
 
 impl BeaconApi {
@@ -66,21 +275,97 @@ impl BeaconApi {
         Self {
             node_hosts: node_hosts.to_vec(),
             client: reqwest::Client::new(),
+            health: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    /// Pick a random beacon node from the list we've been initialized with.
-    fn random_host(&self) -> &Url {
-        self.node_hosts.choose(&mut rand::thread_rng()).unwrap()
+    /// The beacon nodes we've been initialized with, results of the `*_all` methods are in the
+    /// same order.
+    pub fn node_hosts(&self) -> &[Url] {
+        &self.node_hosts
+    }
+
+    /// Health of every node, in the same order as `node_hosts`.
+    pub fn node_health(&self) -> Vec<(Url, NodeHealth)> {
+        let health = self.health.lock().unwrap();
+        self.node_hosts
+            .iter()
+            .map(|node| (node.clone(), health.get(node).cloned().unwrap_or_default()))
+            .collect()
+    }
+
+    /// Nodes to try in order, nodes due a retry first, then healthy nodes, then by error rate
+    /// and latency. Nodes which look the same are shuffled to spread the load.
+    fn ranked_hosts(&self) -> Vec<Url> {
+        let now = Instant::now();
+        let health = self.node_health();
+        let mut ranked = health.iter().collect::<Vec<_>>();
+        ranked.shuffle(&mut rand::thread_rng());
+        ranked.sort_by(|(_, a), (_, b)| {
+            b.is_due_retry(now)
+                .cmp(&a.is_due_retry(now))
+                .then(b.is_healthy().cmp(&a.is_healthy()))
+                .then(a.error_rate.total_cmp(&b.error_rate))
+                .then(
+                    a.latency
+                        .unwrap_or_default()
+                        .cmp(&b.latency.unwrap_or_default()),
+                )
+        });
+        ranked.into_iter().map(|(node, _)| node.clone()).collect()
+    }
+
+    /// Run a request against `node` and record how it went in the node's health.
+    async fn track<T, E: Display>(
+        &self,
+        node: &Url,
+        request: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        let start = Instant::now();
+        let result = request.await;
+        let error = result.as_ref().err().map(ToString::to_string);
+        self.health
+            .lock()
+            .unwrap()
+            .entry(node.clone())
+            .or_default()
+            .record(start.elapsed(), error, Instant::now());
+        result
     }
 
-    /// Fetch a validator index from a pubkey.
+    /// Run a request against the nodes in order of `ranked_hosts`, until one succeeds.
+    async fn with_failover<T, F, Fut>(&self, what: &str, request: F) -> anyhow::Result<T>
+    where
+        F: Fn(Url) -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let mut last_err = None;
+        for node in self.ranked_hosts() {
+            match self.track(&node, request(node.clone())).await {
+                Ok(result) => return Ok(result),
+                Err(err) => {
+                    warn!(%node, "failed to fetch {}, trying next node: {}", what, err);
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err.expect("expect at least one node"))
+    }
+
+    /// Fetch a validator index from a pubkey, trying the next node when one fails.
     pub async fn validator_index(&self, pubkey: &String) -> anyhow::Result<Option<String>> {
-        let url = format!(
-            "{}eth/v1/beacon/states/head/validators/{}",
-            self.random_host(),
-            pubkey
-        );
+        self.with_failover("validator index", |node| async move {
+            self.validator_index_from(&node, pubkey).await
+        })
+        .await
+    }
+
+    async fn validator_index_from(
+        &self,
+        node: &Url,
+        pubkey: &String,
+    ) -> anyhow::Result<Option<String>> {
+        let url = format!("{}eth/v1/beacon/states/head/validators/{}", node, pubkey);
 
         debug!(
             "fetching validator index for pubkey {} from {}",
@@ -106,34 +391,71 @@ impl BeaconApi {
         }
     }
 
-    /// Method to fetch the payload from a node and a slot.
-    async fn block_by_slot(
-        &self,
-        node: &Url,
-        slot: i64,
-    ) -> anyhow::Result<Option<ExecutionPayload>> {
-        let url = format!("{}eth/v2/beacon/blocks/{}", node, slot);
-        let res = self.client.get(&url).send().await?;
+    /// Fetch the proposers of every slot in an epoch, trying the next node when one fails. Nodes
+    /// only know the duties up to the next epoch.
+    pub async fn proposer_duties(&self, epoch: i64) -> anyhow::Result<Vec<ProposerDuty>> {
+        self.with_failover("proposer duties", |node| async move {
+            let url = format!("{}eth/v1/validator/duties/proposer/{}", node, epoch);
+            let duties = self
+                .client
+                .get(&url)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<BeaconResponse<Vec<ProposerDuty>>>()
+                .await?
+                .data;
+            Ok(duties)
+        })
+        .await
+    }
+
+    /// Method to fetch the block from a node by slot or root. Asks for SSZ, which is much quicker
+    /// to decode, but takes JSON from nodes which don't support it.
+    async fn block(&self, node: &Url, block_id: &str) -> anyhow::Result<Option<BeaconBlock>> {
+        let url = format!("{}eth/v2/beacon/blocks/{}", node, block_id);
+        let res = self
+            .client
+            .get(&url)
+            .header(
+                "Accept",
+                "application/octet-stream;q=1.0,application/json;q=0.9",
+            )
+            .send()
+            .await?;
         match res.status() {
             // Could mean:
             // 1. Slot doesn't have a block.
             // 2. Slot is in the future and doesn't have a block yet.
             // 3. Slot is before the beacon node backfill limit.
+            // 4. Root is of a block the node hasn't seen.
             StatusCode::NOT_FOUND => {
-                debug!("no block for slot {} on node {}", slot, node);
+                debug!("no block for {} on node {}", block_id, node);
                 Ok(None)
             }
             StatusCode::OK => {
-                debug!("found block for slot {} on node {}", slot, node);
-                let block = res
-                    .json::<BeaconResponse<BlockResponse>>()
-                    .await
-                    .map(|envelope| envelope.data.message)?;
-                Ok(Some(block.body.execution_payload))
+                debug!("found block for {} on node {}", block_id, node);
+                let is_ssz = res
+                    .headers()
+                    .get("Content-Type")
+                    .is_some_and(|content_type| content_type == "application/octet-stream");
+                let version = res
+                    .headers()
+                    .get("Eth-Consensus-Version")
+                    .and_then(|version| version.to_str().ok())
+                    .map(str::to_string);
+                let bytes = res.bytes().await?;
+                let block = if is_ssz {
+                    let version = version.context("ssz block without consensus version")?;
+                    block::decode_ssz(&version, &bytes)?
+                } else {
+                    block::decode_json(&bytes)?
+                };
+                Ok(Some(block))
             }
             status => Err(anyhow!(
-                "failed to fetch block by slot. slot = {} status = {} url = {}",
-                slot,
+                "failed to fetch block. block_id = {} status = {} url = {}",
+                block_id,
                 status,
                 res.url()
             )),
@@ -145,19 +467,20 @@ impl BeaconApi {
     /// This function is intended to be highly reliable, it does so by calling as many nodes as it
     /// can and returning the first Ok(Some) if any, then Ok(None) if any, and finally the first
     /// error.
-    pub async fn block_by_slot_any(&self, slot: i64) -> anyhow::Result<Option<ExecutionPayload>> {
+    pub async fn block_by_slot_any(&self, slot: i64) -> anyhow::Result<Option<BeaconBlock>> {
+        let block_id = slot.to_string();
         let futures = self
             .node_hosts
             .iter()
-            .map(|node| self.block_by_slot(node, slot));
+            .map(|node| self.track(node, self.block(node, &block_id)));
         let results = futures::future::join_all(futures).await;
 
         // Attempt to return the first Ok(Some) if any.
         for result in &results {
             match result {
-                Ok(Some(payload)) => {
+                Ok(Some(block)) => {
                     debug!("at least one node has a block for slot {}", slot);
-                    return Ok(Some(payload.clone()));
+                    return Ok(Some(block.clone()));
                 }
                 Ok(None) => continue,
                 Err(_) => continue,
@@ -187,6 +510,20 @@ impl BeaconApi {
             .expect("expect to results to not be empty")
     }
 
+    /// Fetch a block and its header by root, trying the next node when one fails or hasn't seen
+    /// the block. Unlike a slot, a root names one block whichever fork the node follows.
+    pub async fn block_by_root(&self, root: &str) -> anyhow::Result<(BlockHeader, BeaconBlock)> {
+        self.with_failover("block by root", |node| async move {
+            let header = self.header(&node, root).await?;
+            let block = self
+                .block(&node, root)
+                .await?
+                .with_context(|| format!("block {} not found", root))?;
+            Ok((header, block))
+        })
+        .await
+    }
+
     // Method to fetch the sync status from a node
     async fn sync_status(&self, node_url: &Url) -> reqwest::Result<SyncStatus> {
         let url = format!("{}eth/v1/node/syncing", node_url);
@@ -200,8 +537,359 @@ impl BeaconApi {
             .map(|body| body.data)
     }
 
-    pub async fn sync_status_all(&self) -> Vec<reqwest::Result<SyncStatus>> {
-        let futures = self.node_hosts.iter().map(|node| self.sync_status(node));
+    /// Check the sync status of every node, the results end up in `node_health`.
+    pub async fn refresh_sync_status(&self) {
+        let futures = self.node_hosts.iter().map(|node| async move {
+            let status = self.track(node, self.sync_status(node)).await;
+            if let Some(health) = self.health.lock().unwrap().get_mut(node) {
+                health.is_syncing = status.ok().map(|status| status.is_syncing);
+            }
+        });
+        futures::future::join_all(futures).await;
+    }
+
+    async fn header(&self, node_url: &Url, block_id: &str) -> reqwest::Result<BlockHeader> {
+        let url = format!("{}eth/v1/beacon/headers/{}", node_url, block_id);
+        self.client
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<BeaconResponse<HeaderResponse>>()
+            .await
+            .map(|body| body.data.into())
+    }
+
+    /// Fetch the head block header from every node.
+    pub async fn head_header_all(&self) -> Vec<reqwest::Result<BlockHeader>> {
+        let futures = self
+            .node_hosts
+            .iter()
+            .map(|node| self.track(node, self.header(node, "head")));
+        futures::future::join_all(futures).await
+    }
+
+    async fn finality_checkpoints(&self, node_url: &Url) -> reqwest::Result<FinalityCheckpoints> {
+        let url = format!("{}eth/v1/beacon/states/head/finality_checkpoints", node_url);
+        self.client
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<BeaconResponse<FinalityCheckpoints>>()
+            .await
+            .map(|body| body.data)
+    }
+
+    /// Fetch the finality checkpoints of the head state from every node.
+    pub async fn finality_checkpoints_all(&self) -> Vec<reqwest::Result<FinalityCheckpoints>> {
+        let futures = self
+            .node_hosts
+            .iter()
+            .map(|node| self.track(node, self.finality_checkpoints(node)));
         futures::future::join_all(futures).await
     }
+
+    /// Subscribe to the given topics on every node, resubscribing when a stream ends. Every node
+    /// sends the same events, so the receiver sees duplicates, but keeps getting events when a
+    /// node goes down. Streams stop once the receiver is dropped.
+    pub fn subscribe_all(&self, topics: &'static [&'static str]) -> mpsc::Receiver<BeaconEvent> {
+        let (sender, receiver) = mpsc::channel(64);
+        for node_url in &self.node_hosts {
+            let node_url = node_url.clone();
+            let beacon_api = self.clone();
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                loop {
+                    match beacon_api.stream_events(&node_url, topics, &sender).await {
+                        Ok(()) => return,
+                        Err(err) => {
+                            warn!(%node_url, "beacon event stream ended, resubscribing: {:#}", err);
+                            tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+                        }
+                    }
+                }
+            });
+        }
+        receiver
+    }
+
+    /// Subscribe to the event stream of a node and forward the given topics to the sender.
+    ///
+    /// Returns when the connection closes, stalls, or the receiver is dropped.
+    pub async fn stream_events(
+        &self,
+        node_url: &Url,
+        topics: &[&str],
+        sender: &mpsc::Sender<BeaconEvent>,
+    ) -> anyhow::Result<()> {
+        let url = format!("{}eth/v1/events?topics={}", node_url, topics.join(","));
+        let mut res = self
+            .client
+            .get(&url)
+            .header("Accept", "text/event-stream")
+            .send()
+            .await?
+            .error_for_status()?;
+
+        debug!(%node_url, ?topics, "subscribed to beacon events");
+
+        let mut buffer = String::new();
+        loop {
+            let chunk = timeout(EVENT_STREAM_IDLE_TIMEOUT, res.chunk())
+                .await
+                .context("beacon event stream stalled")??;
+
+            let Some(chunk) = chunk else {
+                return Err(anyhow!("beacon event stream closed by {}", node_url));
+            };
+
+            // Normalize line endings over the whole buffer, a \r\n may be split across chunks.
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+            if buffer.contains("\r\n") {
+                buffer = buffer.replace("\r\n", "\n");
+            }
+
+            for (event, data) in drain_sse_events(&mut buffer) {
+                match BeaconEvent::parse(&event, &data) {
+                    Ok(Some(event)) => {
+                        if sender.send(event).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+                    Ok(None) => debug!(event, "ignoring unknown beacon event"),
+                    Err(err) => warn!(event, data, "failed to parse beacon event: {}", err),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock::{BlockReply, MockBeaconNode, SyncReply};
+
+    #[test]
+    fn test_drain_sse_events() {
+        let mut buffer = String::from(
+            "event: head\ndata: {\"slot\":\"10\", \"block\":\"0x9a\"}\n\n: keep-alive\n\nevent: block\ndata: {\"slot\"",
+        );
+
+        let events = drain_sse_events(&mut buffer);
+
+        assert_eq!(
+            events,
+            vec![(
+                "head".to_string(),
+                "{\"slot\":\"10\", \"block\":\"0x9a\"}".to_string()
+            )]
+        );
+        assert_eq!(buffer, "event: block\ndata: {\"slot\"");
+    }
+
+    #[test]
+    fn test_parse_beacon_event() {
+        assert_eq!(
+            BeaconEvent::parse("block", r#"{"slot":"10","block":"0x9a"}"#).unwrap(),
+            Some(BeaconEvent::Block { slot: 10 })
+        );
+        assert_eq!(
+            BeaconEvent::parse(
+                "chain_reorg",
+                r#"{"slot":"200","depth":"2","old_head_block":"0x9a","new_head_block":"0x76"}"#
+            )
+            .unwrap(),
+            Some(BeaconEvent::ChainReorg {
+                slot: 200,
+                depth: 2,
+                old_head_block: "0x9a".to_string(),
+                new_head_block: "0x76".to_string(),
+            })
+        );
+        assert_eq!(BeaconEvent::parse("attestation", "{}").unwrap(), None);
+    }
+
+    fn beacon_api(nodes: &[&MockBeaconNode]) -> BeaconApi {
+        BeaconApi::new(&nodes.iter().map(|node| node.url()).collect::<Vec<_>>())
+    }
+
+    #[tokio::test]
+    async fn test_block_by_slot_any_prefers_blocks() {
+        let failing = MockBeaconNode::start();
+        failing.set_block_reply(10, BlockReply::Error(StatusCode::INTERNAL_SERVER_ERROR));
+        let missing = MockBeaconNode::start();
+        // The node with the block answers last, we still wait for it.
+        let slow = MockBeaconNode::start();
+        slow.set_block(10, "0x9a", 1000);
+        slow.set_delay(Duration::from_millis(50));
+
+        let payload = beacon_api(&[&failing, &missing, &slow])
+            .block_by_slot_any(10)
+            .await
+            .unwrap()
+            .unwrap()
+            .execution_payload;
+        assert_eq!(payload.block_hash, "0x9a");
+        assert_eq!(payload.block_number, 1000);
+    }
+
+    #[tokio::test]
+    async fn test_block_by_slot_any_prefers_not_found_over_errors() {
+        let failing = MockBeaconNode::start();
+        failing.set_block_reply(10, BlockReply::Error(StatusCode::INTERNAL_SERVER_ERROR));
+        let missing = MockBeaconNode::start();
+
+        let block = beacon_api(&[&failing, &missing])
+            .block_by_slot_any(10)
+            .await
+            .unwrap();
+        assert!(block.is_none());
+        assert_eq!(failing.block_requests(10), 1);
+        assert_eq!(missing.block_requests(10), 1);
+    }
+
+    #[tokio::test]
+    async fn test_block_by_slot_any_returns_first_error() {
+        let first = MockBeaconNode::start();
+        first.set_block_reply(10, BlockReply::Error(StatusCode::INTERNAL_SERVER_ERROR));
+        let second = MockBeaconNode::start();
+        second.set_block_reply(10, BlockReply::Error(StatusCode::SERVICE_UNAVAILABLE));
+
+        let err = beacon_api(&[&first, &second])
+            .block_by_slot_any(10)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("500"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_block_by_slot_decodes_ssz() {
+        let node = MockBeaconNode::start();
+        node.set_block_reply(
+            8626178,
+            BlockReply::Ssz {
+                version: "deneb".to_string(),
+                bytes: std::fs::read("fixtures/blocks/deneb.ssz").unwrap(),
+            },
+        );
+        let json = std::fs::read("fixtures/blocks/deneb.json").unwrap();
+
+        let block = beacon_api(&[&node])
+            .block_by_slot_any(8626178)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(block, block::decode_json(&json).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_validator_index_fails_over() {
+        let down = MockBeaconNode::start();
+        down.set_failure(Some(StatusCode::BAD_GATEWAY));
+        let up = MockBeaconNode::start();
+        up.set_validator("0xa1", "42");
+        let api = beacon_api(&[&down, &up]);
+
+        // Once a node failed it's asked last.
+        api.refresh_sync_status().await;
+        let index = api.validator_index(&"0xa1".to_string()).await.unwrap();
+        assert_eq!(index.as_deref(), Some("42"));
+        assert_eq!(down.validator_requests(), 0);
+        assert_eq!(api.node_health()[0].1.problem(), Some("unreachable"));
+
+        // When the preferred node fails, the next one answers.
+        down.set_failure(None);
+        up.set_failure(Some(StatusCode::BAD_GATEWAY));
+        let index = api.validator_index(&"0xa1".to_string()).await.unwrap();
+        assert!(index.is_none());
+        assert_eq!(up.validator_requests(), 2);
+        assert_eq!(down.validator_requests(), 1);
+
+        down.set_failure(Some(StatusCode::BAD_GATEWAY));
+        assert!(api.validator_index(&"0xa1".to_string()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failed_node_is_retried() {
+        let flaky = MockBeaconNode::start();
+        flaky.set_failure(Some(StatusCode::BAD_GATEWAY));
+        flaky.set_validator("0xa1", "42");
+        let up = MockBeaconNode::start();
+        up.set_validator("0xa1", "42");
+        let api = beacon_api(&[&flaky, &up]);
+
+        api.refresh_sync_status().await;
+        assert_eq!(api.ranked_hosts(), vec![up.url(), flaky.url()]);
+
+        // Once the failure is old enough the node is asked first again, and failing once more
+        // puts it back last.
+        let backdate = |api: &BeaconApi| {
+            api.health
+                .lock()
+                .unwrap()
+                .get_mut(&flaky.url())
+                .unwrap()
+                .failed_at = Some(Instant::now() - RETRY_FAILED_NODE_AFTER);
+        };
+        backdate(&api);
+        assert_eq!(api.ranked_hosts(), vec![flaky.url(), up.url()]);
+        api.validator_index(&"0xa1".to_string()).await.unwrap();
+        assert_eq!(flaky.validator_requests(), 1);
+        assert_eq!(api.ranked_hosts(), vec![up.url(), flaky.url()]);
+
+        // After it recovered, it's asked first until it looks healthy again.
+        backdate(&api);
+        flaky.set_failure(None);
+        while !api.node_health()[0].1.is_healthy() {
+            api.validator_index(&"0xa1".to_string()).await.unwrap();
+        }
+        assert_eq!(up.validator_requests(), 1);
+        assert_eq!(api.node_health()[0].1.problem(), None);
+    }
+
+    #[tokio::test]
+    async fn test_proposer_duties_fail_over() {
+        let down = MockBeaconNode::start();
+        down.set_failure(Some(StatusCode::SERVICE_UNAVAILABLE));
+        let up = MockBeaconNode::start();
+        up.set_proposer_duties(2, &[(64, 7, "0x8a"), (65, 9, "0x9b")]);
+
+        let api = beacon_api(&[&down, &up]);
+
+        // Whichever node is asked first, the duties come from the one that is up.
+        let duties = api.proposer_duties(2).await.unwrap();
+        assert_eq!(
+            duties,
+            vec![
+                ProposerDuty {
+                    pubkey: "0x8a".to_string(),
+                    validator_index: 7,
+                    slot: 64,
+                },
+                ProposerDuty {
+                    pubkey: "0x9b".to_string(),
+                    validator_index: 9,
+                    slot: 65,
+                },
+            ]
+        );
+        // Nodes without duties for an epoch fail rather than return an empty list.
+        assert!(api.proposer_duties(3).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ranked_hosts_prefers_synced_nodes() {
+        let syncing = MockBeaconNode::start();
+        syncing.set_sync_replies([SyncReply::Syncing]);
+        let synced = MockBeaconNode::start();
+        let api = beacon_api(&[&syncing, &synced]);
+
+        api.refresh_sync_status().await;
+
+        assert_eq!(api.ranked_hosts(), vec![synced.url(), syncing.url()]);
+        let health = api.node_health();
+        assert_eq!(health[0].1.problem(), Some("syncing"));
+        assert_eq!(health[1].1.problem(), None);
+    }
 }
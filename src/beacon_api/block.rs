@@ -0,0 +1,415 @@
+//! Beacon blocks from `eth/v2/beacon/blocks`, for the forks which carry an execution payload. Nodes
+//! answer with JSON or SSZ, both decode into the same `BeaconBlock`. We only keep what monitors
+//! use, lists we don't need are reduced to their length.
+
+use anyhow::{anyhow, bail, Context};
+use serde::Deserialize;
+
+use crate::chain_time::Fork;
+
+use super::parse_i64_from_string;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionPayload {
+    pub parent_hash: String,
+    pub fee_recipient: String,
+    pub block_hash: String,
+    pub block_number: i64,
+    pub gas_limit: i64,
+    pub gas_used: i64,
+    pub timestamp: i64,
+    pub base_fee_per_gas: u128,
+    pub extra_data: String,
+    pub transaction_count: usize,
+    /// Capella and later.
+    pub withdrawal_count: Option<usize>,
+    /// Deneb and later.
+    pub blob_gas_used: Option<i64>,
+    /// Deneb and later.
+    pub excess_blob_gas: Option<i64>,
+}
+
+/// Requests from the execution layer to the consensus layer, Electra and later.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionRequests {
+    pub deposits: usize,
+    pub withdrawals: usize,
+    pub consolidations: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BeaconBlock {
+    pub fork: Fork,
+    pub slot: i64,
+    pub proposer_index: i64,
+    /// Hex encoded, as sent by the node.
+    pub graffiti: String,
+    pub execution_payload: ExecutionPayload,
+    /// Deneb and later.
+    pub blob_kzg_commitment_count: Option<usize>,
+    pub execution_requests: Option<ExecutionRequests>,
+}
+
+impl BeaconBlock {
+    /// Graffiti as text, without the zero padding. None if it isn't valid UTF-8.
+    pub fn graffiti_text(&self) -> Option<String> {
+        let bytes = hex::decode(self.graffiti.trim_start_matches("0x")).ok()?;
+        let text = String::from_utf8(bytes).ok()?;
+        Some(text.trim_end_matches('\0').to_string())
+    }
+}
+
+fn parse_fork(version: &str) -> anyhow::Result<Fork> {
+    let fork = version.to_lowercase().parse()?;
+    if fork < Fork::Bellatrix {
+        bail!("{} blocks have no execution payload", fork);
+    }
+    Ok(fork)
+}
+
+fn parse_optional_i64_from_string<'de, D>(deserializer: D) -> Result<Option<i64>, D::Error>
+where
+    D: serde::de::Deserializer<'de>,
+{
+    let s: Option<String> = Deserialize::deserialize(deserializer)?;
+    s.map(|s| s.parse::<i64>().map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+fn parse_u128_from_string<'de, D>(deserializer: D) -> Result<u128, D::Error>
+where
+    D: serde::de::Deserializer<'de>,
+{
+    let s: String = Deserialize::deserialize(deserializer)?;
+    s.parse::<u128>().map_err(serde::de::Error::custom)
+}
+
+#[derive(Deserialize)]
+struct JsonExecutionPayload {
+    parent_hash: String,
+    fee_recipient: String,
+    block_hash: String,
+    #[serde(deserialize_with = "parse_i64_from_string")]
+    block_number: i64,
+    #[serde(deserialize_with = "parse_i64_from_string")]
+    gas_limit: i64,
+    #[serde(deserialize_with = "parse_i64_from_string")]
+    gas_used: i64,
+    #[serde(deserialize_with = "parse_i64_from_string")]
+    timestamp: i64,
+    #[serde(deserialize_with = "parse_u128_from_string")]
+    base_fee_per_gas: u128,
+    extra_data: String,
+    transactions: Vec<String>,
+    withdrawals: Option<Vec<serde_json::Value>>,
+    #[serde(default, deserialize_with = "parse_optional_i64_from_string")]
+    blob_gas_used: Option<i64>,
+    #[serde(default, deserialize_with = "parse_optional_i64_from_string")]
+    excess_blob_gas: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct JsonExecutionRequests {
+    deposits: Vec<serde_json::Value>,
+    withdrawals: Vec<serde_json::Value>,
+    consolidations: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct JsonBlockBody {
+    graffiti: String,
+    execution_payload: JsonExecutionPayload,
+    blob_kzg_commitments: Option<Vec<String>>,
+    execution_requests: Option<JsonExecutionRequests>,
+}
+
+#[derive(Deserialize)]
+struct JsonBlockMessage {
+    #[serde(deserialize_with = "parse_i64_from_string")]
+    slot: i64,
+    #[serde(deserialize_with = "parse_i64_from_string")]
+    proposer_index: i64,
+    body: JsonBlockBody,
+}
+
+#[derive(Deserialize)]
+struct JsonSignedBlock {
+    message: JsonBlockMessage,
+}
+
+#[derive(Deserialize)]
+struct JsonBlockResponse {
+    version: String,
+    data: JsonSignedBlock,
+}
+
+pub fn decode_json(bytes: &[u8]) -> anyhow::Result<BeaconBlock> {
+    let response: JsonBlockResponse =
+        serde_json::from_slice(bytes).context("failed to decode json block")?;
+    let fork = parse_fork(&response.version)?;
+    let message = response.data.message;
+    let payload = message.body.execution_payload;
+
+    Ok(BeaconBlock {
+        fork,
+        slot: message.slot,
+        proposer_index: message.proposer_index,
+        graffiti: message.body.graffiti,
+        execution_payload: ExecutionPayload {
+            parent_hash: payload.parent_hash,
+            fee_recipient: payload.fee_recipient,
+            block_hash: payload.block_hash,
+            block_number: payload.block_number,
+            gas_limit: payload.gas_limit,
+            gas_used: payload.gas_used,
+            timestamp: payload.timestamp,
+            base_fee_per_gas: payload.base_fee_per_gas,
+            extra_data: payload.extra_data,
+            transaction_count: payload.transactions.len(),
+            withdrawal_count: payload.withdrawals.map(|withdrawals| withdrawals.len()),
+            blob_gas_used: payload.blob_gas_used,
+            excess_blob_gas: payload.excess_blob_gas,
+        },
+        blob_kzg_commitment_count: message
+            .body
+            .blob_kzg_commitments
+            .map(|commitments| commitments.len()),
+        execution_requests: message
+            .body
+            .execution_requests
+            .map(|requests| ExecutionRequests {
+                deposits: requests.deposits.len(),
+                withdrawals: requests.withdrawals.len(),
+                consolidations: requests.consolidations.len(),
+            }),
+    })
+}
+
+/// An SSZ encoded container. Fixed size fields are read at their position, variable size fields
+/// through the offsets in the fixed part.
+struct Ssz<'a>(&'a [u8]);
+
+impl<'a> Ssz<'a> {
+    fn bytes(&self, start: usize, len: usize) -> anyhow::Result<&'a [u8]> {
+        self.0
+            .get(start..start + len)
+            .ok_or_else(|| anyhow!("ssz field at {} out of bounds", start))
+    }
+
+    fn hex(&self, start: usize, len: usize) -> anyhow::Result<String> {
+        Ok(format!("0x{}", hex::encode(self.bytes(start, len)?)))
+    }
+
+    fn u64(&self, start: usize) -> anyhow::Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(start, 8)?.try_into()?))
+    }
+
+    fn i64(&self, start: usize) -> anyhow::Result<i64> {
+        Ok(self.u64(start)?.try_into()?)
+    }
+
+    fn offset(&self, start: usize) -> anyhow::Result<usize> {
+        Ok(u32::from_le_bytes(self.bytes(start, 4)?.try_into()?) as usize)
+    }
+
+    /// The variable size fields whose offsets are at `offset_positions`, in order. Each field
+    /// runs up to the next one, the last up to the end of the container.
+    fn variable_fields(&self, offset_positions: &[usize]) -> anyhow::Result<Vec<Ssz<'a>>> {
+        let offsets = offset_positions
+            .iter()
+            .map(|position| self.offset(*position))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        offsets
+            .iter()
+            .enumerate()
+            .map(|(i, start)| {
+                let end = offsets.get(i + 1).copied().unwrap_or(self.0.len());
+                self.0
+                    .get(*start..end)
+                    .map(Ssz)
+                    .ok_or_else(|| anyhow!("invalid ssz offsets {}..{}", start, end))
+            })
+            .collect()
+    }
+
+    /// Length of a list of fixed size items.
+    fn fixed_list_len(&self, item_size: usize) -> anyhow::Result<usize> {
+        if !self.0.len().is_multiple_of(item_size) {
+            bail!(
+                "ssz list of {} bytes is not a multiple of {}",
+                self.0.len(),
+                item_size
+            );
+        }
+        Ok(self.0.len() / item_size)
+    }
+
+    /// Length of a list of variable size items, the first offset tells how many there are.
+    fn variable_list_len(&self) -> anyhow::Result<usize> {
+        if self.0.is_empty() {
+            return Ok(0);
+        }
+        Ok(self.offset(0)? / 4)
+    }
+}
+
+const WITHDRAWAL_SIZE: usize = 44;
+const KZG_COMMITMENT_SIZE: usize = 48;
+const DEPOSIT_REQUEST_SIZE: usize = 192;
+const WITHDRAWAL_REQUEST_SIZE: usize = 76;
+const CONSOLIDATION_REQUEST_SIZE: usize = 116;
+
+fn decode_ssz_payload(payload: Ssz, fork: Fork) -> anyhow::Result<ExecutionPayload> {
+    let mut offset_positions = vec![436, 504];
+    if fork >= Fork::Capella {
+        offset_positions.push(508);
+    }
+    let variable = payload.variable_fields(&offset_positions)?;
+
+    let base_fee = payload.bytes(440, 32)?;
+    if base_fee[16..].iter().any(|byte| *byte != 0) {
+        bail!("base fee per gas does not fit in 128 bits");
+    }
+
+    Ok(ExecutionPayload {
+        parent_hash: payload.hex(0, 32)?,
+        fee_recipient: payload.hex(32, 20)?,
+        block_hash: payload.hex(472, 32)?,
+        block_number: payload.i64(404)?,
+        gas_limit: payload.i64(412)?,
+        gas_used: payload.i64(420)?,
+        timestamp: payload.i64(428)?,
+        base_fee_per_gas: u128::from_le_bytes(base_fee[..16].try_into()?),
+        extra_data: format!("0x{}", hex::encode(variable[0].0)),
+        transaction_count: variable[1].variable_list_len()?,
+        withdrawal_count: variable
+            .get(2)
+            .map(|withdrawals| withdrawals.fixed_list_len(WITHDRAWAL_SIZE))
+            .transpose()?,
+        blob_gas_used: (fork >= Fork::Deneb)
+            .then(|| payload.i64(512))
+            .transpose()?,
+        excess_blob_gas: (fork >= Fork::Deneb)
+            .then(|| payload.i64(520))
+            .transpose()?,
+    })
+}
+
+/// Decode a `SignedBeaconBlock`. SSZ doesn't say which fork it's from, nodes send that in the
+/// `Eth-Consensus-Version` header.
+pub fn decode_ssz(version: &str, bytes: &[u8]) -> anyhow::Result<BeaconBlock> {
+    let fork = parse_fork(version)?;
+
+    let signed_block = Ssz(bytes);
+    let block = Ssz(bytes
+        .get(signed_block.offset(0)?..)
+        .context("invalid signed block offset")?);
+    let body = Ssz(block
+        .0
+        .get(block.offset(80)?..)
+        .context("invalid block body offset")?);
+
+    // proposer_slashings, attester_slashings, attestations, deposits, voluntary_exits and the
+    // execution payload, then one more list for each later fork.
+    let mut offset_positions = vec![200, 204, 208, 212, 216, 380];
+    if fork >= Fork::Capella {
+        offset_positions.push(384);
+    }
+    if fork >= Fork::Deneb {
+        offset_positions.push(388);
+    }
+    if fork >= Fork::Electra {
+        offset_positions.push(392);
+    }
+    let mut variable = body.variable_fields(&offset_positions)?.into_iter().skip(5);
+    let payload = variable.next().context("missing execution payload")?;
+    let _bls_to_execution_changes = variable.next();
+    let blob_kzg_commitments = variable.next();
+    let execution_requests = variable.next();
+
+    Ok(BeaconBlock {
+        fork,
+        slot: block.i64(0)?,
+        proposer_index: block.i64(8)?,
+        graffiti: body.hex(168, 32)?,
+        execution_payload: decode_ssz_payload(payload, fork)?,
+        blob_kzg_commitment_count: blob_kzg_commitments
+            .map(|commitments| commitments.fixed_list_len(KZG_COMMITMENT_SIZE))
+            .transpose()?,
+        execution_requests: execution_requests
+            .map(|requests| -> anyhow::Result<_> {
+                let lists = requests.variable_fields(&[0, 4, 8])?;
+                Ok(ExecutionRequests {
+                    deposits: lists[0].fixed_list_len(DEPOSIT_REQUEST_SIZE)?,
+                    withdrawals: lists[1].fixed_list_len(WITHDRAWAL_REQUEST_SIZE)?,
+                    consolidations: lists[2].fixed_list_len(CONSOLIDATION_REQUEST_SIZE)?,
+                })
+            })
+            .transpose()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(fork: &str) -> (BeaconBlock, BeaconBlock) {
+        let json = std::fs::read(format!("fixtures/blocks/{}.json", fork)).unwrap();
+        let ssz = std::fs::read(format!("fixtures/blocks/{}.ssz", fork)).unwrap();
+        (decode_json(&json).unwrap(), decode_ssz(fork, &ssz).unwrap())
+    }
+
+    #[test]
+    fn test_json_and_ssz_agree() {
+        for fork in ["bellatrix", "capella", "deneb", "electra"] {
+            let (json, ssz) = fixture(fork);
+            assert_eq!(json, ssz, "{} blocks differ", fork);
+            assert_eq!(json.fork.to_string(), fork);
+            assert_eq!(
+                json.graffiti_text().unwrap(),
+                format!("ultrasound {}", fork)
+            );
+            assert_eq!(json.execution_payload.transaction_count, 3);
+        }
+    }
+
+    #[test]
+    fn test_fork_specific_fields() {
+        let (bellatrix, _) = fixture("bellatrix");
+        assert_eq!(bellatrix.slot, 4700013);
+        assert_eq!(bellatrix.execution_payload.block_number, 15537394);
+        assert_eq!(bellatrix.execution_payload.withdrawal_count, None);
+        assert_eq!(bellatrix.blob_kzg_commitment_count, None);
+
+        let (capella, _) = fixture("capella");
+        assert_eq!(capella.execution_payload.withdrawal_count, Some(2));
+        assert_eq!(capella.execution_payload.blob_gas_used, None);
+
+        let (deneb, _) = fixture("deneb");
+        assert_eq!(deneb.execution_payload.blob_gas_used, Some(262144));
+        assert_eq!(deneb.execution_payload.excess_blob_gas, Some(79429632));
+        assert_eq!(deneb.blob_kzg_commitment_count, Some(2));
+        assert_eq!(deneb.execution_requests, None);
+
+        let (electra, _) = fixture("electra");
+        assert_eq!(electra.proposer_index, 1300500);
+        assert_eq!(electra.execution_payload.base_fee_per_gas, 7000000003);
+        assert_eq!(
+            electra.execution_requests,
+            Some(ExecutionRequests {
+                deposits: 1,
+                withdrawals: 2,
+                consolidations: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_reject_invalid_blocks() {
+        let ssz = std::fs::read("fixtures/blocks/deneb.ssz").unwrap();
+        assert!(decode_ssz("altair", &ssz).is_err());
+        assert!(decode_ssz("deneb", &ssz[..ssz.len() / 2]).is_err());
+        // Decoding with the wrong fork reads the offsets wrong.
+        assert!(decode_ssz("electra", &ssz).is_err());
+    }
+}
@@ -0,0 +1,385 @@
+//! A scriptable stand-in for a beacon node, for tests which go through `BeaconApi`. Each mock
+//! serves blocks by slot or root, headers, validators, proposer duties, sync status, errors and delays as set up by the
+//! test, and counts the requests it receives.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use reqwest::Url;
+use serde_json::json;
+
+#[derive(Debug, Clone)]
+pub enum BlockReply {
+    Block {
+        block_hash: String,
+        block_number: i64,
+    },
+    /// A block as SSZ, like nodes send when asked for `application/octet-stream`.
+    Ssz {
+        version: String,
+        bytes: Vec<u8>,
+    },
+    NotFound,
+    Error(StatusCode),
+}
+
+#[derive(Debug, Clone)]
+pub enum SyncReply {
+    Synced,
+    Syncing,
+    Error(StatusCode),
+}
+
+/// A block known by its root, with its place in the chain.
+#[derive(Debug, Clone)]
+struct RootBlock {
+    slot: i64,
+    parent_root: String,
+    block_hash: String,
+}
+
+#[derive(Default)]
+struct Script {
+    blocks: HashMap<i64, BlockReply>,
+    blocks_by_root: HashMap<String, RootBlock>,
+    /// Validator indices by pubkey.
+    validators: HashMap<String, String>,
+    /// (slot, validator index, pubkey) per epoch.
+    proposer_duties: HashMap<i64, Vec<(i64, i64, String)>>,
+    /// Replies for the next sync status requests, the last one repeats.
+    sync_replies: VecDeque<SyncReply>,
+    delay: Duration,
+    /// Status every endpoint fails with, for a node that is down.
+    failure: Option<StatusCode>,
+    block_requests: HashMap<i64, usize>,
+    validator_requests: usize,
+    sync_requests: usize,
+}
+
+type SharedScript = Arc<Mutex<Script>>;
+
+pub struct MockBeaconNode {
+    addr: SocketAddr,
+    script: SharedScript,
+}
+
+impl MockBeaconNode {
+    /// Start a node on a random local port. It serves 404s for every block and reports being
+    /// synced until told otherwise.
+    pub fn start() -> Self {
+        let script = SharedScript::default();
+        let app = Router::new()
+            .route("/eth/v2/beacon/blocks/:block_id", get(block))
+            .route("/eth/v1/beacon/headers/:block_id", get(block_header))
+            .route(
+                "/eth/v1/beacon/states/head/validators/:pubkey",
+                get(validator),
+            )
+            .route(
+                "/eth/v1/validator/duties/proposer/:epoch",
+                get(proposer_duties),
+            )
+            .route("/eth/v1/node/syncing", get(syncing))
+            .with_state(script.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service());
+        tokio::spawn(server);
+
+        Self { addr, script }
+    }
+
+    /// Url with a trailing slash, like the configured consensus nodes.
+    pub fn url(&self) -> Url {
+        format!("http://{}/", self.addr).parse().unwrap()
+    }
+
+    pub fn set_block(&self, slot: i64, block_hash: &str, block_number: i64) {
+        self.set_block_reply(
+            slot,
+            BlockReply::Block {
+                block_hash: block_hash.to_string(),
+                block_number,
+            },
+        );
+    }
+
+    pub fn set_block_reply(&self, slot: i64, reply: BlockReply) {
+        self.script.lock().unwrap().blocks.insert(slot, reply);
+    }
+
+    /// Serve a block and its header by root. Doesn't change what the slot serves, like a node
+    /// which has seen a block on another fork.
+    pub fn set_block_by_root(&self, root: &str, slot: i64, parent_root: &str, block_hash: &str) {
+        self.script.lock().unwrap().blocks_by_root.insert(
+            root.to_string(),
+            RootBlock {
+                slot,
+                parent_root: parent_root.to_string(),
+                block_hash: block_hash.to_string(),
+            },
+        );
+    }
+
+    pub fn set_validator(&self, pubkey: &str, index: &str) {
+        self.script
+            .lock()
+            .unwrap()
+            .validators
+            .insert(pubkey.to_string(), index.to_string());
+    }
+
+    /// Proposers of an epoch as (slot, validator index, pubkey). Epochs without duties fail like
+    /// epochs too far ahead do on a real node.
+    pub fn set_proposer_duties(&self, epoch: i64, duties: &[(i64, i64, &str)]) {
+        self.script.lock().unwrap().proposer_duties.insert(
+            epoch,
+            duties
+                .iter()
+                .map(|(slot, index, pubkey)| (*slot, *index, pubkey.to_string()))
+                .collect(),
+        );
+    }
+
+    /// Reply to the next sync status requests in order, repeating the last reply.
+    pub fn set_sync_replies(&self, replies: impl IntoIterator<Item = SyncReply>) {
+        self.script.lock().unwrap().sync_replies = replies.into_iter().collect();
+    }
+
+    /// Delay every response by `delay`.
+    pub fn set_delay(&self, delay: Duration) {
+        self.script.lock().unwrap().delay = delay;
+    }
+
+    /// Fail every request with `status`, or serve the script again for `None`.
+    pub fn set_failure(&self, status: Option<StatusCode>) {
+        self.script.lock().unwrap().failure = status;
+    }
+
+    pub fn block_requests(&self, slot: i64) -> usize {
+        let script = self.script.lock().unwrap();
+        script.block_requests.get(&slot).copied().unwrap_or(0)
+    }
+
+    pub fn validator_requests(&self) -> usize {
+        self.script.lock().unwrap().validator_requests
+    }
+
+    pub fn sync_requests(&self) -> usize {
+        self.script.lock().unwrap().sync_requests
+    }
+}
+
+async fn block(State(script): State<SharedScript>, Path(block_id): Path<String>) -> Response {
+    let (slot, reply, delay) = {
+        let mut script = script.lock().unwrap();
+        let (slot, reply) = match block_id.parse::<i64>() {
+            Ok(slot) => {
+                *script.block_requests.entry(slot).or_default() += 1;
+                let reply = script.blocks.get(&slot).cloned();
+                (slot, reply)
+            }
+            Err(_) => match script.blocks_by_root.get(&block_id) {
+                Some(block) => (
+                    block.slot,
+                    Some(BlockReply::Block {
+                        block_hash: block.block_hash.clone(),
+                        block_number: block.slot,
+                    }),
+                ),
+                None => (0, None),
+            },
+        };
+        let reply = match script.failure {
+            Some(status) => BlockReply::Error(status),
+            None => reply.unwrap_or(BlockReply::NotFound),
+        };
+        (slot, reply, script.delay)
+    };
+    tokio::time::sleep(delay).await;
+
+    match reply {
+        BlockReply::Block {
+            block_hash,
+            block_number,
+        } => Json(json!({
+            "version": "bellatrix",
+            "data": {
+                "message": {
+                    "slot": slot.to_string(),
+                    "proposer_index": "1",
+                    "body": {
+                        "graffiti": format!("0x{}", "00".repeat(32)),
+                        "execution_payload": {
+                            "parent_hash": format!("0x{}", "00".repeat(32)),
+                            "fee_recipient": format!("0x{}", "00".repeat(20)),
+                            "block_hash": block_hash,
+                            "block_number": block_number.to_string(),
+                            "gas_limit": "30000000",
+                            "gas_used": "0",
+                            "timestamp": "0",
+                            "base_fee_per_gas": "7",
+                            "extra_data": "0x",
+                            "transactions": [],
+                        }
+                    }
+                }
+            }
+        }))
+        .into_response(),
+        BlockReply::Ssz { version, bytes } => (
+            [
+                (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+                (
+                    header::HeaderName::from_static("eth-consensus-version"),
+                    version,
+                ),
+            ],
+            bytes,
+        )
+            .into_response(),
+        BlockReply::NotFound => StatusCode::NOT_FOUND.into_response(),
+        BlockReply::Error(status) => status.into_response(),
+    }
+}
+
+async fn block_header(
+    State(script): State<SharedScript>,
+    Path(block_id): Path<String>,
+) -> Response {
+    let (reply, delay) = {
+        let script = script.lock().unwrap();
+        let reply = match script.failure {
+            Some(status) => Err(status),
+            None => script
+                .blocks_by_root
+                .get(&block_id)
+                .cloned()
+                .ok_or(StatusCode::NOT_FOUND),
+        };
+        (reply, script.delay)
+    };
+    tokio::time::sleep(delay).await;
+
+    match reply {
+        Ok(block) => Json(json!({
+            "data": {
+                "root": block_id,
+                "canonical": true,
+                "header": {
+                    "message": {
+                        "slot": block.slot.to_string(),
+                        "proposer_index": "1",
+                        "parent_root": block.parent_root,
+                    }
+                }
+            }
+        }))
+        .into_response(),
+        Err(status) => status.into_response(),
+    }
+}
+
+async fn validator(State(script): State<SharedScript>, Path(pubkey): Path<String>) -> Response {
+    let (reply, delay) = {
+        let mut script = script.lock().unwrap();
+        script.validator_requests += 1;
+        let reply = match script.failure {
+            Some(status) => Err(status),
+            None => script
+                .validators
+                .get(&pubkey)
+                .cloned()
+                .ok_or(StatusCode::NOT_FOUND),
+        };
+        (reply, script.delay)
+    };
+    tokio::time::sleep(delay).await;
+
+    match reply {
+        Ok(index) => Json(json!({
+            "data": {
+                "index": index,
+                "status": "active_ongoing",
+            }
+        }))
+        .into_response(),
+        Err(status) => status.into_response(),
+    }
+}
+
+async fn proposer_duties(State(script): State<SharedScript>, Path(epoch): Path<i64>) -> Response {
+    let (reply, delay) = {
+        let script = script.lock().unwrap();
+        let reply = match script.failure {
+            Some(status) => Err(status),
+            None => script
+                .proposer_duties
+                .get(&epoch)
+                .cloned()
+                .ok_or(StatusCode::BAD_REQUEST),
+        };
+        (reply, script.delay)
+    };
+    tokio::time::sleep(delay).await;
+
+    match reply {
+        Ok(duties) => Json(json!({
+            "dependent_root": format!("0x{}", "00".repeat(32)),
+            "execution_optimistic": false,
+            "data": duties
+                .iter()
+                .map(|(slot, index, pubkey)| json!({
+                    "pubkey": pubkey,
+                    "validator_index": index.to_string(),
+                    "slot": slot.to_string(),
+                }))
+                .collect::<Vec<_>>(),
+        }))
+        .into_response(),
+        Err(status) => status.into_response(),
+    }
+}
+
+async fn syncing(State(script): State<SharedScript>) -> Response {
+    let (reply, delay) = {
+        let mut script = script.lock().unwrap();
+        script.sync_requests += 1;
+        let reply = if let Some(status) = script.failure {
+            Some(SyncReply::Error(status))
+        } else if script.sync_replies.len() > 1 {
+            script.sync_replies.pop_front()
+        } else {
+            script.sync_replies.front().cloned()
+        };
+        (reply.unwrap_or(SyncReply::Synced), script.delay)
+    };
+    tokio::time::sleep(delay).await;
+
+    match reply {
+        SyncReply::Synced | SyncReply::Syncing => Json(json!({
+            "data": {
+                "head_slot": "100",
+                "sync_distance": "0",
+                "is_syncing": matches!(reply, SyncReply::Syncing),
+            }
+        }))
+        .into_response(),
+        SyncReply::Error(status) => status.into_response(),
+    }
+}
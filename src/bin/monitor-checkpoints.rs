@@ -0,0 +1,15 @@
+use std::env;
+
+use anyhow::Result;
+
+/// Usage: monitor-checkpoints list
+///        monitor-checkpoints history <monitor> [limit]
+///        monitor-checkpoints rewind <monitor> <slot|timestamp>
+///        monitor-checkpoints pause|resume|reset <monitor>
+///
+/// Timestamps are RFC 3339. Monitors pick up changes on their next run.
+#[tokio::main]
+pub async fn main() -> Result<()> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    relay_backend::manage_checkpoints(&args).await
+}
@@ -1,11 +1,12 @@
 use std::env;
 
 use anyhow::Result;
+use relay_backend::{Slot, SlotRange};
 
 #[tokio::main]
 pub async fn main() -> Result<()> {
     let args = env::args().collect::<Vec<_>>();
-    let start = args[1].parse::<i64>()?;
-    let end = args[2].parse::<i64>()?;
-    relay_backend::patch_block_production_interval(start, end).await
+    let start = args[1].parse::<Slot>()?;
+    let end = args[2].parse::<Slot>()?;
+    relay_backend::patch_block_production_interval(SlotRange::new(start, end)).await
 }
@@ -0,0 +1,25 @@
+use std::env;
+
+use anyhow::{bail, Result};
+use relay_backend::ReplayRange;
+
+const USAGE: &str = "usage: replay-inclusion <start> <end> [--alert]";
+
+/// Usage: replay-inclusion <start> <end> [--alert]
+///
+/// Start and end are either slot numbers or RFC 3339 timestamps. Alerts for the misses found are
+/// only sent with `--alert`.
+#[tokio::main]
+pub async fn main() -> Result<()> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let send_alerts = args.iter().any(|arg| arg == "--alert");
+    let [start, end] = args
+        .iter()
+        .filter(|arg| *arg != "--alert")
+        .collect::<Vec<_>>()[..]
+    else {
+        bail!(USAGE);
+    };
+    let range = ReplayRange::parse(start, end)?;
+    relay_backend::replay_inclusion(range, send_alerts).await
+}
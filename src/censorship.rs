@@ -13,7 +13,10 @@ use std::net::SocketAddr;
 use std::process;
 use tracing::{error, info, warn};
 
-use crate::log;
+use crate::{
+    chain_time::{Slot, SlotRange},
+    log,
+};
 
 use self::db::{CensorshipDB, PostgresCensorshipDB};
 use self::env::APP_CONFIG;
@@ -134,17 +137,17 @@ async fn backfill_block_production_data(db: &impl CensorshipDB) -> Result<()> {
     Ok(())
 }
 
-pub async fn patch_block_production_interval(start_slot: i64, end_slot: i64) -> Result<()> {
+pub async fn patch_block_production_interval(slots: SlotRange) -> Result<()> {
     log::init();
 
     let db = PostgresCensorshipDB::new().await?;
-    let mut checkpoints: HashMap<RelayId, i64> = all::<RelayId>()
-        .map(|relay| (relay, end_slot + 1))
+    let mut checkpoints: HashMap<RelayId, Slot> = all::<RelayId>()
+        .map(|relay| (relay, slots.end + 1))
         .collect();
 
     info!(
         "patching block production data from slot {} to {}",
-        start_slot, end_slot
+        slots.start, slots.end
     );
 
     loop {
@@ -170,10 +173,10 @@ pub async fn patch_block_production_interval(start_slot: i64, end_slot: i64) ->
 
         for (relay, payloads) in &all_payloads {
             match payloads.last() {
-                Some(payload) if payload.slot_number <= start_slot => {
+                Some(payload) if payload.slot_number <= slots.start => {
                     info!(
                         "reached start slot {} for {}, removing from checkpoints",
-                        start_slot, &relay
+                        slots.start, &relay
                     );
                     checkpoints.remove(relay);
                 }
@@ -202,7 +205,7 @@ pub async fn patch_block_production_interval(start_slot: i64, end_slot: i64) ->
 
 type BlockProductionBatch = Vec<(RelayId, Vec<DeliveredPayload>)>;
 
-async fn fetch_block_production_batch(end_slot: &Option<i64>) -> Result<BlockProductionBatch> {
+async fn fetch_block_production_batch(end_slot: &Option<Slot>) -> Result<BlockProductionBatch> {
     let futs = all::<RelayId>()
         .map(|relay| async move {
             let mut payloads = relay.fetch_delivered_payloads(end_slot).await?;
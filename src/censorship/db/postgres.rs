@@ -9,7 +9,10 @@ use sqlx::{
 };
 
 use super::CensorshipDB;
-use crate::censorship::{env::APP_CONFIG, relay::DeliveredPayload};
+use crate::{
+    censorship::{env::APP_CONFIG, relay::DeliveredPayload},
+    chain_time::Slot,
+};
 
 pub struct PostgresCensorshipDB {
     pool: Pool<Postgres>,
@@ -33,7 +36,7 @@ impl PostgresCensorshipDB {
 
 #[async_trait]
 impl CensorshipDB for PostgresCensorshipDB {
-    async fn get_block_production_checkpoint(&self) -> Result<Option<i64>> {
+    async fn get_block_production_checkpoint(&self) -> Result<Option<Slot>> {
         sqlx::query_scalar!(
             "
             SELECT slot_number
@@ -44,6 +47,7 @@ impl CensorshipDB for PostgresCensorshipDB {
         )
         .fetch_optional(&self.pool)
         .await
+        .map(|slot_number| slot_number.map(Slot))
         .map_err(Into::into)
     }
 
@@ -69,7 +73,7 @@ impl CensorshipDB for PostgresCensorshipDB {
                   relays = ARRAY (SELECT DISTINCT UNNEST(block_production.relays || $6)),
                   value = $7
                 ",
-                slot_number,
+                slot_number.0,
                 block_number,
                 block_hash,
                 builder_pubkey,
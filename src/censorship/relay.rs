@@ -6,17 +6,19 @@ use enum_iterator::Sequence;
 use reqwest::Url;
 use std::fmt;
 
+use crate::chain_time::Slot;
+
 #[async_trait]
 pub trait RelayApi {
     async fn fetch_delivered_payloads(
         &self,
-        end_slot: &Option<i64>,
+        end_slot: &Option<Slot>,
     ) -> Result<Vec<DeliveredPayload>>;
 }
 
 pub struct DeliveredPayload {
     pub relay_id: RelayId,
-    pub slot_number: i64,
+    pub slot_number: Slot,
     pub block_number: i64,
     pub block_hash: String,
     pub builder_pubkey: String,
@@ -4,6 +4,7 @@ use reqwest::Url;
 use serde::Deserialize;
 
 use super::{DeliveredPayload, RelayApi, RelayId};
+use crate::chain_time::Slot;
 
 #[derive(Deserialize)]
 struct DeliveredPayloadResponse {
@@ -22,12 +23,12 @@ const PAYLOAD_LIMIT: usize = 100;
 impl RelayApi for RelayId {
     async fn fetch_delivered_payloads(
         &self,
-        end_slot: &Option<i64>,
+        end_slot: &Option<Slot>,
     ) -> Result<Vec<DeliveredPayload>> {
         let url: Url = self.clone().into();
         let limit = format!("?limit={}", PAYLOAD_LIMIT);
         let query = end_slot
-            .map(|end_slot| format!("{}&cursor={}", &limit, &end_slot))
+            .map(|end_slot| format!("{}&cursor={}", &limit, end_slot.0))
             .unwrap_or(limit);
 
         let url = format!(
@@ -0,0 +1,332 @@
+//! Slot and epoch arithmetic, shared by all services. Conversions between slots and time go
+//! through a `ChainTime`, which is built for a network rather than read from global config, so any
+//! genesis can be used in tests.
+
+use std::{fmt, ops, str::FromStr};
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::env::Network;
+
+pub const SLOTS_PER_EPOCH: i64 = 32;
+
+#[derive(
+    Debug, Copy, Clone, Default, Eq, Hash, Ord, PartialEq, PartialOrd, Deserialize, Serialize,
+)]
+#[serde(transparent)]
+pub struct Slot(pub i64);
+
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:07}", self.0)
+    }
+}
+
+impl FromStr for Slot {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let slot = s.parse::<i64>()?;
+        Ok(Self(slot))
+    }
+}
+
+impl ops::Add<i64> for Slot {
+    type Output = Self;
+
+    fn add(self, rhs: i64) -> Self::Output {
+        Self(self.0 + rhs)
+    }
+}
+
+impl ops::Sub<i64> for Slot {
+    type Output = Self;
+
+    fn sub(self, rhs: i64) -> Self::Output {
+        Self(self.0 - rhs)
+    }
+}
+
+impl Slot {
+    pub fn epoch(&self) -> Epoch {
+        Epoch(self.0.div_euclid(SLOTS_PER_EPOCH))
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, Hash, Ord, PartialEq, PartialOrd, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Epoch(pub i64);
+
+impl fmt::Display for Epoch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Epoch {
+    pub fn first_slot(&self) -> Slot {
+        Slot(self.0 * SLOTS_PER_EPOCH)
+    }
+
+    pub fn slots(&self) -> SlotRange {
+        SlotRange::new(self.first_slot(), self.first_slot() + (SLOTS_PER_EPOCH - 1))
+    }
+}
+
+/// Slots from `start` up to and including `end`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SlotRange {
+    pub start: Slot,
+    pub end: Slot,
+}
+
+impl SlotRange {
+    pub fn new(start: Slot, end: Slot) -> Self {
+        Self { start, end }
+    }
+
+    pub fn contains(&self, slot: Slot) -> bool {
+        self.start <= slot && slot <= self.end
+    }
+
+    pub fn len(&self) -> usize {
+        (self.end.0 - self.start.0 + 1).max(0) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Slot> {
+        (self.start.0..=self.end.0).map(Slot)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Fork {
+    Phase0,
+    Altair,
+    Bellatrix,
+    Capella,
+    Deneb,
+    Electra,
+    Fulu,
+}
+
+impl fmt::Display for Fork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let str = match self {
+            Fork::Phase0 => "phase0",
+            Fork::Altair => "altair",
+            Fork::Bellatrix => "bellatrix",
+            Fork::Capella => "capella",
+            Fork::Deneb => "deneb",
+            Fork::Electra => "electra",
+            Fork::Fulu => "fulu",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+impl FromStr for Fork {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "phase0" => Ok(Fork::Phase0),
+            "altair" => Ok(Fork::Altair),
+            "bellatrix" => Ok(Fork::Bellatrix),
+            "capella" => Ok(Fork::Capella),
+            "deneb" => Ok(Fork::Deneb),
+            "electra" => Ok(Fork::Electra),
+            "fulu" => Ok(Fork::Fulu),
+            _ => Err(anyhow!("unknown fork: {}", s)),
+        }
+    }
+}
+
+/// Parse a fork schedule like `altair:0,bellatrix:0,capella:10`.
+pub fn parse_fork_schedule(input: &str) -> anyhow::Result<Vec<(Fork, Epoch)>> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (fork, epoch) = entry
+                .split_once(':')
+                .with_context(|| format!("fork schedule entry without epoch: {}", entry))?;
+            Ok((fork.trim().parse()?, Epoch(epoch.trim().parse()?)))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct ChainTime {
+    genesis: DateTime<Utc>,
+    seconds_per_slot: i64,
+    /// Ordered by activation epoch.
+    forks: Vec<(Fork, Epoch)>,
+}
+
+impl ChainTime {
+    pub fn new(
+        genesis: DateTime<Utc>,
+        seconds_per_slot: i64,
+        mut forks: Vec<(Fork, Epoch)>,
+    ) -> Self {
+        forks.sort_by_key(|(_, epoch)| *epoch);
+        Self {
+            genesis,
+            seconds_per_slot,
+            forks,
+        }
+    }
+
+    pub fn for_network(network: &Network) -> Self {
+        Self::new(
+            network.genesis_time,
+            network.seconds_per_slot,
+            network.forks.clone(),
+        )
+    }
+
+    pub fn genesis(&self) -> DateTime<Utc> {
+        self.genesis
+    }
+
+    pub fn slot_duration(&self) -> Duration {
+        Duration::seconds(self.seconds_per_slot)
+    }
+
+    pub fn slot_start(&self, slot: Slot) -> DateTime<Utc> {
+        self.genesis + Duration::seconds(slot.0 * self.seconds_per_slot)
+    }
+
+    /// The slot `at` falls in. Times before genesis are slot 0.
+    pub fn slot_at(&self, at: &DateTime<Utc>) -> Slot {
+        if at < &self.genesis {
+            return Slot(0);
+        }
+        let seconds_since_genesis = at.signed_duration_since(self.genesis).num_seconds();
+        Slot(seconds_since_genesis / self.seconds_per_slot)
+    }
+
+    pub fn current_slot(&self) -> Slot {
+        self.slot_at(&Utc::now())
+    }
+
+    pub fn ms_into_slot(&self, slot: Slot, at: &DateTime<Utc>) -> i64 {
+        at.signed_duration_since(self.slot_start(slot))
+            .num_milliseconds()
+    }
+
+    pub fn fork_at_epoch(&self, epoch: Epoch) -> Fork {
+        self.forks
+            .iter()
+            .take_while(|(_, activation)| *activation <= epoch)
+            .last()
+            .map(|(fork, _)| *fork)
+            .unwrap_or(Fork::Phase0)
+    }
+
+    pub fn fork_at(&self, slot: Slot) -> Fork {
+        self.fork_at_epoch(slot.epoch())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mainnet() -> ChainTime {
+        ChainTime::for_network(&Network::mainnet())
+    }
+
+    #[test]
+    fn test_slot_at() {
+        let chain_time = mainnet();
+        let genesis = chain_time.genesis();
+
+        // Exactly genesis.
+        assert_eq!(chain_time.slot_at(&genesis), Slot(0));
+
+        // 11 seconds after genesis, still slot 0.
+        assert_eq!(
+            chain_time.slot_at(&(genesis + Duration::seconds(11))),
+            Slot(0)
+        );
+
+        // 12 seconds after genesis, slot 1.
+        assert_eq!(
+            chain_time.slot_at(&(genesis + Duration::seconds(12))),
+            Slot(1)
+        );
+
+        // Before genesis, slot 0.
+        assert_eq!(
+            chain_time.slot_at(&(genesis - Duration::seconds(1))),
+            Slot(0)
+        );
+        assert_eq!(
+            chain_time.slot_at(&(genesis - Duration::seconds(13))),
+            Slot(0)
+        );
+    }
+
+    #[test]
+    fn test_slot_start_and_ms_into_slot() {
+        let chain_time = mainnet();
+        let start = chain_time.slot_start(Slot(100));
+        assert_eq!(start, chain_time.genesis() + Duration::seconds(1200));
+        assert_eq!(chain_time.slot_at(&start), Slot(100));
+
+        let at = start + Duration::milliseconds(4150);
+        assert_eq!(chain_time.ms_into_slot(Slot(100), &at), 4150);
+        assert_eq!(chain_time.ms_into_slot(Slot(101), &at), -7850);
+    }
+
+    #[test]
+    fn test_epochs() {
+        assert_eq!(Slot(63).epoch(), Epoch(1));
+        assert_eq!(Slot(64).epoch(), Epoch(2));
+        assert_eq!(Epoch(2).slots(), SlotRange::new(Slot(64), Slot(95)));
+        assert_eq!(Epoch(2).slots().len(), 32);
+    }
+
+    #[test]
+    fn test_slot_range() {
+        let range = SlotRange::new(Slot(11), Slot(12));
+        assert!(range.contains(Slot(11)));
+        assert!(!range.contains(Slot(10)));
+        assert_eq!(range.iter().collect::<Vec<_>>(), vec![Slot(11), Slot(12)]);
+        assert!(SlotRange::new(Slot(12), Slot(11)).is_empty());
+    }
+
+    #[test]
+    fn test_fork_schedule() {
+        let chain_time = mainnet();
+        assert_eq!(chain_time.fork_at(Slot(0)), Fork::Phase0);
+        assert_eq!(
+            chain_time.fork_at(Epoch(194048).first_slot()),
+            Fork::Capella
+        );
+        assert_eq!(
+            chain_time.fork_at(Epoch(194048).first_slot() - 1),
+            Fork::Bellatrix
+        );
+
+        let devnet = ChainTime::new(
+            "2025-01-01T00:00:00Z".parse().unwrap(),
+            6,
+            parse_fork_schedule("electra:10, deneb:0").unwrap(),
+        );
+        assert_eq!(devnet.fork_at(Slot(0)), Fork::Deneb);
+        assert_eq!(devnet.fork_at(Epoch(10).first_slot()), Fork::Electra);
+        assert_eq!(devnet.slot_at(&devnet.slot_start(Slot(7))), Slot(7));
+
+        assert!(parse_fork_schedule("electra").is_err());
+        assert!(parse_fork_schedule("gloas:10").is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
 use reqwest::Url;
 use serde::{
     de::{DeserializeOwned, Error},
@@ -6,106 +8,177 @@ use serde::{
 use std::{collections::HashSet, fmt};
 use tracing::error;
 
-#[derive(PartialEq)]
-pub enum Env {
-    Stag,
-    Prod,
+use crate::chain_time::{parse_fork_schedule, Epoch, Fork};
+
+/// A beacon chain network and the services we link to for it. Mainnet, Holesky and Hoodi are
+/// built in, other networks, e.g. local devnets, are defined through `NETWORK_*` variables, which
+/// can also override the built in values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Network {
+    pub name: String,
+    pub genesis_time: DateTime<Utc>,
+    pub seconds_per_slot: i64,
+    pub forks: Vec<(Fork, Epoch)>,
+    pub explorer_url: String,
+    pub relay_analytics_url: String,
+    /// Prefix of the relay's keys in Redis, e.g. `boost-relay/mainnet`.
+    pub redis_key_prefix: String,
 }
 
-impl fmt::Display for Env {
+impl fmt::Display for Network {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match &self {
-            Env::Stag => write!(f, "staging"),
-            Env::Prod => write!(f, "production"),
-        }
+        write!(f, "{}", self.name)
     }
 }
 
-impl<'de> Deserialize<'de> for Env {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let s: String = Deserialize::deserialize(deserializer)?;
-        match s.as_ref() {
-            "stag" => Ok(Env::Stag),
-            "staging" => Ok(Env::Stag),
-            "prod" => Ok(Env::Prod),
-            "production" => Ok(Env::Prod),
-            _ => Err(Error::custom(
-                "env present but not stag, staging, prod or production",
-            )),
-        }
+impl Network {
+    fn builtin(name: &str) -> Option<Self> {
+        let (genesis_time, forks, explorer_url, relay_analytics_url) = match name {
+            "mainnet" => (
+                "2020-12-01T12:00:23Z",
+                vec![
+                    (Fork::Altair, Epoch(74240)),
+                    (Fork::Bellatrix, Epoch(144896)),
+                    (Fork::Capella, Epoch(194048)),
+                    (Fork::Deneb, Epoch(269568)),
+                    (Fork::Electra, Epoch(364032)),
+                    (Fork::Fulu, Epoch(411392)),
+                ],
+                "https://beaconcha.in",
+                "https://relay-analytics.ultrasound.money",
+            ),
+            "holesky" => (
+                "2023-09-28T12:00:00Z",
+                vec![
+                    (Fork::Altair, Epoch(0)),
+                    (Fork::Bellatrix, Epoch(0)),
+                    (Fork::Capella, Epoch(256)),
+                    (Fork::Deneb, Epoch(29696)),
+                    (Fork::Electra, Epoch(115968)),
+                    (Fork::Fulu, Epoch(165120)),
+                ],
+                "https://holesky.beaconcha.in",
+                "https://relay-analytics-holesky.ultrasound.money",
+            ),
+            "hoodi" => (
+                "2025-03-17T12:10:00Z",
+                vec![
+                    (Fork::Altair, Epoch(0)),
+                    (Fork::Bellatrix, Epoch(0)),
+                    (Fork::Capella, Epoch(0)),
+                    (Fork::Deneb, Epoch(0)),
+                    (Fork::Electra, Epoch(2048)),
+                    (Fork::Fulu, Epoch(50688)),
+                ],
+                "https://hoodi.beaconcha.in",
+                "https://relay-analytics-hoodi.ultrasound.money",
+            ),
+            _ => return None,
+        };
+
+        Some(Self {
+            name: name.to_string(),
+            genesis_time: genesis_time.parse().unwrap(),
+            seconds_per_slot: 12,
+            forks,
+            explorer_url: explorer_url.to_string(),
+            relay_analytics_url: relay_analytics_url.to_string(),
+            redis_key_prefix: format!("boost-relay/{}", name),
+        })
     }
-}
 
-#[derive(PartialEq, Deserialize)]
-pub enum Network {
-    Mainnet,
-    Holesky,
-    Hoodi,
-}
+    #[cfg(test)]
+    pub fn mainnet() -> Self {
+        Self::builtin("mainnet").unwrap()
+    }
 
-impl fmt::Display for Network {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let str = match &self {
-            Network::Mainnet => "mainnet",
-            Network::Holesky => "holesky",
-            Network::Hoodi => "hoodi",
-        };
-        write!(f, "{}", str)
+    pub fn is_mainnet(&self) -> bool {
+        self.name == "mainnet"
     }
-}
 
-pub trait ToNetwork {
-    fn to_network(&self) -> Network;
-}
+    /// Resolve a network by name, applying `config` on top of the built in definition if there
+    /// is one.
+    fn resolve(name: &str, config: NetworkConfig) -> anyhow::Result<Self> {
+        let builtin = Self::builtin(name);
+        let forks = match config.forks {
+            Some(forks) => parse_fork_schedule(&forks)?,
+            None => builtin
+                .as_ref()
+                .map(|network| network.forks.clone())
+                .unwrap_or_default(),
+        };
+        let missing = |var: &str| anyhow!("NETWORK_{} must be set for network {}", var, name);
 
-impl ToNetwork for Env {
-    fn to_network(&self) -> Network {
-        match *self {
-            Env::Stag => Network::Holesky,
-            Env::Prod => Network::Mainnet,
-        }
+        Ok(Self {
+            name: name.to_string(),
+            genesis_time: config
+                .genesis_time
+                .or(builtin.as_ref().map(|network| network.genesis_time))
+                .ok_or_else(|| missing("GENESIS_TIME"))?,
+            seconds_per_slot: config
+                .seconds_per_slot
+                .or(builtin.as_ref().map(|network| network.seconds_per_slot))
+                .unwrap_or(12),
+            forks,
+            explorer_url: config
+                .explorer_url
+                .or(builtin.as_ref().map(|network| network.explorer_url.clone()))
+                .ok_or_else(|| missing("EXPLORER_URL"))?,
+            relay_analytics_url: config
+                .relay_analytics_url
+                .or(builtin
+                    .as_ref()
+                    .map(|network| network.relay_analytics_url.clone()))
+                .ok_or_else(|| missing("RELAY_ANALYTICS_URL"))?,
+            redis_key_prefix: config
+                .redis_key_prefix
+                .unwrap_or_else(|| format!("boost-relay/{}", name)),
+        })
     }
 }
 
+/// Network settings read from `NETWORK_*` variables.
+#[derive(Default, Deserialize)]
+struct NetworkConfig {
+    genesis_time: Option<DateTime<Utc>>,
+    seconds_per_slot: Option<i64>,
+    /// Fork schedule like `altair:0,bellatrix:0,capella:10`.
+    forks: Option<String>,
+    explorer_url: Option<String>,
+    relay_analytics_url: Option<String>,
+    redis_key_prefix: Option<String>,
+}
+
 pub fn deserialize_network<'de, D>(deserializer: D) -> Result<Network, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let s: String = Deserialize::deserialize(deserializer)?;
-    match s.as_ref() {
-        "mainnet" => Ok(Network::Mainnet),
-        "holesky" => Ok(Network::Holesky),
-        "hoodi" => Ok(Network::Hoodi),
-        _ => Err(Error::custom("network present but not mainnet or goerli")),
-    }
-}
-
-pub trait ToBeaconExplorerUrl {
-    fn to_beacon_explorer_url(&self) -> String;
+    let name: String = Deserialize::deserialize(deserializer)?;
+    let config: NetworkConfig = envy::prefixed("NETWORK_")
+        .from_env()
+        .map_err(Error::custom)?;
+    Network::resolve(&name, config).map_err(Error::custom)
 }
 
-impl ToBeaconExplorerUrl for Env {
-    fn to_beacon_explorer_url(&self) -> String {
-        match *self {
-            Env::Stag => "https://holesky.beaconcha.in",
-            Env::Prod => "https://beaconcha.in",
-        }
-        .to_string()
+/// Network of a deployment configured with the `ENV` variable used before `NETWORK`.
+fn legacy_env_network(env: &str) -> &str {
+    match env {
+        "stag" | "staging" => "holesky",
+        "prod" | "production" => "mainnet",
+        _ => env,
     }
 }
 
-impl ToBeaconExplorerUrl for Network {
-    fn to_beacon_explorer_url(&self) -> String {
-        match *self {
-            Network::Mainnet => "https://beaconcha.in",
-            Network::Holesky => "https://holesky.beaconcha.in",
-            Network::Hoodi => "https://hoodi.beaconcha.in",
-        }
-        .to_string()
-    }
+/// Like `deserialize_network`, also accepting the values of the older `ENV` variable.
+pub fn deserialize_network_or_legacy_env<'de, D>(deserializer: D) -> Result<Network, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let name: String = Deserialize::deserialize(deserializer)?;
+    let config: NetworkConfig = envy::prefixed("NETWORK_")
+        .from_env()
+        .map_err(Error::custom)?;
+    Network::resolve(legacy_env_network(&name), config).map_err(Error::custom)
 }
 
 pub fn deserialize_urls<'de, D>(deserializer: D) -> Result<Vec<Url>, D::Error>
@@ -136,3 +209,52 @@ pub fn get_app_config<T: DeserializeOwned>() -> T {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_env_network() {
+        assert_eq!(legacy_env_network("prod"), "mainnet");
+        assert_eq!(legacy_env_network("staging"), "holesky");
+        assert_eq!(legacy_env_network("hoodi"), "hoodi");
+    }
+
+    #[test]
+    fn test_resolve_network() {
+        let mainnet = Network::resolve("mainnet", NetworkConfig::default()).unwrap();
+        assert_eq!(mainnet, Network::mainnet());
+        assert_eq!(mainnet.redis_key_prefix, "boost-relay/mainnet");
+
+        let config = NetworkConfig {
+            explorer_url: Some("https://explorer.example".to_string()),
+            ..Default::default()
+        };
+        let hoodi = Network::resolve("hoodi", config).unwrap();
+        assert_eq!(hoodi.explorer_url, "https://explorer.example");
+        assert_eq!(
+            hoodi.relay_analytics_url,
+            "https://relay-analytics-hoodi.ultrasound.money"
+        );
+
+        // Unknown networks need their genesis and urls.
+        assert!(Network::resolve("devnet", NetworkConfig::default()).is_err());
+        let config = NetworkConfig {
+            genesis_time: Some("2025-01-01T00:00:00Z".parse().unwrap()),
+            seconds_per_slot: Some(6),
+            forks: Some("deneb:0,electra:10".to_string()),
+            explorer_url: Some("http://localhost:8080".to_string()),
+            relay_analytics_url: Some("http://localhost:3000".to_string()),
+            redis_key_prefix: None,
+        };
+        let devnet = Network::resolve("devnet", config).unwrap();
+        assert_eq!(devnet.seconds_per_slot, 6);
+        assert_eq!(
+            devnet.forks,
+            vec![(Fork::Deneb, Epoch(0)), (Fork::Electra, Epoch(10))]
+        );
+        assert_eq!(devnet.redis_key_prefix, "boost-relay/devnet");
+        assert!(!devnet.is_mainnet());
+    }
+}
@@ -1,11 +1,15 @@
 mod beacon_api;
 mod censorship;
+mod chain_time;
 mod env;
 mod log;
 mod phoenix;
+mod proposers;
 mod serve;
 
 pub use censorship::patch_block_production_interval;
 pub use censorship::start_block_production_ingest;
-pub use phoenix::monitor_critical_services;
+pub use chain_time::{Slot, SlotRange};
+pub use phoenix::{manage_checkpoints, monitor_critical_services};
+pub use phoenix::{replay_inclusion, ReplayRange};
 pub use serve::start_server;
@@ -0,0 +1,119 @@
+//! Routes which change monitor state or expose data not meant for everyone. They are served on
+//! their own port, apart from the unauthenticated health route, and every request needs the admin
+//! token. Without a token configured the admin server doesn't start.
+
+use std::net::SocketAddr;
+
+use anyhow::Result;
+use axum::{
+    http::{header, HeaderMap, Request, StatusCode},
+    middleware::{self, Next},
+    response::Response,
+    routing::{get, post},
+    Router,
+};
+use tracing::{info, warn};
+
+use super::{checkpoint, env::APP_CONFIG, error_rules, promotion_tokens, ServerState};
+
+/// Compare without returning early, so response times don't leak how much of a token matched.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn is_authorized(headers: &HeaderMap, admin_token: &str) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| constant_time_eq(token.as_bytes(), admin_token.as_bytes()))
+}
+
+/// An empty token counts as not set, it would let anyone in.
+fn admin_token() -> Option<&'static str> {
+    APP_CONFIG
+        .admin_token
+        .as_deref()
+        .filter(|token| !token.is_empty())
+}
+
+async fn require_admin_token<B>(
+    request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, StatusCode> {
+    match admin_token() {
+        Some(admin_token) if is_authorized(request.headers(), admin_token) => {
+            Ok(next.run(request).await)
+        }
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+pub async fn mount_admin_route(state: ServerState) -> Result<()> {
+    if admin_token().is_none() {
+        warn!("ADMIN_TOKEN not set, admin routes are disabled");
+        return Ok(());
+    }
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], APP_CONFIG.admin_port));
+
+    let app = Router::new()
+        .route(
+            "/checkpoints/:monitor_id/rewind",
+            post(checkpoint::rewind_checkpoint),
+        )
+        .route(
+            "/checkpoints/:monitor_id/pause",
+            post(checkpoint::pause_checkpoint),
+        )
+        .route(
+            "/checkpoints/:monitor_id/resume",
+            post(checkpoint::resume_checkpoint),
+        )
+        .route(
+            "/checkpoints/:monitor_id/reset",
+            post(checkpoint::reset_checkpoint),
+        )
+        .route("/sim-error-rules/preview", post(error_rules::preview_rules))
+        .route("/promotion-tokens", get(promotion_tokens::active_tokens))
+        .route(
+            "/promotion-tokens/use",
+            post(promotion_tokens::record_token_use),
+        )
+        .route_layer(middleware::from_fn(require_admin_token))
+        .with_state(state);
+
+    info!("admin routes listening on {}", addr);
+
+    axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .await
+        .map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_is_authorized() {
+        let mut headers = HeaderMap::new();
+        assert!(!is_authorized(&headers, "s3cret"));
+
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("s3cret"));
+        assert!(!is_authorized(&headers, "s3cret"));
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer s3cre"),
+        );
+        assert!(!is_authorized(&headers, "s3cret"));
+
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer s3cret"),
+        );
+        assert!(is_authorized(&headers, "s3cret"));
+    }
+}
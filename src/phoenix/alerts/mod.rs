@@ -4,8 +4,6 @@ pub mod telegram;
 use telegram::Channel;
 use tracing::{debug, error};
 
-use crate::env::Network;
-
 pub use self::telegram::TelegramMessage;
 
 use super::env::APP_CONFIG;
@@ -19,7 +17,7 @@ pub async fn send_opsgenie_telegram_alert(message: &str) {
         .await;
 
     // Only send actual OpsGenie alerts on Mainnet.
-    if APP_CONFIG.network == Network::Mainnet {
+    if APP_CONFIG.network.is_mainnet() {
         let result_send_opsgenie_alert = opsgenie::send_opsgenie_alert(message).await;
         match result_send_opsgenie_alert {
             Ok(_) => {
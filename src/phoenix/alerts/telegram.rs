@@ -1,23 +1,10 @@
-use std::{collections::HashMap, fmt, sync::LazyLock};
+use std::fmt;
 
 use anyhow::{anyhow, Result};
 use reqwest::StatusCode;
-use tracing::error;
 
 use crate::phoenix::env::APP_CONFIG;
 
-pub static BUILDER_ID_CHANNEL_ID_MAP: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
-    vec![
-        ("titan".to_string(), "-1002036721274".to_string()),
-        ("beaverbuild".to_string(), "-614386130".to_string()),
-        ("beaverbuild-staging".to_string(), "-614386130".to_string()),
-        ("flashbots".to_string(), "-4827360620".to_string()),
-        ("buildernet".to_string(), "-4827360620".to_string()),
-    ]
-    .into_iter()
-    .collect()
-});
-
 /// Used to escape characters in telegram messages.
 /// https://core.telegram.org/bots/api#markdownv2-style
 pub fn escape_str(input: &str) -> String {
@@ -231,31 +218,20 @@ impl TelegramBot {
             .await;
     }
 
+    /// Send a message to a builder's chat, falling back to our alerts channel if it fails.
     pub async fn send_message_to_builder(
         &self,
         message: &TelegramMessage,
-        builder_id: &str,
+        chat_id: &str,
         button_url: Option<&str>,
     ) {
-        match BUILDER_ID_CHANNEL_ID_MAP.get(builder_id) {
-            Some(channel_id) => {
-                self.send_message_with_retry(
-                    message,
-                    Channel::Id(channel_id.clone()),
-                    button_url,
-                    Some(Channel::Alerts), // Fallback to internal alerts
-                )
-                .await;
-            }
-            None => {
-                error!("failed to find channel_id for builder_id: {}", builder_id);
-                let fallback_message =
-                    TelegramMessage::new("failed to find channel_id, please check logs");
-                // This specific fallback (channel not found) should still go to Alerts.
-                self.send_message_with_retry(&fallback_message, Channel::Alerts, None, None)
-                    .await;
-            }
-        }
+        self.send_message_with_retry(
+            message,
+            Channel::Id(chat_id.to_string()),
+            button_url,
+            Some(Channel::Alerts),
+        )
+        .await;
     }
 
     /// Send a demotion message with a button to the Demotions channel.
@@ -1,14 +1,15 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
-use sqlx::PgPool;
+use itertools::Itertools;
+use sqlx::{PgPool, Row};
 use tracing::debug;
 
-use super::util::get_current_slot;
-
 use crate::phoenix::{Alarm, AlarmType};
 
-use super::env::APP_CONFIG;
+use super::env::{APP_CONFIG, CHAIN_TIME};
 
-async fn get_latest_auction_analysis_slot(mev_pool: &PgPool) -> anyhow::Result<u32> {
+async fn get_latest_auction_analysis_slot(mev_pool: &PgPool) -> anyhow::Result<Option<i64>> {
     sqlx::query_scalar!(
         r#"
         SELECT MAX(slot)
@@ -17,28 +18,251 @@ async fn get_latest_auction_analysis_slot(mev_pool: &PgPool) -> anyhow::Result<u
     )
     .fetch_one(mev_pool)
     .await
-    .map(|max| {
-        max.expect("No maximum slot found from auction_analysis")
-            .try_into()
-            .expect("Maximum slot is negative")
-    })
+    .map(|max| max.map(Into::into))
     .map_err(Into::into)
 }
 
-pub async fn run_auction_analysis_monitor(mev_pool: &PgPool, alarm: &mut Alarm) -> Result<()> {
-    let latest_slot = get_latest_auction_analysis_slot(mev_pool).await?;
-    let current_slot = get_current_slot()?;
+/// Number of rows per slot, for the slots from `start` up to and including `end`.
+async fn get_analysis_counts(mev_pool: &PgPool, start: i64, end: i64) -> Result<HashMap<i64, i64>> {
+    let rows = sqlx::query(
+        "
+        SELECT slot::bigint AS slot, COUNT(*) AS count
+        FROM auction_analysis
+        WHERE slot BETWEEN $1 AND $2
+        GROUP BY slot
+        ",
+    )
+    .bind(start)
+    .bind(end)
+    .fetch_all(mev_pool)
+    .await?;
+    Ok(rows
+        .iter()
+        .map(|row| (row.get("slot"), row.get("count")))
+        .collect())
+}
+
+async fn get_delivered_counts(
+    relay_pool: &PgPool,
+    start: i64,
+    end: i64,
+) -> Result<HashMap<i64, i64>> {
+    let rows = sqlx::query(
+        "
+        SELECT slot, COUNT(*) AS count
+        FROM payload_delivered
+        WHERE slot BETWEEN $1 AND $2
+        GROUP BY slot
+        ",
+    )
+    .bind(start)
+    .bind(end)
+    .fetch_all(relay_pool)
+    .await?;
+    Ok(rows
+        .iter()
+        .map(|row| (row.get("slot"), row.get("count")))
+        .collect())
+}
+
+/// Latest analysed slot per geo, for every geo which analysed or delivered a payload since
+/// `start`.
+async fn get_latest_slot_per_geo(
+    relay_pool: &PgPool,
+    mev_pool: &PgPool,
+    start: i64,
+) -> Result<Vec<(String, Option<i64>)>> {
+    let analysed = sqlx::query(
+        "
+        SELECT geo::text AS geo, MAX(slot)::bigint AS latest_slot
+        FROM auction_analysis
+        WHERE slot >= $1
+        GROUP BY geo
+        ",
+    )
+    .bind(start)
+    .fetch_all(mev_pool)
+    .await?
+    .iter()
+    .map(|row| {
+        (
+            row.get::<String, _>("geo"),
+            row.get::<i64, _>("latest_slot"),
+        )
+    })
+    .collect::<HashMap<_, _>>();
+
+    let delivering_geos: Vec<String> = sqlx::query_scalar(
+        "
+        SELECT DISTINCT geo::text
+        FROM payload_delivered
+        WHERE slot >= $1
+        ",
+    )
+    .bind(start)
+    .fetch_all(relay_pool)
+    .await?;
+
+    Ok(analysed
+        .keys()
+        .chain(delivering_geos.iter())
+        .unique()
+        .sorted()
+        .map(|geo| (geo.clone(), analysed.get(geo).copied()))
+        .collect())
+}
+
+fn find_missing_slots(start: i64, end: i64, analysis_counts: &HashMap<i64, i64>) -> Vec<i64> {
+    (start..=end)
+        .filter(|slot| !analysis_counts.contains_key(slot))
+        .collect()
+}
+
+/// Slots with fewer analysis rows than delivered payloads. Slots without any rows are missing,
+/// not incomplete.
+fn find_incomplete_slots(
+    analysis_counts: &HashMap<i64, i64>,
+    delivered_counts: &HashMap<i64, i64>,
+) -> Vec<(i64, i64, i64)> {
+    delivered_counts
+        .iter()
+        .filter_map(|(slot, delivered)| {
+            analysis_counts
+                .get(slot)
+                .filter(|analysed| *analysed < delivered)
+                .map(|analysed| (*slot, *analysed, *delivered))
+        })
+        .sorted()
+        .collect()
+}
+
+/// Collapse sorted slots into ranges, e.g. `100-103, 107`.
+fn format_slot_ranges(slots: &[i64]) -> String {
+    let mut ranges: Vec<(i64, i64)> = Vec::new();
+    for &slot in slots {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == slot => *end = slot,
+            _ => ranges.push((slot, slot)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .join(", ")
+}
+
+pub async fn run_auction_analysis_monitor(
+    relay_pool: &PgPool,
+    mev_pool: &PgPool,
+    alarm: &mut Alarm,
+) -> Result<()> {
+    let current_slot = CHAIN_TIME.current_slot().0;
+    let max_slot_lag: i64 = APP_CONFIG.max_auction_analysis_slot_lag.into();
+
+    let latest_slot = match get_latest_auction_analysis_slot(mev_pool).await? {
+        Some(latest_slot) => latest_slot,
+        None => {
+            alarm
+                .fire("Auction analysis table is empty", &AlarmType::Telegram)
+                .await;
+            return Ok(());
+        }
+    };
+
+    let mut problems = Vec::new();
+
     let slot_lag = current_slot - latest_slot;
     debug!(
         "Auction analysis is {:} slots behind current slot",
         slot_lag
     );
-    if slot_lag > APP_CONFIG.max_auction_analysis_slot_lag {
-        let message = format!(
+    if slot_lag > max_slot_lag {
+        problems.push(format!(
             "Auction analysis is {:} slots behind the current slot",
             slot_lag
-        );
-        alarm.fire(&message, &AlarmType::Telegram).await;
+        ));
     }
+
+    // Only look at slots up to the latest analysed one, later slots may simply not be analysed
+    // yet.
+    let window_start = latest_slot - APP_CONFIG.auction_analysis_window_slots + 1;
+    let analysis_counts = get_analysis_counts(mev_pool, window_start, latest_slot).await?;
+    let delivered_counts = get_delivered_counts(relay_pool, window_start, latest_slot).await?;
+
+    let missing_slots = find_missing_slots(window_start, latest_slot, &analysis_counts);
+    if !missing_slots.is_empty() {
+        problems.push(format!(
+            "Auction analysis is missing {} slots: {}",
+            missing_slots.len(),
+            format_slot_ranges(&missing_slots)
+        ));
+    }
+
+    let incomplete_slots = find_incomplete_slots(&analysis_counts, &delivered_counts);
+    if !incomplete_slots.is_empty() {
+        let details = incomplete_slots
+            .iter()
+            .map(|(slot, analysed, delivered)| {
+                format!("{} ({}/{} rows)", slot, analysed, delivered)
+            })
+            .join(", ");
+        problems.push(format!(
+            "Auction analysis has fewer rows than delivered payloads for slots: {}",
+            details
+        ));
+    }
+
+    for (geo, geo_latest_slot) in
+        get_latest_slot_per_geo(relay_pool, mev_pool, window_start).await?
+    {
+        match geo_latest_slot {
+            Some(geo_latest_slot) if current_slot - geo_latest_slot <= max_slot_lag => {}
+            Some(geo_latest_slot) => problems.push(format!(
+                "Auction analysis for {} is {} slots behind the current slot",
+                geo,
+                current_slot - geo_latest_slot
+            )),
+            None => problems.push(format!(
+                "Auction analysis has no rows for {} since slot {}",
+                geo, window_start
+            )),
+        }
+    }
+
+    if !problems.is_empty() {
+        alarm
+            .fire(&problems.join("\n\n"), &AlarmType::Telegram)
+            .await;
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_slot_ranges() {
+        let analysis_counts = HashMap::from([(100, 1), (101, 1), (105, 2), (107, 1)]);
+        let missing_slots = find_missing_slots(100, 107, &analysis_counts);
+        assert_eq!(missing_slots, vec![102, 103, 104, 106]);
+        assert_eq!(format_slot_ranges(&missing_slots), "102-104, 106");
+    }
+
+    #[test]
+    fn test_incomplete_slots() {
+        let analysis_counts = HashMap::from([(100, 1), (101, 2)]);
+        let delivered_counts = HashMap::from([(100, 2), (101, 2), (102, 1)]);
+        assert_eq!(
+            find_incomplete_slots(&analysis_counts, &delivered_counts),
+            vec![(100, 1, 2)]
+        );
+    }
+}
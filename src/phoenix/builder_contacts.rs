@@ -0,0 +1,213 @@
+//! How to reach builders, and what they want to hear about. Contacts live in the
+//! `builder_contacts` table, so onboarding a builder is an insert rather than a release.
+
+use std::{fmt, sync::LazyLock, time::Duration};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use sqlx::{PgPool, Row};
+use tracing::{debug, error, info, warn};
+
+use super::{
+    alerts::telegram::{TelegramBot, TelegramMessage},
+    env::APP_CONFIG,
+};
+
+/// Builder endpoints are outside our control, don't let a slow one hold on to a request.
+static WEBHOOK_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(3))
+        .timeout(Duration::from_secs(10))
+        .build()
+        .expect("failed to build webhook client")
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    Demotion,
+    Promotion,
+    MissedSlot,
+}
+
+impl fmt::Display for NotificationKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let str = match self {
+            NotificationKind::Demotion => "demotion",
+            NotificationKind::Promotion => "promotion",
+            NotificationKind::MissedSlot => "missed_slot",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BuilderContact {
+    pub telegram_chat_id: Option<String>,
+    pub email: Option<String>,
+    pub webhook_url: Option<String>,
+    pub notify_demotions: bool,
+    pub notify_promotions: bool,
+    pub notify_missed_slots: bool,
+    /// Builders only hear from us once they've agreed to, whatever their preferences say.
+    pub opted_in: bool,
+}
+
+impl BuilderContact {
+    pub fn wants(&self, kind: NotificationKind) -> bool {
+        self.opted_in
+            && match kind {
+                NotificationKind::Demotion => self.notify_demotions,
+                NotificationKind::Promotion => self.notify_promotions,
+                NotificationKind::MissedSlot => self.notify_missed_slots,
+            }
+    }
+}
+
+pub async fn get_builder_contact(
+    mev_pool: &PgPool,
+    builder_id: &str,
+) -> Result<Option<BuilderContact>> {
+    sqlx::query(
+        "
+        SELECT
+            telegram_chat_id,
+            email,
+            webhook_url,
+            notify_demotions,
+            notify_promotions,
+            notify_missed_slots,
+            opted_in
+        FROM builder_contacts
+        WHERE builder_id = $1
+        ",
+    )
+    .bind(builder_id)
+    .fetch_optional(mev_pool)
+    .await
+    .map(|row| {
+        row.map(|row| BuilderContact {
+            telegram_chat_id: row.get("telegram_chat_id"),
+            email: row.get("email"),
+            webhook_url: row.get("webhook_url"),
+            notify_demotions: row.get("notify_demotions"),
+            notify_promotions: row.get("notify_promotions"),
+            notify_missed_slots: row.get("notify_missed_slots"),
+            opted_in: row.get("opted_in"),
+        })
+    })
+    .map_err(Into::into)
+}
+
+/// The message is sent as is, formatted as Telegram MarkdownV2.
+fn webhook_body(
+    builder_id: &str,
+    kind: NotificationKind,
+    message: &TelegramMessage,
+    button_url: Option<&str>,
+) -> JsonValue {
+    serde_json::json!({
+        "builder_id": builder_id,
+        "kind": kind,
+        "network": APP_CONFIG.network.to_string(),
+        "message": message.to_string(),
+        "button_url": button_url,
+    })
+}
+
+async fn send_webhook(webhook_url: &str, body: &JsonValue) -> Result<()> {
+    let response = WEBHOOK_CLIENT.post(webhook_url).json(body).send().await?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "failed to send builder webhook, status: {}",
+            response.status()
+        ))
+    }
+}
+
+/// Notify a builder on every channel they gave us, if they want to hear about `kind`.
+/// Failures are logged, a builder we can't reach shouldn't stop the monitors.
+pub async fn notify_builder(
+    mev_pool: &PgPool,
+    telegram_bot: &TelegramBot,
+    builder_id: &str,
+    kind: NotificationKind,
+    message: &TelegramMessage,
+    button_url: Option<&str>,
+) {
+    let contact = match get_builder_contact(mev_pool, builder_id).await {
+        Ok(Some(contact)) if contact.wants(kind) => contact,
+        Ok(_) => {
+            debug!(builder_id, %kind, "builder does not want notification, skipping");
+            return;
+        }
+        Err(err) => {
+            error!(builder_id, "failed to get builder contact: {:#}", err);
+            return;
+        }
+    };
+
+    if let Some(chat_id) = &contact.telegram_chat_id {
+        info!(builder_id, %kind, "sending telegram message to builder");
+        telegram_bot
+            .send_message_to_builder(message, chat_id, button_url)
+            .await;
+    }
+
+    // Sent in the background, the monitors don't wait on builder endpoints.
+    if let Some(webhook_url) = contact.webhook_url.clone() {
+        info!(builder_id, %kind, "sending webhook to builder");
+        let body = webhook_body(builder_id, kind, message, button_url);
+        let builder_id = builder_id.to_string();
+        tokio::spawn(async move {
+            if let Err(err) = send_webhook(&webhook_url, &body).await {
+                error!(builder_id, %kind, "{:#}", err);
+            }
+        });
+    }
+
+    if contact.telegram_chat_id.is_none() && contact.webhook_url.is_none() {
+        warn!(
+            builder_id,
+            email = contact.email.as_deref(),
+            %kind,
+            "builder has no telegram chat or webhook, email is not sent automatically"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contact(opted_in: bool) -> BuilderContact {
+        BuilderContact {
+            telegram_chat_id: Some("-1002036721274".to_string()),
+            email: None,
+            webhook_url: None,
+            notify_demotions: true,
+            notify_promotions: false,
+            notify_missed_slots: true,
+            opted_in,
+        }
+    }
+
+    #[test]
+    fn test_wants() {
+        let contact = contact(true);
+        assert!(contact.wants(NotificationKind::Demotion));
+        assert!(!contact.wants(NotificationKind::Promotion));
+        assert!(contact.wants(NotificationKind::MissedSlot));
+    }
+
+    #[test]
+    fn test_not_opted_in_wants_nothing() {
+        let contact = contact(false);
+        assert!(!contact.wants(NotificationKind::Demotion));
+        assert!(!contact.wants(NotificationKind::MissedSlot));
+    }
+}
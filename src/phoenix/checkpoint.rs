@@ -1,14 +1,42 @@
-use anyhow::Result;
-use chrono::{DateTime, Utc};
-use sqlx::PgPool;
-use std::fmt;
+//! Where each polling monitor continues from, as a slot or a timestamp. Monitors advance their
+//! checkpoint after every run, and every change is kept in `monitor_checkpoint_history` with the
+//! number of items the run processed. Checkpoints can be inspected over HTTP, and rewound, paused
+//! and reset on the admin routes or with the `monitor-checkpoints` command.
 
-#[derive(Clone, Copy)]
+use std::{fmt, str::FromStr};
+
+use anyhow::{anyhow, bail, Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use enum_iterator::{all, Sequence};
+use serde::{Deserialize, Serialize, Serializer};
+use sqlx::{postgres::PgRow, PgPool, Postgres, Row, Transaction};
+use tracing::info;
+
+use crate::chain_time::{ChainTime, Slot};
+
+use super::{
+    env::{APP_CONFIG, CHAIN_TIME},
+    ServerState,
+};
+
+/// How long the history of checkpoint changes is kept.
+const HISTORY_RETENTION: Duration = Duration::days(30);
+const DEFAULT_HISTORY_LIMIT: i64 = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Sequence)]
 pub enum CheckpointId {
     Demotion,
     InclusionDelivered,
+    InclusionEvents,
+    InclusionEventsCanonical,
     InclusionPayloadRequests,
     Promotion,
+    PublishStats,
 }
 
 impl fmt::Display for CheckpointId {
@@ -16,45 +44,673 @@ impl fmt::Display for CheckpointId {
         match self {
             CheckpointId::Demotion => write!(f, "demotion_monitor"),
             CheckpointId::InclusionDelivered => write!(f, "inclusion_monitor_delivered"),
+            CheckpointId::InclusionEvents => write!(f, "inclusion_monitor_events"),
+            CheckpointId::InclusionEventsCanonical => {
+                write!(f, "inclusion_monitor_events_canonical")
+            }
             CheckpointId::InclusionPayloadRequests => {
                 write!(f, "inclusion_monitor_payload_requests")
             }
             CheckpointId::Promotion => write!(f, "promotion_monitor"),
+            CheckpointId::PublishStats => write!(f, "publish_stats_monitor"),
+        }
+    }
+}
+
+impl FromStr for CheckpointId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        all::<CheckpointId>()
+            .find(|id| id.to_string() == s)
+            .ok_or_else(|| anyhow!("unknown monitor: {}", s))
+    }
+}
+
+impl Serialize for CheckpointId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Position {
+    Timestamp(DateTime<Utc>),
+    Slot(Slot),
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Position::Timestamp(timestamp) => write!(f, "{}", timestamp.to_rfc3339()),
+            Position::Slot(slot) => write!(f, "slot {}", slot),
+        }
+    }
+}
+
+/// Parses a slot number or an RFC 3339 timestamp.
+impl FromStr for Position {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(slot) = s.parse::<Slot>() {
+            return Ok(Position::Slot(slot));
         }
+        DateTime::parse_from_rfc3339(s)
+            .map(|timestamp| Position::Timestamp(timestamp.with_timezone(&Utc)))
+            .with_context(|| format!("expected a slot or an RFC 3339 timestamp, got {}", s))
     }
 }
 
-pub async fn get_checkpoint(mev_pool: &PgPool, id: CheckpointId) -> Result<Option<DateTime<Utc>>> {
-    sqlx::query_scalar!(
-        r#"
-        SELECT timestamp
+impl Position {
+    fn from_columns(timestamp: Option<DateTime<Utc>>, slot: Option<i64>) -> Option<Self> {
+        match (timestamp, slot) {
+            (Some(timestamp), _) => Some(Position::Timestamp(timestamp)),
+            (None, Some(slot)) => Some(Position::Slot(Slot(slot))),
+            (None, None) => None,
+        }
+    }
+
+    fn timestamp_column(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Position::Timestamp(timestamp) => Some(*timestamp),
+            Position::Slot(_) => None,
+        }
+    }
+
+    fn slot_column(&self) -> Option<i64> {
+        match self {
+            Position::Timestamp(_) => None,
+            Position::Slot(slot) => Some(slot.0),
+        }
+    }
+
+    /// The same point, as a slot or a timestamp like `like`. Slots convert to their start time.
+    fn convert_like(&self, like: &Position, chain_time: &ChainTime) -> Position {
+        match (self, like) {
+            (Position::Slot(slot), Position::Timestamp(_)) => {
+                Position::Timestamp(chain_time.slot_start(*slot))
+            }
+            (Position::Timestamp(timestamp), Position::Slot(_)) => {
+                Position::Slot(chain_time.slot_at(timestamp))
+            }
+            _ => *self,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Action {
+    Init,
+    Advance,
+    Rewind,
+    Pause,
+    Resume,
+    Reset,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let str = match self {
+            Action::Init => "init",
+            Action::Advance => "advance",
+            Action::Rewind => "rewind",
+            Action::Pause => "pause",
+            Action::Resume => "resume",
+            Action::Reset => "reset",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    pub id: CheckpointId,
+    pub position: Position,
+    pub paused: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Checkpoint {
+    fn from_row(row: &PgRow) -> Result<Self> {
+        let monitor_id: String = row.get("monitor_id");
+        Ok(Self {
+            id: monitor_id.parse()?,
+            position: Position::from_columns(row.get("timestamp"), row.get("slot"))
+                .with_context(|| format!("checkpoint of {} has no position", monitor_id))?,
+            paused: row.get("paused"),
+            updated_at: row.get("updated_at"),
+        })
+    }
+
+    pub fn timestamp(&self) -> Result<DateTime<Utc>> {
+        match self.position {
+            Position::Timestamp(timestamp) => Ok(timestamp),
+            Position::Slot(_) => bail!("checkpoint of {} is not a timestamp", self.id),
+        }
+    }
+
+    pub fn slot(&self) -> Result<Slot> {
+        match self.position {
+            Position::Slot(slot) => Ok(slot),
+            Position::Timestamp(_) => bail!("checkpoint of {} is not a slot", self.id),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    action: String,
+    position: Option<Position>,
+    items_processed: Option<i64>,
+    created_at: DateTime<Utc>,
+}
+
+async fn record_history(
+    tx: &mut Transaction<'_, Postgres>,
+    id: CheckpointId,
+    action: Action,
+    position: Option<&Position>,
+    items_processed: Option<usize>,
+) -> Result<()> {
+    sqlx::query(
+        "
+        INSERT INTO monitor_checkpoint_history (
+            monitor_id,
+            action,
+            timestamp,
+            slot,
+            items_processed
+        )
+        VALUES ($1, $2, $3, $4, $5)
+        ",
+    )
+    .bind(id.to_string())
+    .bind(action.to_string())
+    .bind(position.and_then(Position::timestamp_column))
+    .bind(position.and_then(Position::slot_column))
+    .bind(items_processed.map(|items| items as i64))
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_checkpoint(mev_pool: &PgPool, id: CheckpointId) -> Result<Option<Checkpoint>> {
+    sqlx::query(
+        "
+        SELECT monitor_id, timestamp, slot, paused, updated_at
         FROM monitor_checkpoints
         WHERE monitor_id = $1
-        LIMIT 1
-        "#,
-        id.to_string()
+        ",
     )
+    .bind(id.to_string())
     .fetch_optional(mev_pool)
-    .await
-    .map_err(Into::into)
+    .await?
+    .as_ref()
+    .map(Checkpoint::from_row)
+    .transpose()
 }
 
-pub async fn put_checkpoint(
+pub async fn list_checkpoints(mev_pool: &PgPool) -> Result<Vec<Checkpoint>> {
+    let rows = sqlx::query(
+        "
+        SELECT monitor_id, timestamp, slot, paused, updated_at
+        FROM monitor_checkpoints
+        ORDER BY monitor_id
+        ",
+    )
+    .fetch_all(mev_pool)
+    .await?;
+    // Monitors which no longer exist may have left checkpoints behind.
+    Ok(rows
+        .iter()
+        .filter_map(|row| Checkpoint::from_row(row).ok())
+        .collect())
+}
+
+/// The checkpoint of a monitor, created at `start` on its first run.
+pub async fn get_or_init(
     mev_pool: &PgPool,
     id: CheckpointId,
-    checkpoint: &DateTime<Utc>,
-) -> Result<()> {
-    sqlx::query!(
-        r#"
-        INSERT INTO monitor_checkpoints (monitor_id, timestamp)
-        VALUES ($1, $2)
-        ON CONFLICT (monitor_id) DO UPDATE SET timestamp = $2
-        "#,
-        id.to_string(),
-        checkpoint
-    )
-    .execute(mev_pool)
-    .await
-    .map(|_| ())
-    .map_err(Into::into)
+    start: Position,
+) -> Result<Checkpoint> {
+    if let Some(checkpoint) = get_checkpoint(mev_pool, id).await? {
+        return Ok(checkpoint);
+    }
+
+    info!(monitor = %id, %start, "no checkpoint found, initializing");
+    let mut tx = mev_pool.begin().await?;
+    sqlx::query(
+        "
+        INSERT INTO monitor_checkpoints (monitor_id, timestamp, slot)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (monitor_id) DO NOTHING
+        ",
+    )
+    .bind(id.to_string())
+    .bind(start.timestamp_column())
+    .bind(start.slot_column())
+    .execute(&mut *tx)
+    .await?;
+    record_history(&mut tx, id, Action::Init, Some(&start), None).await?;
+    tx.commit().await?;
+
+    get_checkpoint(mev_pool, id)
+        .await?
+        .context("checkpoint missing right after initializing it")
+}
+
+/// Move a checkpoint on after a run which started from `from`. Nothing moves when the checkpoint
+/// was paused, rewound or reset during the run, returns whether it moved.
+pub async fn advance(
+    mev_pool: &PgPool,
+    from: &Checkpoint,
+    to: Position,
+    items_processed: usize,
+) -> Result<bool> {
+    let mut tx = mev_pool.begin().await?;
+    let updated = sqlx::query(
+        "
+        UPDATE monitor_checkpoints
+        SET timestamp = $4, slot = $5, updated_at = now()
+        WHERE monitor_id = $1
+          AND timestamp IS NOT DISTINCT FROM $2
+          AND slot IS NOT DISTINCT FROM $3
+          AND NOT paused
+        ",
+    )
+    .bind(from.id.to_string())
+    .bind(from.position.timestamp_column())
+    .bind(from.position.slot_column())
+    .bind(to.timestamp_column())
+    .bind(to.slot_column())
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    if updated == 0 {
+        info!(monitor = %from.id, "checkpoint changed during run, not advancing");
+        return Ok(false);
+    }
+
+    record_history(
+        &mut tx,
+        from.id,
+        Action::Advance,
+        Some(&to),
+        Some(items_processed),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Where a rewind to `target` leaves a checkpoint, in the unit the monitor uses.
+fn rewind_target(
+    current: &Checkpoint,
+    target: &Position,
+    chain_time: &ChainTime,
+) -> Result<Position> {
+    let target = target.convert_like(&current.position, chain_time);
+    if target > current.position {
+        bail!(
+            "{} is ahead of the checkpoint of {} at {}, only rewinding is allowed",
+            target,
+            current.id,
+            current.position
+        );
+    }
+    Ok(target)
+}
+
+async fn rewind(mev_pool: &PgPool, current: &Checkpoint, target: Position) -> Result<()> {
+    let mut tx = mev_pool.begin().await?;
+    sqlx::query(
+        "
+        UPDATE monitor_checkpoints
+        SET timestamp = $2, slot = $3, updated_at = now()
+        WHERE monitor_id = $1
+        ",
+    )
+    .bind(current.id.to_string())
+    .bind(target.timestamp_column())
+    .bind(target.slot_column())
+    .execute(&mut *tx)
+    .await?;
+    record_history(&mut tx, current.id, Action::Rewind, Some(&target), None).await?;
+    tx.commit().await?;
+    info!(monitor = %current.id, from = %current.position, to = %target, "rewound checkpoint");
+    Ok(())
+}
+
+async fn set_paused(mev_pool: &PgPool, current: &Checkpoint, paused: bool) -> Result<()> {
+    let mut tx = mev_pool.begin().await?;
+    sqlx::query(
+        "
+        UPDATE monitor_checkpoints
+        SET paused = $2, updated_at = now()
+        WHERE monitor_id = $1
+        ",
+    )
+    .bind(current.id.to_string())
+    .bind(paused)
+    .execute(&mut *tx)
+    .await?;
+    let action = if paused {
+        Action::Pause
+    } else {
+        Action::Resume
+    };
+    record_history(&mut tx, current.id, action, Some(&current.position), None).await?;
+    tx.commit().await?;
+    info!(monitor = %current.id, paused, "changed checkpoint");
+    Ok(())
+}
+
+/// Remove a checkpoint, the monitor starts over from the present on its next run.
+async fn reset(mev_pool: &PgPool, current: &Checkpoint) -> Result<()> {
+    let mut tx = mev_pool.begin().await?;
+    sqlx::query("DELETE FROM monitor_checkpoints WHERE monitor_id = $1")
+        .bind(current.id.to_string())
+        .execute(&mut *tx)
+        .await?;
+    record_history(&mut tx, current.id, Action::Reset, None, None).await?;
+    tx.commit().await?;
+    info!(monitor = %current.id, "reset checkpoint");
+    Ok(())
+}
+
+pub async fn get_history(
+    mev_pool: &PgPool,
+    id: CheckpointId,
+    limit: i64,
+) -> Result<Vec<HistoryEntry>> {
+    let rows = sqlx::query(
+        "
+        SELECT action, timestamp, slot, items_processed, created_at
+        FROM monitor_checkpoint_history
+        WHERE monitor_id = $1
+        ORDER BY created_at DESC, id DESC
+        LIMIT $2
+        ",
+    )
+    .bind(id.to_string())
+    .bind(limit)
+    .fetch_all(mev_pool)
+    .await?;
+    Ok(rows
+        .iter()
+        .map(|row| HistoryEntry {
+            action: row.get("action"),
+            position: Position::from_columns(row.get("timestamp"), row.get("slot")),
+            items_processed: row.get("items_processed"),
+            created_at: row.get("created_at"),
+        })
+        .collect())
+}
+
+pub async fn prune_history(mev_pool: &PgPool, now: DateTime<Utc>) -> Result<()> {
+    let pruned = sqlx::query("DELETE FROM monitor_checkpoint_history WHERE created_at < $1")
+        .bind(now - HISTORY_RETENTION)
+        .execute(mev_pool)
+        .await?
+        .rows_affected();
+    if pruned > 0 {
+        info!(pruned, "pruned checkpoint history");
+    }
+    Ok(())
+}
+
+async fn get_existing(mev_pool: &PgPool, id: CheckpointId) -> Result<Checkpoint> {
+    get_checkpoint(mev_pool, id)
+        .await?
+        .with_context(|| format!("{} has no checkpoint", id))
+}
+
+/// Run a command of the `monitor-checkpoints` binary, `args` without the program name.
+pub async fn manage_checkpoints(args: &[String]) -> Result<()> {
+    let mev_pool = PgPool::connect(&APP_CONFIG.database_url).await?;
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let id = |index: usize| -> Result<CheckpointId> {
+        args.get(index).context("missing monitor")?.parse()
+    };
+
+    match args.first().copied() {
+        Some("list") => {
+            for checkpoint in list_checkpoints(&mev_pool).await? {
+                println!(
+                    "{:<36} {:<28} {}",
+                    checkpoint.id.to_string(),
+                    checkpoint.position.to_string(),
+                    if checkpoint.paused { "paused" } else { "" }
+                );
+            }
+        }
+        Some("history") => {
+            let limit = match args.get(2) {
+                Some(limit) => limit.parse()?,
+                None => DEFAULT_HISTORY_LIMIT,
+            };
+            for entry in get_history(&mev_pool, id(1)?, limit).await? {
+                println!(
+                    "{} {:<8} {:<28} {}",
+                    entry.created_at.to_rfc3339(),
+                    entry.action,
+                    entry.position.map(|p| p.to_string()).unwrap_or_default(),
+                    entry
+                        .items_processed
+                        .map(|items| format!("{} items", items))
+                        .unwrap_or_default()
+                );
+            }
+        }
+        Some("rewind") => {
+            let current = get_existing(&mev_pool, id(1)?).await?;
+            let target: Position = args.get(2).context("missing slot or timestamp")?.parse()?;
+            rewind(
+                &mev_pool,
+                &current,
+                rewind_target(&current, &target, &CHAIN_TIME)?,
+            )
+            .await?;
+        }
+        Some("pause") => {
+            set_paused(&mev_pool, &get_existing(&mev_pool, id(1)?).await?, true).await?
+        }
+        Some("resume") => {
+            set_paused(&mev_pool, &get_existing(&mev_pool, id(1)?).await?, false).await?
+        }
+        Some("reset") => reset(&mev_pool, &get_existing(&mev_pool, id(1)?).await?).await?,
+        _ => bail!("usage: monitor-checkpoints list|history|rewind|pause|resume|reset"),
+    }
+
+    Ok(())
+}
+
+type AdminResponse<T> = Result<Json<T>, (StatusCode, String)>;
+
+fn internal_error(err: anyhow::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+}
+
+async fn find_checkpoint(
+    mev_pool: &PgPool,
+    monitor_id: &str,
+) -> Result<Checkpoint, (StatusCode, String)> {
+    let id = monitor_id
+        .parse()
+        .map_err(|err: anyhow::Error| (StatusCode::NOT_FOUND, err.to_string()))?;
+    get_checkpoint(mev_pool, id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("{} has no checkpoint", id)))
+}
+
+pub async fn checkpoints(State(state): State<ServerState>) -> AdminResponse<Vec<Checkpoint>> {
+    list_checkpoints(&state.mev_pool)
+        .await
+        .map(Json)
+        .map_err(internal_error)
+}
+
+#[derive(Deserialize)]
+pub struct HistoryParams {
+    limit: Option<i64>,
+}
+
+pub async fn checkpoint_history(
+    State(state): State<ServerState>,
+    Path(monitor_id): Path<String>,
+    Query(params): Query<HistoryParams>,
+) -> AdminResponse<Vec<HistoryEntry>> {
+    let checkpoint = find_checkpoint(&state.mev_pool, &monitor_id).await?;
+    let limit = params.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+    get_history(&state.mev_pool, checkpoint.id, limit)
+        .await
+        .map(Json)
+        .map_err(internal_error)
+}
+
+#[derive(Deserialize)]
+pub struct RewindRequest {
+    /// A slot number or an RFC 3339 timestamp.
+    to: String,
+}
+
+pub async fn rewind_checkpoint(
+    State(state): State<ServerState>,
+    Path(monitor_id): Path<String>,
+    Json(request): Json<RewindRequest>,
+) -> AdminResponse<Checkpoint> {
+    let current = find_checkpoint(&state.mev_pool, &monitor_id).await?;
+    let target = request
+        .to
+        .parse()
+        .and_then(|target| rewind_target(&current, &target, &CHAIN_TIME))
+        .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+    rewind(&state.mev_pool, &current, target)
+        .await
+        .map_err(internal_error)?;
+    find_checkpoint(&state.mev_pool, &monitor_id)
+        .await
+        .map(Json)
+}
+
+pub async fn pause_checkpoint(
+    State(state): State<ServerState>,
+    Path(monitor_id): Path<String>,
+) -> AdminResponse<Checkpoint> {
+    let current = find_checkpoint(&state.mev_pool, &monitor_id).await?;
+    set_paused(&state.mev_pool, &current, true)
+        .await
+        .map_err(internal_error)?;
+    find_checkpoint(&state.mev_pool, &monitor_id)
+        .await
+        .map(Json)
+}
+
+pub async fn resume_checkpoint(
+    State(state): State<ServerState>,
+    Path(monitor_id): Path<String>,
+) -> AdminResponse<Checkpoint> {
+    let current = find_checkpoint(&state.mev_pool, &monitor_id).await?;
+    set_paused(&state.mev_pool, &current, false)
+        .await
+        .map_err(internal_error)?;
+    find_checkpoint(&state.mev_pool, &monitor_id)
+        .await
+        .map(Json)
+}
+
+pub async fn reset_checkpoint(
+    State(state): State<ServerState>,
+    Path(monitor_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let current = find_checkpoint(&state.mev_pool, &monitor_id).await?;
+    reset(&state.mev_pool, &current)
+        .await
+        .map_err(internal_error)?;
+    Ok(StatusCode::OK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::env::Network;
+
+    fn checkpoint(position: Position) -> Checkpoint {
+        Checkpoint {
+            id: CheckpointId::InclusionDelivered,
+            position,
+            paused: false,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_ids_round_trip() {
+        for id in all::<CheckpointId>() {
+            assert_eq!(id.to_string().parse::<CheckpointId>().unwrap(), id);
+        }
+        assert!("auction_analysis_monitor".parse::<CheckpointId>().is_err());
+    }
+
+    #[test]
+    fn test_parse_position() {
+        assert_eq!(
+            "9000000".parse::<Position>().unwrap(),
+            Position::Slot(Slot(9000000))
+        );
+        assert_eq!(
+            "2024-05-01T12:00:00Z".parse::<Position>().unwrap(),
+            Position::Timestamp("2024-05-01T12:00:00Z".parse().unwrap())
+        );
+        assert!("yesterday".parse::<Position>().is_err());
+    }
+
+    #[test]
+    fn test_rewind_target() {
+        let chain_time = ChainTime::for_network(&Network::mainnet());
+        let at = chain_time.slot_start(Slot(9000000)) + Duration::seconds(5);
+
+        // Slots are converted for timestamp checkpoints and the other way around.
+        let timestamp_checkpoint = checkpoint(Position::Timestamp(at));
+        assert_eq!(
+            rewind_target(
+                &timestamp_checkpoint,
+                &Position::Slot(Slot(8999990)),
+                &chain_time
+            )
+            .unwrap(),
+            Position::Timestamp(chain_time.slot_start(Slot(8999990)))
+        );
+        let slot_checkpoint = checkpoint(Position::Slot(Slot(9000000)));
+        assert_eq!(
+            rewind_target(
+                &slot_checkpoint,
+                &Position::Timestamp(at - Duration::minutes(1)),
+                &chain_time
+            )
+            .unwrap(),
+            Position::Slot(Slot(8999995))
+        );
+
+        // Moving forward would skip items.
+        assert!(rewind_target(
+            &timestamp_checkpoint,
+            &Position::Slot(Slot(9000001)),
+            &chain_time
+        )
+        .is_err());
+        assert!(rewind_target(
+            &slot_checkpoint,
+            &Position::Slot(Slot(9000001)),
+            &chain_time
+        )
+        .is_err());
+    }
 }
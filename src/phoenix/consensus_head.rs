@@ -0,0 +1,275 @@
+use async_trait::async_trait;
+use itertools::Itertools;
+use reqwest::Url;
+use std::{fmt, time::Duration};
+use tokio::time::sleep;
+use tracing::{debug, error, info, warn};
+
+use super::{clock::SharedClock, env::APP_CONFIG, PhoenixMonitor, UnhealthyNode};
+use crate::beacon_api::{BeaconApi, BlockHeader, Checkpoint};
+
+#[derive(Debug, Clone)]
+struct NodeHead {
+    url: Url,
+    head: BlockHeader,
+    finalized: Checkpoint,
+}
+
+#[derive(Debug, PartialEq)]
+enum Divergence {
+    /// Head trails the majority head by more than the allowed number of slots.
+    Lagging { slots_behind: i64 },
+    /// Head is at the same slot as the majority head but has a different root.
+    HeadRoot { root: String },
+    /// Finalized checkpoint is at the same epoch as the majority but has a different root.
+    FinalizedRoot { root: String },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Divergence::Lagging { slots_behind } => {
+                write!(f, "head {} slots behind majority", slots_behind)
+            }
+            Divergence::HeadRoot { root } => write!(f, "head root {} differs from majority", root),
+            Divergence::FinalizedRoot { root } => {
+                write!(f, "finalized root {} differs from majority", root)
+            }
+        }
+    }
+}
+
+/// Most common value, ties are broken by picking the greatest value. For heads this means the
+/// highest slot wins, which avoids flagging nodes that are merely ahead at a slot boundary.
+fn majority<T: Clone + Eq + Ord + std::hash::Hash>(values: impl Iterator<Item = T>) -> Option<T> {
+    values
+        .counts()
+        .into_iter()
+        .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then_with(|| a.cmp(b)))
+        .map(|(value, _)| value)
+}
+
+/// Compare every node against the majority head and finalized checkpoint.
+///
+/// Nodes ahead of the majority head are not flagged, without walking the chain we can't tell
+/// whether they are on a fork or simply saw the next block first.
+fn find_divergences(heads: &[NodeHead], max_slot_lag: i64) -> Vec<(Url, Divergence)> {
+    let majority_head = majority(heads.iter().map(|n| (n.head.slot, n.head.root.clone())));
+    let majority_finalized = majority(
+        heads
+            .iter()
+            .map(|n| (n.finalized.epoch, n.finalized.root.clone())),
+    );
+
+    let (Some((head_slot, head_root)), Some((finalized_epoch, finalized_root))) =
+        (majority_head, majority_finalized)
+    else {
+        return Vec::new();
+    };
+
+    let mut divergences = Vec::new();
+
+    for node in heads {
+        let slots_behind = head_slot - node.head.slot;
+        if slots_behind > max_slot_lag {
+            divergences.push((node.url.clone(), Divergence::Lagging { slots_behind }));
+        } else if node.head.slot == head_slot && node.head.root != head_root {
+            divergences.push((
+                node.url.clone(),
+                Divergence::HeadRoot {
+                    root: node.head.root.clone(),
+                },
+            ));
+        }
+
+        if node.finalized.epoch == finalized_epoch && node.finalized.root != finalized_root {
+            divergences.push((
+                node.url.clone(),
+                Divergence::FinalizedRoot {
+                    root: node.finalized.root.clone(),
+                },
+            ));
+        }
+    }
+
+    divergences
+}
+
+/// Watches for consensus nodes which respond and claim to be synced, but are stuck behind or
+/// following a different fork than the rest of our nodes.
+pub struct ConsensusHeadMonitor {
+    beacon_api: BeaconApi,
+}
+
+impl ConsensusHeadMonitor {
+    pub fn new() -> Self {
+        Self {
+            beacon_api: BeaconApi::new(&APP_CONFIG.consensus_nodes),
+        }
+    }
+
+    async fn node_heads(&self) -> Vec<NodeHead> {
+        let (headers, checkpoints) = tokio::join!(
+            self.beacon_api.head_header_all(),
+            self.beacon_api.finality_checkpoints_all()
+        );
+
+        // Unreachable nodes are reported by the ConsensusNodeMonitor, we only compare the nodes
+        // that answered.
+        self.beacon_api
+            .node_hosts()
+            .iter()
+            .zip(headers.into_iter().zip(checkpoints))
+            .filter_map(|(url, results)| match results {
+                (Ok(head), Ok(checkpoints)) => Some(NodeHead {
+                    url: url.clone(),
+                    head,
+                    finalized: checkpoints.finalized,
+                }),
+                (Err(err), _) | (_, Err(err)) => {
+                    error!(%url, "error getting consensus node head: {}", err);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    async fn check_nodes_once(&self) -> Vec<(Url, Divergence)> {
+        let heads = self.node_heads().await;
+        find_divergences(&heads, APP_CONFIG.consensus_head_max_slot_lag)
+    }
+
+    async fn unhealthy_nodes(&self) -> Vec<UnhealthyNode> {
+        let mut divergences = self.check_nodes_once().await;
+
+        // Heads race at slot boundaries, give the nodes a moment to agree before reporting.
+        if !divergences.is_empty() {
+            info!(
+                "found {} consensus head divergences, retrying in 3s",
+                divergences.len()
+            );
+            sleep(Duration::from_secs(3)).await;
+            divergences = self.check_nodes_once().await;
+        }
+
+        for (url, divergence) in &divergences {
+            warn!(%url, ?divergence, "consensus node diverges from majority");
+        }
+
+        let unhealthy_nodes = divergences
+            .into_iter()
+            .into_group_map()
+            .into_iter()
+            .map(|(url, divergences)| UnhealthyNode::new(&url, divergences.iter().join(", ")))
+            .sorted_by(|a, b| a.url.cmp(&b.url))
+            .collect_vec();
+        debug!(
+            "{} consensus nodes diverge from majority",
+            unhealthy_nodes.len()
+        );
+        unhealthy_nodes
+    }
+}
+
+#[async_trait]
+impl PhoenixMonitor for ConsensusHeadMonitor {
+    async fn refresh(&self, _clock: &SharedClock) -> Vec<UnhealthyNode> {
+        self.unhealthy_nodes().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node_head(host: &str, slot: i64, root: &str, epoch: i64, finalized_root: &str) -> NodeHead {
+        NodeHead {
+            url: Url::parse(&format!("http://{}:5052/", host)).unwrap(),
+            head: BlockHeader {
+                root: root.to_string(),
+                slot,
+                parent_root: format!("0x{}", "00".repeat(32)),
+            },
+            finalized: Checkpoint {
+                epoch,
+                root: finalized_root.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_all_nodes_agree() {
+        let heads = vec![
+            node_head("a", 100, "0xa", 1, "0xf"),
+            node_head("b", 100, "0xa", 1, "0xf"),
+            node_head("c", 100, "0xa", 1, "0xf"),
+        ];
+
+        assert_eq!(find_divergences(&heads, 2), vec![]);
+    }
+
+    #[test]
+    fn test_node_within_lag_is_healthy() {
+        let heads = vec![
+            node_head("a", 100, "0xa", 1, "0xf"),
+            node_head("b", 100, "0xa", 1, "0xf"),
+            node_head("c", 98, "0xb", 1, "0xf"),
+        ];
+
+        assert_eq!(find_divergences(&heads, 2), vec![]);
+    }
+
+    #[test]
+    fn test_lagging_node() {
+        let heads = vec![
+            node_head("a", 100, "0xa", 1, "0xf"),
+            node_head("b", 100, "0xa", 1, "0xf"),
+            node_head("c", 97, "0xb", 1, "0xf"),
+        ];
+
+        assert_eq!(
+            find_divergences(&heads, 2),
+            vec![(
+                Url::parse("http://c:5052/").unwrap(),
+                Divergence::Lagging { slots_behind: 3 }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_minority_fork() {
+        let heads = vec![
+            node_head("a", 100, "0xa", 1, "0xf"),
+            node_head("b", 100, "0xa", 1, "0xf"),
+            node_head("c", 100, "0xc", 1, "0xe"),
+        ];
+
+        assert_eq!(
+            find_divergences(&heads, 2),
+            vec![
+                (
+                    Url::parse("http://c:5052/").unwrap(),
+                    Divergence::HeadRoot {
+                        root: "0xc".to_string()
+                    }
+                ),
+                (
+                    Url::parse("http://c:5052/").unwrap(),
+                    Divergence::FinalizedRoot {
+                        root: "0xe".to_string()
+                    }
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn test_node_ahead_at_slot_boundary() {
+        let heads = vec![
+            node_head("a", 101, "0xb", 1, "0xf"),
+            node_head("b", 100, "0xa", 1, "0xf"),
+        ];
+
+        assert_eq!(find_divergences(&heads, 2), vec![]);
+    }
+}
@@ -1,77 +1,147 @@
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
 use std::time::Duration;
 use tokio::time::sleep;
 use tracing::{debug, error, info};
 
-use super::{env::APP_CONFIG, PhoenixMonitor};
+use super::{clock::SharedClock, env::APP_CONFIG, PhoenixMonitor, UnhealthyNode};
 use crate::beacon_api::BeaconApi;
 
+const RETRY_DELAY: Duration = Duration::from_secs(3);
+
 pub struct ConsensusNodeMonitor {
     beacon_api: BeaconApi,
+    retry_delay: Duration,
 }
 
 impl ConsensusNodeMonitor {
     pub fn new() -> Self {
+        Self::with_beacon_api(BeaconApi::new(&APP_CONFIG.consensus_nodes), RETRY_DELAY)
+    }
+
+    fn with_beacon_api(beacon_api: BeaconApi, retry_delay: Duration) -> Self {
         Self {
-            beacon_api: BeaconApi::new(&APP_CONFIG.consensus_nodes),
+            beacon_api,
+            retry_delay,
         }
     }
 
-    async fn check_nodes_once(&self) -> Vec<bool> {
-        let mut results = Vec::new();
+    async fn check_nodes_once(&self) -> Vec<UnhealthyNode> {
+        self.beacon_api.refresh_sync_status().await;
 
-        let statuses = self.beacon_api.sync_status_all().await;
-        for status in statuses {
-            match status {
-                Ok(s) => results.push(!s.is_syncing),
-                Err(err) => {
+        self.beacon_api
+            .node_health()
+            .into_iter()
+            .filter_map(|(url, health)| {
+                if let Some(err) = &health.last_error {
                     error!("error getting consensus node status: {}", err);
-                    results.push(false)
                 }
-            }
-        }
-
-        results
+                health
+                    .problem()
+                    .map(|reason| UnhealthyNode::new(&url, reason))
+            })
+            .collect()
     }
 
-    async fn num_unsynced_nodes(&self) -> usize {
+    async fn unhealthy_nodes(&self) -> Vec<UnhealthyNode> {
         // First attempt
-        let mut results = self.check_nodes_once().await;
-        let mut offline_nodes = results.iter().filter(|is_synced| !**is_synced).count();
+        let mut unhealthy_nodes = self.check_nodes_once().await;
 
         // If any nodes are offline, retry after 3 seconds
-        if offline_nodes > 0 {
+        if !unhealthy_nodes.is_empty() {
             info!(
                 "found {} offline consensus nodes, retrying in 3s",
-                offline_nodes
+                unhealthy_nodes.len()
             );
-            sleep(Duration::from_secs(3)).await;
+            sleep(self.retry_delay).await;
 
-            results = self.check_nodes_once().await;
-            offline_nodes = results.iter().filter(|is_synced| !**is_synced).count();
+            unhealthy_nodes = self.check_nodes_once().await;
 
             // If still offline, try one last time
-            if offline_nodes > 0 {
+            if !unhealthy_nodes.is_empty() {
                 info!(
                     "still found {} offline consensus nodes, final retry in 3s",
-                    offline_nodes
+                    unhealthy_nodes.len()
                 );
-                sleep(Duration::from_secs(3)).await;
-                results = self.check_nodes_once().await;
+                sleep(self.retry_delay).await;
+                unhealthy_nodes = self.check_nodes_once().await;
             }
         }
 
-        let synced: Vec<&bool> = results.iter().filter(|is_synced| **is_synced).collect();
-        debug!("{}/{} consensus nodes synced", synced.len(), results.len());
-        results.len() - synced.len()
+        let node_count = self.beacon_api.node_hosts().len();
+        debug!(
+            "{}/{} consensus nodes synced",
+            node_count - unhealthy_nodes.len(),
+            node_count
+        );
+        unhealthy_nodes
     }
 }
 
 #[async_trait]
 impl PhoenixMonitor for ConsensusNodeMonitor {
-    async fn refresh(&self) -> (DateTime<Utc>, usize) {
-        let num_unsynced_nodes = self.num_unsynced_nodes().await;
-        (Utc::now(), num_unsynced_nodes)
+    async fn refresh(&self, _clock: &SharedClock) -> Vec<UnhealthyNode> {
+        self.unhealthy_nodes().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beacon_api::mock::{MockBeaconNode, SyncReply};
+    use reqwest::StatusCode;
+
+    fn monitor(nodes: &[&MockBeaconNode]) -> ConsensusNodeMonitor {
+        let urls = nodes.iter().map(|node| node.url()).collect::<Vec<_>>();
+        ConsensusNodeMonitor::with_beacon_api(BeaconApi::new(&urls), Duration::ZERO)
+    }
+
+    #[tokio::test]
+    async fn test_healthy_nodes_checked_once() {
+        let node = MockBeaconNode::start();
+
+        assert!(monitor(&[&node]).unhealthy_nodes().await.is_empty());
+        assert_eq!(node.sync_requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_node_recovering_on_retry() {
+        let node = MockBeaconNode::start();
+        node.set_sync_replies([
+            SyncReply::Error(StatusCode::BAD_GATEWAY),
+            SyncReply::Syncing,
+            SyncReply::Synced,
+        ]);
+        let other = MockBeaconNode::start();
+
+        assert!(monitor(&[&node, &other]).unhealthy_nodes().await.is_empty());
+        assert_eq!(node.sync_requests(), 3);
+        // Every attempt checks all nodes.
+        assert_eq!(other.sync_requests(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_unhealthy_after_three_attempts() {
+        let syncing = MockBeaconNode::start();
+        syncing.set_sync_replies([SyncReply::Syncing]);
+        let unreachable = MockBeaconNode::start();
+        unreachable.set_sync_replies([SyncReply::Error(StatusCode::INTERNAL_SERVER_ERROR)]);
+        let synced = MockBeaconNode::start();
+
+        let unhealthy_nodes = monitor(&[&syncing, &unreachable, &synced])
+            .unhealthy_nodes()
+            .await;
+
+        let reasons = unhealthy_nodes
+            .iter()
+            .map(|node| (node.url.clone(), node.reason.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            reasons,
+            vec![
+                (syncing.url(), "syncing"),
+                (unreachable.url(), "unreachable")
+            ]
+        );
+        assert_eq!(syncing.sync_requests(), 3);
     }
 }
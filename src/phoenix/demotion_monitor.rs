@@ -1,34 +1,22 @@
-use std::sync::LazyLock;
-
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use indoc::formatdoc;
 use itertools::Itertools;
-use rand::{distributions::Alphanumeric, Rng};
 use sqlx::{PgPool, Row};
 use tracing::{debug, info};
 
-use crate::{
-    env::ToBeaconExplorerUrl,
-    phoenix::{
-        alerts::telegram::{Channel, TelegramMessage},
-        promotion_monitor::is_promotable_error,
-        telegram,
-    },
+use crate::phoenix::{
+    alerts::telegram::{Channel, TelegramMessage},
+    builder_contacts::{self, NotificationKind},
+    error_rules::{self, ErrorRules},
+    promotion_tokens, telegram,
 };
 
 use super::{
-    checkpoint::{self, CheckpointId},
+    checkpoint::{self, CheckpointId, Position},
     env::{Geo, APP_CONFIG},
 };
 
-static DIRECT_MESSAGE_BUILDER_IDS: LazyLock<Vec<String>> = LazyLock::new(|| {
-    telegram::BUILDER_ID_CHANNEL_ID_MAP
-        .keys()
-        .cloned()
-        .collect_vec()
-});
-
 #[derive(Debug, Clone)]
 pub struct BuilderDemotion {
     pub geo: Geo,
@@ -80,43 +68,10 @@ pub async fn get_builder_demotions(
         .map_err(Into::into)
 }
 
-/// Demotion errors that shouldn't be broadcast on telegram
-const IGNORED_ERRORS: &[&str] = &[
-    "Post \"http://prio-load-balancer:80\": context deadline exceeded (Client.Timeout exceeded while awaiting headers)",
-    "json error: request timeout hit before processing",
-    "simulation failed: unknown ancestor",
-    "simulation queue timed out"
-];
-
-fn is_ignored_error(error: &str) -> bool {
-    IGNORED_ERRORS
-        .iter()
-        // Use starts_with to account for dynamic info in error message
-        .any(|silent_error| error.starts_with(silent_error))
-}
-
-async fn fetch_demotions(
-    relay_pool: &PgPool,
-    mev_pool: &PgPool,
-    now: DateTime<Utc>,
-) -> Result<Vec<BuilderDemotion>> {
-    let checkpoint = match checkpoint::get_checkpoint(mev_pool, CheckpointId::Demotion).await? {
-        Some(c) => c,
-        None => {
-            info!("no checkpoint found, initializing");
-            checkpoint::put_checkpoint(mev_pool, CheckpointId::Demotion, &now).await?;
-            now
-        }
-    };
-    debug!("checking demotions between {} and {}", &checkpoint, &now);
-    let demotions = get_builder_demotions(relay_pool, &checkpoint, &now).await?;
-    Ok(demotions)
-}
-
-fn filter_demotions(demotions: Vec<BuilderDemotion>) -> Vec<BuilderDemotion> {
+fn filter_demotions(rules: &ErrorRules, demotions: Vec<BuilderDemotion>) -> Vec<BuilderDemotion> {
     demotions
         .into_iter()
-        .filter(|d| !is_ignored_error(&d.sim_error))
+        .filter(|d| !rules.is_ignored(&d.sim_error))
         .collect_vec()
 }
 
@@ -126,7 +81,7 @@ fn format_demotion_message(demotion: &BuilderDemotion) -> String {
     const MAX_ERROR_LEN: usize = 512;
     const ERROR_TRUNCATION_MARKER: &str = "..TRUNCATED..";
 
-    let explorer_url = APP_CONFIG.network.to_beacon_explorer_url();
+    let explorer_url = &APP_CONFIG.network.explorer_url;
     let network = APP_CONFIG.network.to_string();
     let builder_id = demotion.builder_id.as_deref().unwrap_or("unknown");
     let escaped_builder_id = telegram::escape_str(builder_id);
@@ -167,57 +122,18 @@ fn format_demotion_message(demotion: &BuilderDemotion) -> String {
     )
 }
 
-async fn gen_promotion_token(pool: &PgPool, builder_id: &str) -> Result<String> {
-    let expires_at = chrono::Utc::now() + chrono::Duration::days(7);
-    let mut token: String;
-
-    loop {
-        token = rand::thread_rng()
-            .sample_iter(&Alphanumeric)
-            .take(16)
-            .map(char::from)
-            .collect();
-
-        let token_exists = sqlx::query_scalar::<_, bool>(
-            "SELECT EXISTS(SELECT 1 FROM promotion_tokens WHERE token = $1)",
-        )
-        .bind(&token)
-        .fetch_one(pool)
-        .await?;
-
-        if !token_exists {
-            break;
-        }
-    }
-
-    sqlx::query(
-        "
-        INSERT INTO promotion_tokens (
-            builder_id,
-            token,
-            expires_at
-        )
-        VALUES ($1, $2, $3)
-        ",
-    )
-    .bind(builder_id)
-    .bind(&token)
-    .bind(expires_at)
-    .execute(pool)
-    .await?;
-
-    Ok(token)
-}
-
 async fn generate_and_send_alerts(
+    rules: &ErrorRules,
     demotions: Vec<BuilderDemotion>,
     global_db_pool: &PgPool,
+    mev_pool: &PgPool,
+    now: DateTime<Utc>,
 ) -> Result<()> {
-    let filtered_demotions = filter_demotions(demotions);
+    let filtered_demotions = filter_demotions(rules, demotions);
     let (warning_demotions, alert_demotions): (Vec<BuilderDemotion>, Vec<BuilderDemotion>) =
         filtered_demotions
             .into_iter()
-            .partition(|d| is_promotable_error(&d.sim_error));
+            .partition(|d| rules.is_promotable(&d.sim_error));
 
     let unique_demotions = |demotions: Vec<BuilderDemotion>| {
         let mut seen = std::collections::HashSet::new();
@@ -245,29 +161,45 @@ async fn generate_and_send_alerts(
             let alert_message = TelegramMessage::from_escaped_string(alert_message);
 
             let builder_id = demotion.builder_id.as_deref().unwrap_or("unknown");
-            match gen_promotion_token(global_db_pool, builder_id).await {
-                Ok(token) => {
-                    let button_url = format!(
+            // Without a token the alert still goes out, only without the promote button.
+            let button_url =
+                match promotion_tokens::create_token(global_db_pool, mev_pool, builder_id, now)
+                    .await
+                {
+                    Ok(token) => Some(format!(
                         "{}/ultrasound/v1/data/admin/promote?token={}",
                         APP_CONFIG.relay_analytics_url(),
                         token
-                    );
-                    info!(%alert_message, "sending telegram message to demotions channel");
-                    telegram_bot
-                        .send_demotion_with_button(&alert_message, &button_url)
-                        .await;
-
-                    if DIRECT_MESSAGE_BUILDER_IDS.contains(&builder_id.to_string()) {
-                        info!(%alert_message, builder_id, "sending telegram message to builder");
-                        telegram_bot
-                            .send_message_to_builder(&alert_message, builder_id, Some(&button_url))
-                            .await;
+                    )),
+                    Err(err) => {
+                        tracing::error!(%err, "failed to generate and store promotion token");
+                        None
                     }
+                };
+
+            info!(%alert_message, "sending telegram message to demotions channel");
+            match &button_url {
+                Some(button_url) => {
+                    telegram_bot
+                        .send_demotion_with_button(&alert_message, button_url)
+                        .await
                 }
-                Err(err) => {
-                    tracing::error!(%err, "failed to generate and store promotion token");
+                None => {
+                    telegram_bot
+                        .send_message(&alert_message, Channel::Demotions)
+                        .await
                 }
             }
+
+            builder_contacts::notify_builder(
+                mev_pool,
+                &telegram_bot,
+                builder_id,
+                NotificationKind::Demotion,
+                &alert_message,
+                button_url.as_deref(),
+            )
+            .await;
         }
     }
 
@@ -294,15 +226,30 @@ async fn generate_and_send_alerts(
     Ok(())
 }
 
-async fn update_checkpoint(mev_pool: &PgPool, now: DateTime<Utc>) -> Result<()> {
-    checkpoint::put_checkpoint(mev_pool, CheckpointId::Demotion, &now).await?;
-    Ok(())
-}
+pub async fn run_demotion_monitor(
+    relay_pool: &PgPool,
+    mev_pool: &PgPool,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let checkpoint =
+        checkpoint::get_or_init(mev_pool, CheckpointId::Demotion, Position::Timestamp(now)).await?;
+    if checkpoint.paused {
+        debug!("demotion monitor is paused");
+        return Ok(());
+    }
 
-pub async fn run_demotion_monitor(relay_pool: &PgPool, mev_pool: &PgPool) -> Result<()> {
-    let now = Utc::now();
-    let demotions = fetch_demotions(relay_pool, mev_pool, now).await?;
-    generate_and_send_alerts(demotions, relay_pool).await?;
-    update_checkpoint(mev_pool, now).await?;
+    let start = checkpoint.timestamp()?;
+    debug!("checking demotions between {} and {}", &start, &now);
+    let demotions = get_builder_demotions(relay_pool, &start, &now).await?;
+    let demotion_count = demotions.len();
+    let rules = error_rules::load_rules(mev_pool).await?;
+    generate_and_send_alerts(&rules, demotions, relay_pool, mev_pool, now).await?;
+    checkpoint::advance(
+        mev_pool,
+        &checkpoint,
+        Position::Timestamp(now),
+        demotion_count,
+    )
+    .await?;
     Ok(())
 }
@@ -0,0 +1,270 @@
+//! Looks at the pattern of demotions rather than individual ones. Compares the demotions of the
+//! last hour, per builder and per error category, with the rate over the preceding week and sends
+//! a single summarised warning when rates spike.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use itertools::Itertools;
+use sqlx::{PgPool, Row};
+use tracing::debug;
+
+use super::{
+    env::APP_CONFIG,
+    error_rules::{self, ErrorCategory, ErrorRules},
+    Alarm, AlarmType,
+};
+
+const RECENT_WINDOW: Duration = Duration::hours(1);
+const BASELINE_WINDOW: Duration = Duration::days(7);
+/// A category spiking for at least this many builders at once points at the simulator rather
+/// than the builders.
+const SIMULATOR_WIDE_MIN_BUILDERS: usize = 3;
+
+/// Demotions of a builder for a single sim error, either within the recent window or the
+/// baseline window.
+#[derive(Debug)]
+struct DemotionCount {
+    builder: String,
+    sim_error: String,
+    recent: bool,
+    count: i64,
+}
+
+async fn get_demotion_counts(
+    relay_pool: &PgPool,
+    now: DateTime<Utc>,
+) -> Result<Vec<DemotionCount>> {
+    let recent_start = now - RECENT_WINDOW;
+    let baseline_start = recent_start - BASELINE_WINDOW;
+
+    let query = r#"
+        SELECT
+            COALESCE(bb.builder_id, bd.builder_pubkey) AS builder,
+            bd.sim_error,
+            bd.inserted_at > $2 AS recent,
+            COUNT(*) AS count
+        FROM builder_demotions bd
+        LEFT JOIN builder bb
+          ON bd.builder_pubkey = bb.builder_pubkey
+        WHERE bd.inserted_at > $1
+          AND bd.inserted_at <= $3
+        GROUP BY 1, 2, 3
+    "#;
+
+    sqlx::query(query)
+        .bind(baseline_start)
+        .bind(recent_start)
+        .bind(now)
+        .fetch_all(relay_pool)
+        .await
+        .map(|rows| {
+            rows.iter()
+                .map(|row| DemotionCount {
+                    builder: row.get("builder"),
+                    sim_error: row.get::<String, _>("sim_error").trim().to_string(),
+                    recent: row.get("recent"),
+                    count: row.get("count"),
+                })
+                .collect()
+        })
+        .map_err(Into::into)
+}
+
+#[derive(Debug, Default)]
+struct Rate {
+    recent: i64,
+    baseline: i64,
+    builders: HashSet<String>,
+}
+
+impl Rate {
+    fn add(&mut self, count: &DemotionCount) {
+        if count.recent {
+            self.recent += count.count;
+            self.builders.insert(count.builder.clone());
+        } else {
+            self.baseline += count.count;
+        }
+    }
+
+    /// Demotions we'd expect in a recent window, going by the baseline.
+    fn expected(&self) -> f64 {
+        let windows = BASELINE_WINDOW.num_seconds() as f64 / RECENT_WINDOW.num_seconds() as f64;
+        self.baseline as f64 / windows
+    }
+
+    fn is_spike(&self, min_count: i64, spike_factor: f64) -> bool {
+        self.recent >= min_count && self.recent as f64 > self.expected() * spike_factor
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum SpikeScope {
+    Builder(String),
+    Category(ErrorCategory),
+}
+
+#[derive(Debug)]
+struct Spike {
+    scope: SpikeScope,
+    recent: i64,
+    expected: f64,
+    builder_count: usize,
+}
+
+impl Spike {
+    fn is_simulator_wide(&self) -> bool {
+        matches!(self.scope, SpikeScope::Category(_))
+            && self.builder_count >= SIMULATOR_WIDE_MIN_BUILDERS
+    }
+}
+
+fn find_spikes(
+    rules: &ErrorRules,
+    counts: &[DemotionCount],
+    min_count: i64,
+    spike_factor: f64,
+) -> Vec<Spike> {
+    let mut builder_rates: HashMap<&str, Rate> = HashMap::new();
+    let mut category_rates: HashMap<ErrorCategory, Rate> = HashMap::new();
+    for count in counts {
+        builder_rates
+            .entry(count.builder.as_str())
+            .or_default()
+            .add(count);
+        category_rates
+            .entry(rules.classify(&count.sim_error))
+            .or_default()
+            .add(count);
+    }
+
+    let to_spike = |scope: SpikeScope, rate: &Rate| Spike {
+        scope,
+        recent: rate.recent,
+        expected: rate.expected(),
+        builder_count: rate.builders.len(),
+    };
+
+    let builder_spikes = builder_rates
+        .iter()
+        .filter(|(_, rate)| rate.is_spike(min_count, spike_factor))
+        .map(|(builder, rate)| to_spike(SpikeScope::Builder(builder.to_string()), rate));
+    let category_spikes = category_rates
+        .iter()
+        .filter(|(_, rate)| rate.is_spike(min_count, spike_factor))
+        .map(|(category, rate)| to_spike(SpikeScope::Category(*category), rate));
+
+    builder_spikes
+        .chain(category_spikes)
+        .sorted_by_key(|spike| std::cmp::Reverse(spike.recent))
+        .collect()
+}
+
+fn format_spikes(spikes: &[Spike]) -> String {
+    let lines = spikes.iter().map(|spike| {
+        let expected = format!("{:.1}", spike.expected);
+        match &spike.scope {
+            SpikeScope::Builder(builder) => format!(
+                "builder {}: {} demotions in the last hour, baseline {} per hour",
+                builder, spike.recent, expected
+            ),
+            SpikeScope::Category(category) => {
+                let mut line = format!(
+                    "category {}: {} demotions across {} builders in the last hour, baseline {} per hour",
+                    category, spike.recent, spike.builder_count, expected
+                );
+                if spike.is_simulator_wide() {
+                    line.push_str(", likely simulator wide");
+                }
+                line
+            }
+        }
+    });
+
+    format!(
+        "demotion rate spike on {}\n\n{}",
+        APP_CONFIG.network,
+        lines.collect::<Vec<_>>().join("\n")
+    )
+}
+
+pub async fn run_demotion_rate_monitor(
+    relay_pool: &PgPool,
+    mev_pool: &PgPool,
+    now: DateTime<Utc>,
+    alarm: &mut Alarm,
+) -> Result<()> {
+    let counts = get_demotion_counts(relay_pool, now).await?;
+    let rules = error_rules::load_rules(mev_pool).await?;
+    let spikes = find_spikes(
+        &rules,
+        &counts,
+        APP_CONFIG.demotion_rate_min_count,
+        APP_CONFIG.demotion_rate_spike_factor,
+    );
+    debug!("found {} demotion rate spikes", spikes.len());
+
+    if !spikes.is_empty() {
+        // The alarm is throttled, a lasting spike is summarised once an hour.
+        alarm
+            .fire(&format_spikes(&spikes), &AlarmType::Telegram)
+            .await;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(builder: &str, sim_error: &str, recent: bool, count: i64) -> DemotionCount {
+        DemotionCount {
+            builder: builder.to_string(),
+            sim_error: sim_error.to_string(),
+            recent,
+            count,
+        }
+    }
+
+    #[test]
+    fn test_builder_spike_against_baseline() {
+        let counts = vec![
+            // Roughly one demotion an hour is normal for titan.
+            count(
+                "titan",
+                "simulation failed: invalid merkle root",
+                false,
+                168,
+            ),
+            count("titan", "simulation failed: invalid merkle root", true, 2),
+            count("beaverbuild", "simulation failed: some new error", true, 6),
+        ];
+        let spikes = find_spikes(&ErrorRules::seeded(), &counts, 5, 3.0);
+
+        assert_eq!(spikes.len(), 2);
+        assert!(spikes
+            .iter()
+            .any(|spike| spike.scope == SpikeScope::Builder("beaverbuild".to_string())));
+        assert!(spikes
+            .iter()
+            .any(|spike| spike.scope == SpikeScope::Category(ErrorCategory::Alert)));
+        assert!(spikes.iter().all(|spike| !spike.is_simulator_wide()));
+    }
+
+    #[test]
+    fn test_simulator_wide_spike() {
+        let counts = ["titan", "beaverbuild", "flashbots"]
+            .iter()
+            .map(|builder| count(builder, "simulation queue timed out", true, 2))
+            .collect_vec();
+        let spikes = find_spikes(&ErrorRules::seeded(), &counts, 5, 3.0);
+
+        // No single builder spiked, but the category did, for all of them.
+        assert_eq!(spikes.len(), 1);
+        assert_eq!(spikes[0].scope, SpikeScope::Category(ErrorCategory::Ignore));
+        assert!(spikes[0].is_simulator_wide());
+    }
+}
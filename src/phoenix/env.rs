@@ -4,6 +4,7 @@ use reqwest::Url;
 use serde::Deserialize;
 use serde_with::{serde_as, DisplayFromStr};
 
+use crate::chain_time::ChainTime;
 use crate::env::{
     deserialize_hash_set, deserialize_network, deserialize_urls, get_app_config, Network,
 };
@@ -11,17 +12,48 @@ use crate::env::{
 #[serde_as]
 #[derive(Deserialize)]
 pub struct AppConfig {
+    /// Port of the admin routes, see `admin`.
+    #[serde(default = "default_admin_port")]
+    pub admin_port: u16,
+    /// Bearer token required by the admin routes, which are disabled when it is not set.
+    pub admin_token: Option<String>,
+    /// Number of slots, up to the latest analysed slot, checked for gaps in auction analysis.
+    #[serde(default = "default_auction_analysis_window_slots")]
+    pub auction_analysis_window_slots: i64,
     #[serde(default = "default_wait")]
     pub canonical_wait_minutes: i64,
+    /// Number of slots a consensus node head may trail the majority head before it is considered
+    /// unhealthy.
+    #[serde(default = "default_consensus_head_max_slot_lag")]
+    pub consensus_head_max_slot_lag: i64,
     #[serde(deserialize_with = "deserialize_urls")]
     pub consensus_nodes: Vec<Url>,
     pub database_url: String,
+    /// Minimum number of demotions in the last hour before a rate is considered a spike.
+    #[serde(default = "default_demotion_rate_min_count")]
+    pub demotion_rate_min_count: i64,
+    /// How many times the baseline hourly rate the demotions of the last hour must exceed.
+    #[serde(default = "default_demotion_rate_spike_factor")]
+    pub demotion_rate_spike_factor: f64,
+    /// Check inclusion as soon as beacon nodes report a new head or block, and again once the
+    /// canonical wait has passed, next to the polling inclusion monitor.
+    #[serde(default)]
+    pub ff_inclusion_events: bool,
     /// Skip global checks in `run_ops_monitors` and only check for beacon/sim node status.
     #[serde(default)]
     pub ff_node_check_only: bool,
+    /// Record promotion decisions without promoting builders, to evaluate rule changes safely.
+    #[serde(default)]
+    pub ff_promotion_shadow_mode: bool,
     #[serde_as(as = "DisplayFromStr")]
     pub geo: Geo,
-    pub loki_url: String,
+    /// Number of delivered payloads the inclusion and publish stats monitors each check
+    /// concurrently. The ops database pools get a connection per check.
+    #[serde(default = "default_inclusion_check_concurrency")]
+    pub inclusion_check_concurrency: usize,
+    /// Read payload api logs from this JSON lines file instead of Loki.
+    pub log_file: Option<String>,
+    pub loki_url: Option<String>,
     /// Minimum number of missed slots per check interval to trigger an alert
     #[serde(default = "default_missed_slots_alert_threshold")]
     pub missed_slots_alert_threshold: i64,
@@ -32,6 +64,23 @@ pub struct AppConfig {
     pub network: Network,
     pub opsgenie_api_key: String,
     pub port: u16,
+    /// Replication lag above which a database is considered unhealthy.
+    #[serde(default = "default_postgres_max_replication_lag_seconds")]
+    pub postgres_max_replication_lag_seconds: u64,
+    /// Queries running longer than this are reported.
+    #[serde(default = "default_postgres_max_query_seconds")]
+    pub postgres_max_query_seconds: u64,
+    /// How far the share of next epoch slots with a registered proposer may drop below the
+    /// average of recent epochs before we warn, e.g. 0.2 for 20 percentage points.
+    #[serde(default = "default_proposer_coverage_max_drop")]
+    pub proposer_coverage_max_drop: f64,
+    /// Fraction of `maxmemory` redis may use before it is considered unhealthy.
+    #[serde(default = "default_redis_max_memory_usage")]
+    pub redis_max_memory_usage: f64,
+    #[serde(default = "default_redis_max_ping_ms")]
+    pub redis_max_ping_ms: u64,
+    /// Redis is only monitored when this is set.
+    pub redis_uri: Option<String>,
     pub relay_database_url: String,
     pub telegram_api_key: String,
     pub telegram_alerts_channel_id: String,
@@ -40,6 +89,15 @@ pub struct AppConfig {
     pub telegram_warnings_channel_id: String,
     #[serde(deserialize_with = "deserialize_urls")]
     pub validation_nodes: Vec<Url>,
+    /// Number of blocks a validation node may trail the highest node before it is considered
+    /// unhealthy.
+    #[serde(default = "default_validation_node_max_block_lag")]
+    pub validation_node_max_block_lag: u64,
+    /// Maximum age of the latest block a validation node has seen.
+    #[serde(default = "default_validation_node_max_block_age_seconds")]
+    pub validation_node_max_block_age_seconds: i64,
+    #[serde(default = "default_validation_node_min_peer_count")]
+    pub validation_node_min_peer_count: u64,
     #[serde(default = "default_unsynced_nodes_threshold_tg_warning")]
     pub unsynced_nodes_threshold_tg_warning: usize,
     #[serde(default = "default_unsynced_nodes_threshold_og_alert")]
@@ -51,10 +109,66 @@ pub struct AppConfig {
     pub trusted_builder_ids: HashSet<String>,
 }
 
+fn default_admin_port() -> u16 {
+    3010
+}
+
+fn default_auction_analysis_window_slots() -> i64 {
+    300 // 1 hour
+}
+
+fn default_consensus_head_max_slot_lag() -> i64 {
+    2
+}
+
+fn default_demotion_rate_min_count() -> i64 {
+    5
+}
+
+fn default_demotion_rate_spike_factor() -> f64 {
+    3.0
+}
+
+fn default_inclusion_check_concurrency() -> usize {
+    2
+}
+
 fn default_max_auction_analysis_slot_lag() -> u32 {
     50
 }
 
+fn default_postgres_max_replication_lag_seconds() -> u64 {
+    30
+}
+
+fn default_postgres_max_query_seconds() -> u64 {
+    300
+}
+
+fn default_proposer_coverage_max_drop() -> f64 {
+    0.2
+}
+
+fn default_redis_max_memory_usage() -> f64 {
+    0.9
+}
+
+fn default_redis_max_ping_ms() -> u64 {
+    100
+}
+
+fn default_validation_node_max_block_lag() -> u64 {
+    2
+}
+
+fn default_validation_node_max_block_age_seconds() -> i64 {
+    60
+}
+
+fn default_validation_node_min_peer_count() -> u64 {
+    1
+}
+
 fn default_unsynced_nodes_threshold_og_alert() -> usize {
     2
 }
@@ -113,12 +227,11 @@ impl str::FromStr for Geo {
 
 pub static APP_CONFIG: LazyLock<AppConfig> = LazyLock::new(get_app_config);
 
+pub static CHAIN_TIME: LazyLock<ChainTime> =
+    LazyLock::new(|| ChainTime::for_network(&APP_CONFIG.network));
+
 impl AppConfig {
     pub fn relay_analytics_url(&self) -> &str {
-        match self.network {
-            Network::Mainnet => "https://relay-analytics.ultrasound.money",
-            Network::Holesky => "https://relay-analytics-holesky.ultrasound.money",
-            Network::Hoodi => "https://relay-analytics-hoodi.ultrasound.money",
-        }
+        &self.network.relay_analytics_url
     }
 }
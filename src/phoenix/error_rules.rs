@@ -0,0 +1,352 @@
+//! Rules classifying the simulation errors builders get demoted for. Rules live in the
+//! `sim_error_rules` table and are reloaded on every monitor run, so new simulator errors don't
+//! need a deploy. The initial rules are seeded by the migration creating the table.
+
+use std::{fmt, str};
+
+use anyhow::{anyhow, Result};
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::{DateTime, Duration, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::{postgres::PgRow, PgPool, Row};
+use tracing::{debug, error, warn};
+
+use super::{demotion_monitor::get_builder_demotions, ServerState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// Not broadcast on telegram, the builder may be re-promoted.
+    Ignore,
+    /// Broadcast as a warning, the builder may be re-promoted.
+    Promotable,
+    /// Like promotable, but only for trusted builders, an alert otherwise.
+    TrustedPromotable,
+    /// Broadcast as an alert, the builder stays demoted. The category of unmatched errors.
+    Alert,
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let str = match self {
+            ErrorCategory::Ignore => "ignore",
+            ErrorCategory::Promotable => "promotable",
+            ErrorCategory::TrustedPromotable => "trusted_promotable",
+            ErrorCategory::Alert => "alert",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+impl str::FromStr for ErrorCategory {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "ignore" => Ok(ErrorCategory::Ignore),
+            "promotable" => Ok(ErrorCategory::Promotable),
+            "trusted_promotable" => Ok(ErrorCategory::TrustedPromotable),
+            "alert" => Ok(ErrorCategory::Alert),
+            _ => Err(anyhow!("invalid error category: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchType {
+    /// Error starts with the pattern, accounts for dynamic info at the end of errors.
+    Prefix,
+    Regex,
+}
+
+impl str::FromStr for MatchType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "prefix" => Ok(MatchType::Prefix),
+            "regex" => Ok(MatchType::Regex),
+            _ => Err(anyhow!("invalid match type: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuleDefinition {
+    pub match_type: MatchType,
+    pub pattern: String,
+    pub category: ErrorCategory,
+}
+
+#[derive(Debug)]
+enum Matcher {
+    Prefix(String),
+    Regex(Regex),
+}
+
+#[derive(Debug)]
+struct ErrorRule {
+    matcher: Matcher,
+    category: ErrorCategory,
+}
+
+impl ErrorRule {
+    fn new(definition: &RuleDefinition) -> Result<Self> {
+        let matcher = match definition.match_type {
+            MatchType::Prefix => Matcher::Prefix(definition.pattern.clone()),
+            MatchType::Regex => Matcher::Regex(Regex::new(&definition.pattern)?),
+        };
+        Ok(Self {
+            matcher,
+            category: definition.category,
+        })
+    }
+
+    fn is_match(&self, error: &str) -> bool {
+        match &self.matcher {
+            Matcher::Prefix(prefix) => error.starts_with(prefix.as_str()),
+            Matcher::Regex(regex) => regex.is_match(error),
+        }
+    }
+}
+
+/// Ordered rules, the first matching rule decides the category of an error.
+#[derive(Debug)]
+pub struct ErrorRules {
+    rules: Vec<ErrorRule>,
+    /// Ids of stored rules which failed to compile and were left out.
+    invalid_rule_ids: Vec<i32>,
+}
+
+impl ErrorRules {
+    pub fn from_definitions(definitions: &[RuleDefinition]) -> Result<Self> {
+        let rules = definitions
+            .iter()
+            .map(ErrorRule::new)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            rules,
+            invalid_rule_ids: Vec::new(),
+        })
+    }
+
+    /// The rules seeded by the migration creating `sim_error_rules`, for tests.
+    #[cfg(test)]
+    pub fn seeded() -> Self {
+        let migration = include_str!("../../migrations/0028_sim_error_rules.up.sql");
+        let row = Regex::new(r"\(\d+, '(\w+)', '((?:[^']|'')*)', '(\w+)'\)").unwrap();
+        let definitions = row
+            .captures_iter(migration)
+            .map(|captures| RuleDefinition {
+                match_type: captures[1].parse().unwrap(),
+                pattern: captures[2].replace("''", "'"),
+                category: captures[3].parse().unwrap(),
+            })
+            .collect::<Vec<_>>();
+        Self::from_definitions(&definitions).unwrap()
+    }
+
+    pub fn classify(&self, error: &str) -> ErrorCategory {
+        self.rules
+            .iter()
+            .find(|rule| rule.is_match(error))
+            .map(|rule| rule.category)
+            .unwrap_or(ErrorCategory::Alert)
+    }
+
+    pub fn is_ignored(&self, error: &str) -> bool {
+        self.classify(error) == ErrorCategory::Ignore
+    }
+
+    pub fn is_promotable(&self, error: &str) -> bool {
+        matches!(
+            self.classify(error),
+            ErrorCategory::Ignore | ErrorCategory::Promotable
+        )
+    }
+
+    pub fn is_trusted_promotable(&self, error: &str) -> bool {
+        self.classify(error) == ErrorCategory::TrustedPromotable
+    }
+}
+
+fn rule_from_row(row: &PgRow) -> Result<ErrorRule> {
+    let definition = RuleDefinition {
+        match_type: row.get::<String, _>("match_type").parse()?,
+        pattern: row.get("pattern"),
+        category: row.get::<String, _>("category").parse()?,
+    };
+    ErrorRule::new(&definition)
+}
+
+/// Load the current rules. Rules which fail to compile are skipped and logged, a bad rule shouldn't
+/// take down the monitors.
+pub async fn load_rules(mev_pool: &PgPool) -> Result<ErrorRules> {
+    let rows = sqlx::query(
+        "
+        SELECT id, match_type, pattern, category
+        FROM sim_error_rules
+        ORDER BY priority ASC, id ASC
+        ",
+    )
+    .fetch_all(mev_pool)
+    .await?;
+
+    if rows.is_empty() {
+        warn!("no sim error rules stored, all sim errors are alerted on");
+    }
+
+    let mut rules = Vec::new();
+    let mut invalid_rule_ids = Vec::new();
+    for row in &rows {
+        let id: i32 = row.get("id");
+        match rule_from_row(row) {
+            Ok(rule) => rules.push(rule),
+            Err(err) => {
+                error!(id, "skipping invalid sim error rule: {:#}", err);
+                invalid_rule_ids.push(id);
+            }
+        }
+    }
+
+    debug!("loaded {} sim error rules", rules.len());
+    Ok(ErrorRules {
+        rules,
+        invalid_rule_ids,
+    })
+}
+
+#[derive(Deserialize)]
+pub struct PreviewRequest {
+    rules: Vec<RuleDefinition>,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct ChangedDemotion {
+    slot: i64,
+    builder_id: Option<String>,
+    sim_error: String,
+    current: ErrorCategory,
+    proposed: ErrorCategory,
+}
+
+#[derive(Serialize)]
+pub struct PreviewResponse {
+    demotion_count: usize,
+    changed: Vec<ChangedDemotion>,
+}
+
+/// Show how past demotions would be classified under a proposed rule set, compared to the
+/// current rules. Defaults to the last seven days.
+pub async fn preview_rules(
+    State(state): State<ServerState>,
+    Json(request): Json<PreviewRequest>,
+) -> Result<Json<PreviewResponse>, (StatusCode, String)> {
+    let internal_error = |err: anyhow::Error| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string());
+
+    let proposed = ErrorRules::from_definitions(&request.rules)
+        .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+    let current = load_rules(&state.mev_pool).await.map_err(internal_error)?;
+    // Comparing against a partial rule set would show changes which aren't there.
+    if !current.invalid_rule_ids.is_empty() {
+        return Err((
+            StatusCode::CONFLICT,
+            format!(
+                "stored sim error rules {:?} are invalid, fix them before previewing",
+                current.invalid_rule_ids
+            ),
+        ));
+    }
+
+    let end = request.end.unwrap_or_else(Utc::now);
+    let start = request.start.unwrap_or(end - Duration::days(7));
+    let demotions = get_builder_demotions(&state.relay_pool, &start, &end)
+        .await
+        .map_err(internal_error)?;
+
+    let changed = demotions
+        .iter()
+        .filter_map(|demotion| {
+            let current = current.classify(&demotion.sim_error);
+            let proposed = proposed.classify(&demotion.sim_error);
+            (current != proposed).then(|| ChangedDemotion {
+                slot: demotion.slot,
+                builder_id: demotion.builder_id.clone(),
+                sim_error: demotion.sim_error.clone(),
+                current,
+                proposed,
+            })
+        })
+        .collect();
+
+    Ok(Json(PreviewResponse {
+        demotion_count: demotions.len(),
+        changed,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_rules() {
+        let rules = ErrorRules::seeded();
+        assert_eq!(rules.rules.len(), 10);
+        assert_eq!(
+            rules.classify("Post \"http://prio-load-balancer:80\": context deadline exceeded (Client.Timeout exceeded while awaiting headers)"),
+            ErrorCategory::Ignore
+        );
+        assert_eq!(
+            rules.classify("simulation failed: unknown ancestor"),
+            ErrorCategory::Ignore
+        );
+        // Prefix rules allow extra info at the end.
+        assert_eq!(
+            rules.classify("simulation failed: incorrect gas limit set, expected 30000000"),
+            ErrorCategory::Promotable
+        );
+        assert_eq!(
+            rules.classify("simulation failed: invalid merkle root"),
+            ErrorCategory::TrustedPromotable
+        );
+        assert_eq!(rules.classify("invalid error"), ErrorCategory::Alert);
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let rules = ErrorRules::from_definitions(&[
+            RuleDefinition {
+                match_type: MatchType::Regex,
+                pattern: "^simulation failed: .* balance".to_string(),
+                category: ErrorCategory::Alert,
+            },
+            RuleDefinition {
+                match_type: MatchType::Prefix,
+                pattern: "simulation failed:".to_string(),
+                category: ErrorCategory::Promotable,
+            },
+        ])
+        .unwrap();
+
+        assert_eq!(
+            rules.classify("simulation failed: insufficient balance for transfer"),
+            ErrorCategory::Alert
+        );
+        assert!(rules.is_promotable("simulation failed: parent block not found"));
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        let definitions = [RuleDefinition {
+            match_type: MatchType::Regex,
+            pattern: "simulation failed: (".to_string(),
+            category: ErrorCategory::Ignore,
+        }];
+        assert!(ErrorRules::from_definitions(&definitions).is_err());
+    }
+}
@@ -0,0 +1,73 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::OnceCell;
+
+use crate::beacon_api::{BeaconApi, BeaconBlock};
+
+/// Blocks by slot for the duration of a single inclusion run. Checks running concurrently often
+/// look up the same slots, e.g. the slot itself and its parent slot for the reorg check, this
+/// makes sure we only ask the beacon nodes once per slot. Failed lookups are not cached.
+pub struct BlockCache {
+    beacon_api: BeaconApi,
+    blocks: Mutex<HashMap<i64, Arc<OnceCell<Option<BeaconBlock>>>>>,
+}
+
+impl BlockCache {
+    pub fn new(beacon_api: BeaconApi) -> Self {
+        Self {
+            beacon_api,
+            blocks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn block_by_slot(&self, slot: i64) -> anyhow::Result<Option<BeaconBlock>> {
+        let cell = self.blocks.lock().unwrap().entry(slot).or_default().clone();
+
+        cell.get_or_try_init(|| self.beacon_api.block_by_slot_any(slot))
+            .await
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beacon_api::mock::{BlockReply, MockBeaconNode};
+    use reqwest::StatusCode;
+
+    #[tokio::test]
+    async fn test_caches_successful_lookups_only() {
+        let node = MockBeaconNode::start();
+        node.set_block(10, "0x9a", 1000);
+        node.set_block_reply(11, BlockReply::Error(StatusCode::INTERNAL_SERVER_ERROR));
+        let blocks = BlockCache::new(BeaconApi::new(&[node.url()]));
+
+        let (first, second) = tokio::join!(blocks.block_by_slot(10), blocks.block_by_slot(10));
+        assert_eq!(first.unwrap().unwrap().execution_payload.block_hash, "0x9a");
+        assert_eq!(
+            second.unwrap().unwrap().execution_payload.block_hash,
+            "0x9a"
+        );
+        assert!(blocks.block_by_slot(12).await.unwrap().is_none());
+        assert!(blocks.block_by_slot(12).await.unwrap().is_none());
+        assert_eq!(node.block_requests(10), 1);
+        assert_eq!(node.block_requests(12), 1);
+
+        assert!(blocks.block_by_slot(11).await.is_err());
+        node.set_block(11, "0x76", 1001);
+        assert_eq!(
+            blocks
+                .block_by_slot(11)
+                .await
+                .unwrap()
+                .unwrap()
+                .execution_payload
+                .block_hash,
+            "0x76"
+        );
+        assert_eq!(node.block_requests(11), 2);
+    }
+}
@@ -0,0 +1,201 @@
+//! Checks delivered payloads driven by head and block events rather than by polling. A slot is
+//! checked as soon as its block arrives, and checked again once the head is the canonical wait
+//! past it, the same delay the polling monitor uses, so payloads which were reorged out are still
+//! reported. The polling monitor still runs as a safety net. Progress of both checks is kept in
+//! slot checkpoints, so a restart catches up on the slots it missed.
+
+use std::ops::RangeInclusive;
+
+use anyhow::anyhow;
+use chrono::Duration;
+use sqlx::PgPool;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    beacon_api::{BeaconApi, BeaconEvent},
+    chain_time::Slot,
+    phoenix::{
+        checkpoint::{self, CheckpointId, Position},
+        env::{APP_CONFIG, CHAIN_TIME},
+    },
+};
+
+use super::{
+    block_cache::BlockCache, check_missing_payload, get_delivered_payloads_for_slot,
+    report_missed_slot, LokiClient,
+};
+
+const EVENT_TOPICS: &[&str] = &["head", "block", "chain_reorg"];
+
+/// After a gap in events we only catch up on this many slots, older slots are left to the
+/// polling monitor.
+const MAX_CATCH_UP_SLOTS: i64 = 32;
+
+async fn check_slot(
+    beacon_api: &BeaconApi,
+    loki_client: &LokiClient,
+    relay_pool: &PgPool,
+    mev_pool: &PgPool,
+    slot: i64,
+) -> anyhow::Result<usize> {
+    let payloads = get_delivered_payloads_for_slot(relay_pool, slot).await?;
+    let blocks = BlockCache::new(beacon_api.clone());
+    for payload in &payloads {
+        if let Some(miss) =
+            check_missing_payload(&blocks, loki_client, mev_pool, payload, relay_pool).await?
+        {
+            report_missed_slot(mev_pool, &miss).await?;
+        }
+    }
+    debug!(
+        slot,
+        payloads = payloads.len(),
+        "checked slot on beacon event"
+    );
+    Ok(payloads.len())
+}
+
+/// Number of slots the head has to be past a slot before we check it.
+fn canonical_wait_slots() -> i64 {
+    Duration::minutes(APP_CONFIG.canonical_wait_minutes).num_seconds()
+        / CHAIN_TIME.slot_duration().num_seconds()
+}
+
+/// Slots to check when the checkpoint is at `last_checked_slot` and the head reached `slot`.
+fn slots_to_check(last_checked_slot: i64, slot: i64) -> RangeInclusive<i64> {
+    (last_checked_slot + 1).max(slot - MAX_CATCH_UP_SLOTS + 1)..=slot
+}
+
+/// Slots to check again after a reorg of `depth` slots up to `slot`. Slots within the canonical
+/// wait are checked again anyway once the wait has passed.
+fn slots_to_recheck(slot: i64, depth: i64, wait_slots: i64) -> RangeInclusive<i64> {
+    (slot - depth.min(MAX_CATCH_UP_SLOTS) + 1)..=(slot - wait_slots)
+}
+
+/// Check the slots from the checkpoint `id` up to `slot`, moving the checkpoint along. The
+/// checkpoint stops before a slot which fails, so the next event tries it again. The first call
+/// only checks `slot` itself.
+async fn check_up_to(
+    beacon_api: &BeaconApi,
+    loki_client: &LokiClient,
+    relay_pool: &PgPool,
+    mev_pool: &PgPool,
+    id: CheckpointId,
+    slot: i64,
+) -> anyhow::Result<()> {
+    let checkpoint = checkpoint::get_or_init(mev_pool, id, Position::Slot(Slot(slot - 1))).await?;
+    if checkpoint.paused {
+        debug!(monitor = %id, slot, "event driven inclusion checks are paused");
+        return Ok(());
+    }
+    let last_checked_slot = checkpoint.slot()?.0;
+    if slot <= last_checked_slot {
+        return Ok(());
+    }
+
+    let mut checked_up_to = last_checked_slot;
+    let mut payload_count = 0;
+    let mut result = Ok(());
+    for slot in slots_to_check(last_checked_slot, slot) {
+        match check_slot(beacon_api, loki_client, relay_pool, mev_pool, slot).await {
+            Ok(count) => {
+                payload_count += count;
+                checked_up_to = slot;
+            }
+            Err(err) => {
+                result = Err(err.context(format!("failed to check slot {}", slot)));
+                break;
+            }
+        }
+    }
+
+    if checked_up_to > last_checked_slot {
+        checkpoint::advance(
+            mev_pool,
+            &checkpoint,
+            Position::Slot(Slot(checked_up_to)),
+            payload_count,
+        )
+        .await?;
+    }
+    result
+}
+
+pub async fn run_inclusion_event_monitor(
+    relay_pool: &PgPool,
+    mev_pool: &PgPool,
+    loki_client: &LokiClient,
+) -> anyhow::Result<()> {
+    let beacon_api = BeaconApi::new(&APP_CONFIG.consensus_nodes);
+    // Duplicate events from different nodes are skipped by only moving forward.
+    let mut receiver = beacon_api.subscribe_all(EVENT_TOPICS);
+
+    info!("started event driven inclusion monitor");
+
+    let wait_slots = canonical_wait_slots();
+    let mut last_seen_slot: Option<i64> = None;
+
+    while let Some(event) = receiver.recv().await {
+        match event {
+            BeaconEvent::Head { slot } | BeaconEvent::Block { slot } => {
+                if last_seen_slot.is_some_and(|last_seen_slot| slot <= last_seen_slot) {
+                    continue;
+                }
+                last_seen_slot = Some(slot);
+                let checks = [
+                    (CheckpointId::InclusionEvents, slot),
+                    (CheckpointId::InclusionEventsCanonical, slot - wait_slots),
+                ];
+                for (id, slot) in checks {
+                    if let Err(err) =
+                        check_up_to(&beacon_api, loki_client, relay_pool, mev_pool, id, slot).await
+                    {
+                        error!(monitor = %id, slot, "failed to check slots on beacon event: {:#}", err);
+                    }
+                }
+            }
+            BeaconEvent::ChainReorg { slot, depth, .. } => {
+                let slots = slots_to_recheck(slot, depth, wait_slots);
+                if slots.is_empty() {
+                    continue;
+                }
+                warn!(slot, depth, "deep chain reorg, rechecking reorged slots");
+                for slot in slots {
+                    if let Err(err) =
+                        check_slot(&beacon_api, loki_client, relay_pool, mev_pool, slot).await
+                    {
+                        error!(slot, "failed to check slot on beacon event: {:#}", err);
+                    }
+                }
+            }
+        }
+    }
+
+    Err(anyhow!("all beacon event subscriptions ended"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slots_to_check() {
+        assert_eq!(slots_to_check(99, 100), 100..=100);
+        // Skipped slots are checked when the next block arrives.
+        assert_eq!(slots_to_check(98, 100), 99..=100);
+        // Duplicate events from other nodes check nothing.
+        assert!(slots_to_check(100, 100).is_empty());
+        // Long gaps, e.g. after a restart, are left to the polling monitor.
+        assert_eq!(slots_to_check(0, 100), 69..=100);
+    }
+
+    #[test]
+    fn test_slots_to_recheck() {
+        // Reorgs within the canonical wait are left to the check at the canonical wait.
+        assert!(slots_to_recheck(100, 2, 10).is_empty());
+        assert!(slots_to_recheck(100, 10, 10).is_empty());
+        // Deeper reorgs recheck the slots already past the wait.
+        assert_eq!(slots_to_recheck(100, 12, 10), 89..=90);
+        assert_eq!(slots_to_recheck(100, 1000, 10), 69..=90);
+    }
+}
@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+
+use super::{
+    source::{LogQuery, LogSource},
+    JsonValue,
+};
+
+/// Reads logs from a file with one JSON log per line, as written by the relay. Useful for tests
+/// and environments without Loki.
+///
+/// A file holds the logs of a single app, so the app of a query is not checked. The geo is only
+/// checked for logs which carry one. Logs are taken to be recent, `since` is ignored.
+pub struct FileSource {
+    path: PathBuf,
+}
+
+impl FileSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+fn log_time(log: &JsonValue) -> Option<DateTime<Utc>> {
+    log.get("time")
+        .and_then(|time| time.as_str())
+        .and_then(|time| time.parse().ok())
+}
+
+fn matches(query: &LogQuery, log: &JsonValue) -> bool {
+    if let (Some(geo), Some(log_geo)) = (&query.geo, log["geo"].as_str()) {
+        if log_geo != geo {
+            return false;
+        }
+    }
+
+    if let Some(level) = &query.level {
+        if log["level"].as_str() != Some(level) {
+            return false;
+        }
+    }
+
+    if let Some(slot) = query.slot {
+        // The relay logs slots as numbers in some places and as strings in others.
+        let log_slot = match &log["slot"] {
+            JsonValue::Number(number) => number.as_i64(),
+            JsonValue::String(str) => str.parse().ok(),
+            _ => None,
+        };
+        if log_slot != Some(slot) {
+            return false;
+        }
+    }
+
+    if let Some(message) = &query.message {
+        if !log["msg"]
+            .as_str()
+            .is_some_and(|msg| msg.contains(message.as_str()))
+        {
+            return false;
+        }
+    }
+
+    if let Some((start, end)) = &query.range {
+        match log_time(log) {
+            Some(time) if &time >= start && &time < end => {}
+            _ => return false,
+        }
+    }
+
+    true
+}
+
+#[async_trait]
+impl LogSource for FileSource {
+    async fn query(&self, query: &LogQuery) -> anyhow::Result<Vec<JsonValue>> {
+        let contents = tokio::fs::read_to_string(&self.path)
+            .await
+            .with_context(|| format!("failed to read log file {}", self.path.display()))?;
+
+        let logs = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).context("failed to parse log as JSON"))
+            .collect::<anyhow::Result<Vec<JsonValue>>>()?;
+
+        Ok(logs
+            .into_iter()
+            .filter(|log| matches(query, log))
+            .sorted_by_key(log_time)
+            .collect())
+    }
+}
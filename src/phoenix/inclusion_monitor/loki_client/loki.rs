@@ -0,0 +1,198 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use itertools::Itertools;
+use reqwest::Url;
+use tracing::warn;
+
+use super::{
+    source::{LogQuery, LogSource},
+    JsonValue,
+};
+
+/// Parses loki query response into a list of log lines. Oldest log line is first.
+pub(super) fn loki_res_into_json_logs(text: &str) -> anyhow::Result<Vec<JsonValue>> {
+    let log_response_json: JsonValue = match serde_json::from_str(text) {
+        Ok(json) => json,
+        Err(e) => {
+            warn!("failed to parse payload log request body as json: {}", e);
+            return Ok(Vec::new());
+        }
+    };
+
+    // Loki queries many nodes, each may return a stream of logs. We first discard all metadata
+    // leaving only a list of streams.
+    let streams = log_response_json
+        // The rest is response metadata
+        .get("data")
+        // These are the lines, the rest is metadata about the lines
+        .and_then(|data| data.get("result"))
+        .and_then(|result| result.as_array())
+        .ok_or_else(|| anyhow::anyhow!("failed to parse payload log response"))?;
+
+    // Each stream may contain many log lines. We discard the metadata and keep only the log lines.
+    let log_values = streams
+        .iter()
+        .map(|line| {
+            line.get("values")
+                .and_then(|values| values.as_array())
+                .ok_or_else(|| anyhow::anyhow!("failed to parse payload log response"))
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+    // Each value is an array of two values: a timestamp and the log line.
+    let log_tuples = log_values
+        .iter()
+        .map(|value| {
+            let raw_timestamp = value
+                .get(0)
+                .and_then(|timestamp| timestamp.as_str())
+                .ok_or_else(|| {
+                    anyhow::anyhow!("failed to parse timestamp from payload log response")
+                })?;
+            let timestamp: DateTime<Utc> = raw_timestamp
+                .parse::<i64>()
+                .map(|timestamp| Utc.timestamp_nanos(timestamp))
+                .context("failed to parse nanosecond timestamp as i64")?;
+            let log_str = value
+                .get(1)
+                .and_then(|log| log.as_str())
+                .ok_or_else(|| anyhow::anyhow!("failed to parse log from payload log response"))?;
+            let log_value = serde_json::from_str(log_str).context("failed to parse log as JSON")?;
+            anyhow::Ok((timestamp, log_value))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let log_lines = log_tuples
+        .into_iter()
+        .sorted_by_key(|(timestamp, _)| *timestamp)
+        .map(|(_, log)| log)
+        .collect::<Vec<_>>();
+
+    Ok(log_lines)
+}
+
+/// Quote a string for use in a LogQL line filter.
+fn quote(str: &str) -> String {
+    format!("\"{}\"", str.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+pub(super) fn to_logql(query: &LogQuery) -> String {
+    let mut labels = vec![format!("app={}", quote(&query.app))];
+    if let Some(level) = &query.level {
+        labels.push(format!("level={}", quote(level)));
+    }
+
+    let mut logql = format!("{{{}}}", labels.join(","));
+    if let Some(slot) = query.slot {
+        logql.push_str(&format!(r#" |= `"slot":"{slot}"`"#));
+    }
+    if let Some(message) = &query.message {
+        logql.push_str(&format!(" |= {}", quote(message)));
+    }
+    // Streams carry no geo label, the geo can only be told from log lines which have one.
+    if let Some(geo) = &query.geo {
+        logql.push_str(&format!(
+            r#" | json geo="geo" | geo="" or geo={}"#,
+            quote(geo)
+        ));
+    }
+    logql
+}
+
+fn timestamp_nanos(date_time: &DateTime<Utc>) -> anyhow::Result<String> {
+    date_time
+        .timestamp_nanos_opt()
+        .map(|nanos| nanos.to_string())
+        .with_context(|| format!("{} out of range for nanosecond timestamp", date_time))
+}
+
+pub struct LokiSource {
+    client: reqwest::Client,
+    server_url: String,
+}
+
+impl LokiSource {
+    pub fn new(server_url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            server_url,
+        }
+    }
+}
+
+#[async_trait]
+impl LogSource for LokiSource {
+    async fn query(&self, query: &LogQuery) -> anyhow::Result<Vec<JsonValue>> {
+        let logql = to_logql(query);
+        let mut params = vec![("direction", "forward".to_string()), ("query", logql)];
+        if let Some((start, end)) = &query.range {
+            params.push(("start", timestamp_nanos(start)?));
+            params.push(("end", timestamp_nanos(end)?));
+        } else if let Some(since) = query.since {
+            params.push(("since", format!("{}s", since.num_seconds())));
+        }
+
+        let url = format!("{}/loki/api/v1/query_range", self.server_url);
+        let url_with_params = Url::parse_with_params(&url, &params)?;
+
+        let response = self.client.get(url_with_params).send().await?;
+        let body = response.text().await?;
+
+        loki_res_into_json_logs(&body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::Read};
+
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn parse_into_logs_test() {
+        let str = File::open(
+            "src/phoenix/inclusion_monitor/loki_client/test_data/late_call_8365873.json",
+        )
+        .map(|mut file| {
+            let mut str = String::new();
+            file.read_to_string(&mut str).unwrap();
+            str
+        })
+        .unwrap();
+
+        loki_res_into_json_logs(&str).unwrap();
+    }
+
+    #[test]
+    fn to_logql_test() {
+        let query = LogQuery::new("payload-api")
+            .slot(8371236)
+            .message("block published through beacon node")
+            .since(Duration::hours(24));
+        assert_eq!(
+            to_logql(&query),
+            r#"{app="payload-api"} |= `"slot":"8371236"` |= "block published through beacon node""#
+        );
+
+        let query = LogQuery::new("payload-api")
+            .geo("rbx")
+            .slot(8371236)
+            .message("block published through beacon node");
+        assert_eq!(
+            to_logql(&query),
+            r#"{app="payload-api"} |= `"slot":"8371236"` |= "block published through beacon node" | json geo="geo" | geo="" or geo="rbx""#
+        );
+
+        let query = LogQuery::new("payload-api").level("error").slot(8365565);
+        assert_eq!(
+            to_logql(&query),
+            r#"{app="payload-api",level="error"} |= `"slot":"8365565"`"#
+        );
+    }
+}
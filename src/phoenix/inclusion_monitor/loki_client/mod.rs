@@ -1,81 +1,24 @@
+mod file;
+mod loki;
+mod source;
 mod stats;
 
-use anyhow::Context;
-use chrono::{DateTime, TimeZone, Utc};
-use itertools::Itertools;
-use reqwest::Url;
-use tracing::warn;
+use anyhow::bail;
+use chrono::Duration;
 
+pub use file::FileSource;
+pub use loki::LokiSource;
+pub use source::{LogQuery, LogSource};
 pub use stats::{LatePayloadStats, PublishedPayloadStats};
 
-use crate::phoenix::slot::Slot;
+use crate::{
+    chain_time::{ChainTime, Slot},
+    phoenix::env::{Geo, APP_CONFIG, CHAIN_TIME},
+};
 
 type JsonValue = serde_json::Value;
 
-/// Parses loki query response into a list of log lines. Oldest log line is first.
-fn loki_res_into_json_logs(text: &str) -> anyhow::Result<Vec<JsonValue>> {
-    let log_response_json: JsonValue = match serde_json::from_str(text) {
-        Ok(json) => json,
-        Err(e) => {
-            warn!("failed to parse payload log request body as json: {}", e);
-            return Ok(Vec::new());
-        }
-    };
-
-    // Loki queries many nodes, each may return a stream of logs. We first discard all metadata
-    // leaving only a list of streams.
-    let streams = log_response_json
-        // The rest is response metadata
-        .get("data")
-        // These are the lines, the rest is metadata about the lines
-        .and_then(|data| data.get("result"))
-        .and_then(|result| result.as_array())
-        .ok_or_else(|| anyhow::anyhow!("failed to parse payload log response"))?;
-
-    // Each stream may contain many log lines. We discard the metadata and keep only the log lines.
-    let log_values = streams
-        .iter()
-        .map(|line| {
-            line.get("values")
-                .and_then(|values| values.as_array())
-                .ok_or_else(|| anyhow::anyhow!("failed to parse payload log response"))
-        })
-        .collect::<Result<Vec<_>, _>>()?
-        .into_iter()
-        .flatten()
-        .collect::<Vec<_>>();
-
-    // Each value is an array of two values: a timestamp and the log line.
-    let log_tuples = log_values
-        .iter()
-        .map(|value| {
-            let raw_timestamp = value
-                .get(0)
-                .and_then(|timestamp| timestamp.as_str())
-                .ok_or_else(|| {
-                    anyhow::anyhow!("failed to parse timestamp from payload log response")
-                })?;
-            let timestamp: DateTime<Utc> = raw_timestamp
-                .parse::<i64>()
-                .map(|timestamp| Utc.timestamp_nanos(timestamp))
-                .context("failed to parse nanosecond timestamp as i64")?;
-            let log_str = value
-                .get(1)
-                .and_then(|log| log.as_str())
-                .ok_or_else(|| anyhow::anyhow!("failed to parse log from payload log response"))?;
-            let log_value = serde_json::from_str(log_str).context("failed to parse log as JSON")?;
-            anyhow::Ok((timestamp, log_value))
-        })
-        .collect::<Result<Vec<_>, _>>()?;
-
-    let log_lines = log_tuples
-        .into_iter()
-        .sorted_by_key(|(timestamp, _)| *timestamp)
-        .map(|(_, log)| log)
-        .collect::<Vec<_>>();
-
-    Ok(log_lines)
-}
+const PAYLOAD_API_APP: &str = "payload-api";
 
 fn errors_from_logs(logs: &[JsonValue]) -> anyhow::Result<Vec<String>> {
     logs.iter()
@@ -84,119 +27,153 @@ fn errors_from_logs(logs: &[JsonValue]) -> anyhow::Result<Vec<String>> {
         .ok_or_else(|| anyhow::anyhow!("failed to parse error logs"))
 }
 
+fn published_query(slot: i64, geo: &Geo) -> LogQuery {
+    LogQuery::new(PAYLOAD_API_APP)
+        .geo(&geo.to_string())
+        .slot(slot)
+        .message("block published through beacon node")
+        .since(Duration::hours(24))
+}
+
 pub struct LokiClient {
-    client: reqwest::Client,
-    server_url: String,
+    source: Box<dyn LogSource>,
+    chain_time: ChainTime,
 }
 
-/// Query loki for stats related to the publishing of payloads.
+/// Query the payload api logs for stats related to the publishing of payloads.
 /// See the tests for an example of the data.
 impl LokiClient {
-    pub fn new(server_url: String) -> Self {
+    pub fn new(source: impl LogSource + 'static, chain_time: ChainTime) -> Self {
         Self {
-            client: reqwest::Client::new(),
-            server_url,
+            source: Box::new(source),
+            chain_time,
         }
     }
 
+    /// Read logs from the configured log file, or from Loki when there is none.
+    pub fn from_config() -> anyhow::Result<Self> {
+        match (&APP_CONFIG.log_file, &APP_CONFIG.loki_url) {
+            (Some(log_file), _) => Ok(Self::new(FileSource::new(log_file), CHAIN_TIME.clone())),
+            (None, Some(loki_url)) => Ok(Self::new(
+                LokiSource::new(loki_url.clone()),
+                CHAIN_TIME.clone(),
+            )),
+            (None, None) => bail!("either LOG_FILE or LOKI_URL must be set"),
+        }
+    }
+
+    /// Stats of publishing the payload of `slot` from the relay instance in `geo`.
     pub async fn published_stats(
         &self,
         slot: i64,
+        geo: &Geo,
     ) -> anyhow::Result<Option<PublishedPayloadStats>> {
-        let query = format!(
-            r#"{{app="payload-api"}} |= `"slot":"{slot}"` |= "block published through beacon node""#
-        );
-        let since = "24h";
-
-        let url = format!("{}/loki/api/v1/query_range", self.server_url);
-        let url_with_params = Url::parse_with_params(
-            &url,
-            &[
-                ("direction", "forward"),
-                ("query", query.as_str()),
-                ("since", since),
-            ],
-        )?;
-
-        let response = self.client.get(url_with_params).send().await?;
-        let body = response.text().await?;
-
-        let logs = loki_res_into_json_logs(&body)?;
+        let logs = self.source.query(&published_query(slot, geo)).await?;
         stats::published_stats_from_logs(&logs)
     }
 
     pub async fn late_call_stats(&self, slot: i64) -> anyhow::Result<Option<LatePayloadStats>> {
-        let query = format!(
-            r#"{{app="payload-api",level="warning"}} |= `"slot":"{slot}"` |= "getPayload sent too late""#
-        );
-        let since = "24h";
-
-        let url = format!("{}/loki/api/v1/query_range", self.server_url);
-        let url_with_params = Url::parse_with_params(
-            &url,
-            &[
-                ("direction", "forward"),
-                ("query", query.as_str()),
-                ("since", since),
-            ],
-        )?;
-
-        let response = self.client.get(url_with_params).send().await?;
-        let body = response.text().await?;
-
-        let logs = loki_res_into_json_logs(&body)?;
+        let query = LogQuery::new(PAYLOAD_API_APP)
+            .level("warning")
+            .slot(slot)
+            .message("getPayload sent too late")
+            .since(Duration::hours(24));
+
+        let logs = self.source.query(&query).await?;
         stats::late_call_stats_from_logs(&logs)
     }
 
     pub async fn error_messages(&self, slot: i64) -> anyhow::Result<Vec<String>> {
-        let query = format!(r#"{{app="payload-api",level="error"}} |= `"slot":"{slot}"`"#);
-        let slot = Slot(slot as i32);
-        let start = slot
-            .date_time()
-            .timestamp_nanos_opt()
-            .ok_or_else(|| anyhow::anyhow!("Start Time out of range for nanosecond timestamp"))?;
-        let end = (slot.date_time() + chrono::Duration::seconds(12))
-            .timestamp_nanos_opt()
-            .ok_or_else(|| anyhow::anyhow!("End Time out of range for nanosecond timestamp"))?;
-
-        let url = format!("{}/loki/api/v1/query_range", self.server_url);
-        let url_with_params = Url::parse_with_params(
-            &url,
-            &[
-                ("direction", "forward"),
-                ("query", query.as_str()),
-                ("start", start.to_string().as_str()),
-                ("end", end.to_string().as_str()),
-            ],
-        )?;
-
-        let response = self.client.get(url_with_params).send().await?;
-        let body = response.text().await?;
-
-        let logs = loki_res_into_json_logs(&body)?;
+        let start = self.chain_time.slot_start(Slot(slot));
+        let query = LogQuery::new(PAYLOAD_API_APP)
+            .level("error")
+            .slot(slot)
+            .range(start, start + self.chain_time.slot_duration());
+
+        let logs = self.source.query(&query).await?;
         errors_from_logs(&logs)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{fs::File, io::Read};
+    use std::{collections::HashMap, fs::File, io::Read};
 
     use super::*;
+    use crate::env::Network;
+
+    const LOG_FILE: &str = "src/phoenix/inclusion_monitor/loki_client/test_data/payload_api.jsonl";
+
+    fn mainnet() -> ChainTime {
+        ChainTime::for_network(&Network::mainnet())
+    }
+
+    /// Parse `key="value"` label matchers, separated by `separator`.
+    fn label_matchers<'a>(str: &'a str, separator: &str) -> Vec<(&'a str, &'a str)> {
+        str.split(separator)
+            .map(|matcher| {
+                let (key, value) = matcher.split_once('=').unwrap();
+                (key.trim(), value.trim().trim_matches('"'))
+            })
+            .collect()
+    }
+
+    /// Whether `line` of a stream with `labels` makes it through the stream selector, json
+    /// parser and label filters of `logql`. Line filters are left out, the fixtures come from
+    /// other queries.
+    fn logql_matches(logql: &str, labels: &HashMap<String, String>, line: &str) -> bool {
+        let (selector, pipeline) = logql.split_once('}').unwrap();
+        let selected = label_matchers(selector.trim_start_matches('{'), ",")
+            .into_iter()
+            .all(|(key, value)| labels.get(key).map(String::as_str) == Some(value));
+
+        let log: JsonValue = serde_json::from_str(line).unwrap();
+        let mut extracted = HashMap::new();
+        let mut filtered = true;
+        for stage in pipeline.split(" | ").skip(1) {
+            if let Some(extractions) = stage.strip_prefix("json ") {
+                for (label, field) in label_matchers(extractions, ",") {
+                    if let Some(value) = log[field].as_str() {
+                        extracted.insert(label, value.to_string());
+                    }
+                }
+            } else {
+                filtered &= label_matchers(stage, " or ")
+                    .into_iter()
+                    .any(|(key, value)| {
+                        extracted.get(key).map(String::as_str).unwrap_or_default() == value
+                    });
+            }
+        }
+        selected && filtered
+    }
 
     #[test]
-    fn parse_into_logs_test() {
-        let str = File::open(
-            "src/phoenix/inclusion_monitor/loki_client/test_data/late_call_8365873.json",
+    fn published_query_matches_payload_api_streams_test() {
+        let str = std::fs::read_to_string(
+            "src/phoenix/inclusion_monitor/loki_client/test_data/published_8371236.json",
         )
-        .map(|mut file| {
-            let mut str = String::new();
-            file.read_to_string(&mut str).unwrap();
-            str
-        })
         .unwrap();
-
-        loki_res_into_json_logs(&str).unwrap();
+        let response: JsonValue = serde_json::from_str(&str).unwrap();
+        let logql = loki::to_logql(&published_query(8371236, &Geo::RBX));
+
+        let streams = response["data"]["result"].as_array().unwrap();
+        assert!(!streams.is_empty());
+        for stream in streams {
+            let labels: HashMap<String, String> =
+                serde_json::from_value(stream["stream"].clone()).unwrap();
+            for value in stream["values"].as_array().unwrap() {
+                let line = value[1].as_str().unwrap();
+                assert!(logql_matches(&logql, &labels, line), "{}", logql);
+
+                // Lines which name another geo are left out.
+                let mut log: JsonValue = serde_json::from_str(line).unwrap();
+                log["geo"] = "vin".into();
+                assert!(!logql_matches(&logql, &labels, &log.to_string()));
+                log["geo"] = "rbx".into();
+                assert!(logql_matches(&logql, &labels, &log.to_string()));
+            }
+        }
     }
 
     #[test]
@@ -210,7 +187,47 @@ mod tests {
                 })
                 .unwrap();
 
-        let logs = loki_res_into_json_logs(&str).unwrap();
+        let logs = loki::loki_res_into_json_logs(&str).unwrap();
         errors_from_logs(&logs).unwrap();
     }
+
+    #[tokio::test]
+    async fn file_published_stats_test() {
+        let client = LokiClient::new(FileSource::new(LOG_FILE), mainnet());
+
+        let stats = client
+            .published_stats(8371236, &Geo::RBX)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stats.decoded_at_slot_age_ms, 3870);
+        assert_eq!(stats.publish_duration_ms, 693);
+
+        assert!(client
+            .published_stats(8372882, &Geo::RBX)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn file_late_call_stats_test() {
+        let client = LokiClient::new(FileSource::new(LOG_FILE), mainnet());
+
+        let stats = client.late_call_stats(8372882).await.unwrap().unwrap();
+        assert_eq!(stats.decoded_at_slot_age_ms, 7202);
+
+        assert!(client.late_call_stats(8371236).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn file_error_messages_test() {
+        let client = LokiClient::new(FileSource::new(LOG_FILE), mainnet());
+
+        assert_eq!(
+            client.error_messages(8365565).await.unwrap(),
+            vec!["Couldn't write response"; 3]
+        );
+        assert!(client.error_messages(8371236).await.unwrap().is_empty());
+    }
 }
@@ -0,0 +1,70 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+
+use super::JsonValue;
+
+/// A query for JSON log lines, independent of where the logs are stored.
+#[derive(Debug, Clone)]
+pub struct LogQuery {
+    pub app: String,
+    pub geo: Option<String>,
+    pub level: Option<String>,
+    pub slot: Option<i64>,
+    pub message: Option<String>,
+    /// Start inclusive, end exclusive.
+    pub range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    /// Only look at logs from the last `since`, when no range is given.
+    pub since: Option<Duration>,
+}
+
+impl LogQuery {
+    pub fn new(app: &str) -> Self {
+        Self {
+            app: app.to_string(),
+            geo: None,
+            level: None,
+            slot: None,
+            message: None,
+            range: None,
+            since: None,
+        }
+    }
+
+    /// Skip logs of relay instances in other geos. Logs which don't say their geo are kept.
+    pub fn geo(mut self, geo: &str) -> Self {
+        self.geo = Some(geo.to_string());
+        self
+    }
+
+    pub fn level(mut self, level: &str) -> Self {
+        self.level = Some(level.to_string());
+        self
+    }
+
+    pub fn slot(mut self, slot: i64) -> Self {
+        self.slot = Some(slot);
+        self
+    }
+
+    /// Only match logs whose message contains `message`.
+    pub fn message(mut self, message: &str) -> Self {
+        self.message = Some(message.to_string());
+        self
+    }
+
+    pub fn range(mut self, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        self.range = Some((start, end));
+        self
+    }
+
+    pub fn since(mut self, since: Duration) -> Self {
+        self.since = Some(since);
+        self
+    }
+}
+
+#[async_trait]
+pub trait LogSource: Send + Sync {
+    /// Fetch the log lines matching `query`. Oldest log line is first.
+    async fn query(&self, query: &LogQuery) -> anyhow::Result<Vec<JsonValue>>;
+}
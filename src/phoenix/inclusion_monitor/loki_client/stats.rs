@@ -1,12 +1,13 @@
 use anyhow::Context;
 use chrono::{DateTime, TimeZone, Utc};
+use serde::Serialize;
 use tracing::warn;
 
 type JsonValue = serde_json::Value;
 
 /// Statistics on payloads requested. Used to determine if a payload which failed to make it
 /// on-chain should concern us.
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct PublishedPayloadStats {
     pub decoded_at_slot_age_ms: i64,
     pub pre_publish_duration_ms: i64,
@@ -15,9 +16,18 @@ pub struct PublishedPayloadStats {
     pub request_download_duration_ms: i64,
 }
 
+impl PublishedPayloadStats {
+    /// Approximate ms into the slot at which our beacon node finished publishing the block.
+    pub fn publish_done_ms_into_slot(&self) -> i64 {
+        // decoded_at_slot_age_ms is measured after the request body was downloaded.
+        let received_ms_into_slot = self.decoded_at_slot_age_ms - self.request_download_duration_ms;
+        received_ms_into_slot + self.pre_publish_duration_ms + self.publish_duration_ms
+    }
+}
+
 /// Statistics on payloads which were requested too late. Used to determine if a payload which
 /// failed to make it on-chain should concern us.
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct LatePayloadStats {
     pub decoded_at_slot_age_ms: i64,
     pub request_download_duration_ms: i64,
@@ -109,7 +119,7 @@ pub fn late_call_stats_from_logs(logs: &[JsonValue]) -> anyhow::Result<Option<La
 mod tests {
     use std::{fs::File, io::Read};
 
-    use crate::phoenix::inclusion_monitor::loki_client::loki_res_into_json_logs;
+    use crate::phoenix::inclusion_monitor::loki_client::loki::loki_res_into_json_logs;
 
     use super::*;
 
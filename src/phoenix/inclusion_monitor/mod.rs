@@ -1,52 +1,81 @@
+mod block_cache;
+mod events;
 mod loki_client;
 mod proposer_meta;
+mod publish_stats;
+mod replay;
+mod root_cause;
+
+use std::collections::HashSet;
 
 use anyhow::Context;
+use block_cache::BlockCache;
 use chrono::{DateTime, TimeZone, Utc};
+use futures::{stream, StreamExt};
 use indoc::formatdoc;
-use sqlx::{PgPool, Row};
+use serde::Serialize;
+use sqlx::{postgres::PgRow, PgPool, Row};
 use tracing::{debug, info, warn};
 
+pub use events::run_inclusion_event_monitor;
 pub use loki_client::LokiClient;
 use loki_client::PublishedPayloadStats;
+pub use publish_stats::run_publish_stats_monitor;
+pub use replay::{replay_inclusion, ReplayRange};
 
 use crate::{
-    beacon_api::{BeaconApi, ExecutionPayload},
-    env::ToBeaconExplorerUrl,
+    beacon_api::{BeaconApi, BeaconBlock, ExecutionPayload},
+    chain_time::Slot,
     phoenix::{
         alerts,
         inclusion_monitor::proposer_meta::{
             get_proposer_ip, proposer_label_meta, proposer_location,
         },
-        slot::Slot,
     },
 };
 
-use self::{loki_client::LatePayloadStats, proposer_meta::ProposerLocation};
+use self::{
+    loki_client::LatePayloadStats,
+    proposer_meta::{ProposerLabelMeta, ProposerLocation},
+    root_cause::{MissCategory, MissEvidence},
+};
 
 use super::{
     alerts::telegram::{self, Channel, TelegramMessage},
-    checkpoint::{self, CheckpointId},
-    env::{Geo, APP_CONFIG},
+    builder_contacts::{self, NotificationKind},
+    checkpoint::{self, Checkpoint, CheckpointId, Position},
+    env::{Geo, APP_CONFIG, CHAIN_TIME},
 };
 
 #[derive(Debug)]
 struct DeliveredPayload {
     block_hash: String,
     block_number: i64,
-    #[allow(dead_code)]
     inserted_at: DateTime<Utc>,
     proposer_pubkey: String,
     slot: i64,
     geo: Geo,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
 enum DetectionMethod {
+    #[default]
     DeliveredPayload,
     RequestIntersection,
 }
 
+fn delivered_payload_from_row(row: &PgRow) -> DeliveredPayload {
+    DeliveredPayload {
+        block_hash: row.get("block_hash"),
+        block_number: row.get("block_number"),
+        inserted_at: Utc.from_utc_datetime(&row.get("inserted_at")),
+        proposer_pubkey: row.get("proposer_pubkey"),
+        slot: row.get("slot"),
+        geo: row.get("geo"),
+    }
+}
+
 async fn get_delivered_payloads(
     relay_pool: &PgPool,
     start: &DateTime<Utc>,
@@ -71,58 +100,113 @@ async fn get_delivered_payloads(
         .bind(end)
         .fetch_all(relay_pool)
         .await
-        .map(|rows| {
-            rows.iter()
-                .map(|row| DeliveredPayload {
-                    block_hash: row.get("block_hash"),
-                    block_number: row.get("block_number"),
-                    inserted_at: Utc.from_utc_datetime(&row.get("inserted_at")),
-                    proposer_pubkey: row.get("proposer_pubkey"),
-                    slot: row.get("slot"),
-                    geo: row.get("geo"),
-                })
-                .collect()
-        })
+        .map(|rows| rows.iter().map(delivered_payload_from_row).collect())
         .map_err(Into::into)
 }
 
+async fn get_delivered_payloads_for_slot(
+    relay_pool: &PgPool,
+    slot: i64,
+) -> anyhow::Result<Vec<DeliveredPayload>> {
+    let query = r#"
+        SELECT
+            inserted_at,
+            slot,
+            geo,
+            block_hash,
+            block_number,
+            proposer_pubkey
+        FROM payload_delivered
+        WHERE slot = $1
+        ORDER BY inserted_at ASC
+        "#;
+
+    sqlx::query(query)
+        .bind(slot)
+        .fetch_all(relay_pool)
+        .await
+        .map(|rows| rows.iter().map(delivered_payload_from_row).collect())
+        .map_err(Into::into)
+}
+
+/// Store a miss unless it is stored already, its root cause stays unknown until the evidence is
+/// attached. Returns whether the miss still has to be reported.
 async fn insert_missed_slot(
     mev_pool: &PgPool,
     slot_number: &i64,
     relayed: &String,
     canonical: Option<&String>,
-) -> anyhow::Result<()> {
-    // avoid duplicate entries in case the same miss is detected via multiple paths
-    let exists: bool = sqlx::query_scalar(
+) -> anyhow::Result<bool> {
+    sqlx::query(
         r#"
-        SELECT EXISTS (
-            SELECT 1 FROM missed_slots WHERE slot_number = $1 AND relayed_block_hash = $2
+        INSERT INTO missed_slots (
+            slot_number,
+            relayed_block_hash,
+            canonical_block_hash,
+            root_cause,
+            reported
         )
+        VALUES ($1, $2, $3, $4, false)
+        ON CONFLICT (slot_number, relayed_block_hash) DO NOTHING
         "#,
     )
     .bind(slot_number)
     .bind(relayed)
+    .bind(canonical)
+    .bind(MissCategory::Unknown.to_string())
+    .execute(mev_pool)
+    .await?;
+
+    let reported = sqlx::query_scalar::<_, bool>(
+        "SELECT reported FROM missed_slots WHERE slot_number = $1 AND relayed_block_hash = $2",
+    )
+    .bind(slot_number)
+    .bind(relayed)
     .fetch_one(mev_pool)
     .await?;
+    Ok(!reported)
+}
 
-    if exists {
-        debug!(
-            slot = slot_number,
-            relayed_block_hash = relayed,
-            "missed slot already recorded, skipping insert"
-        );
-        return Ok(());
-    }
+/// Mark a miss as reported. Returns false when another check reported it first.
+async fn claim_missed_slot_report(
+    mev_pool: &PgPool,
+    slot_number: &i64,
+    relayed: &String,
+) -> anyhow::Result<bool> {
+    sqlx::query_scalar::<_, i32>(
+        r#"
+        UPDATE missed_slots
+        SET reported = true
+        WHERE slot_number = $1 AND relayed_block_hash = $2 AND NOT reported
+        RETURNING id
+        "#,
+    )
+    .bind(slot_number)
+    .bind(relayed)
+    .fetch_optional(mev_pool)
+    .await
+    .map(|id| id.is_some())
+    .map_err(Into::into)
+}
 
-    sqlx::query!(
+async fn set_missed_slot_root_cause(
+    mev_pool: &PgPool,
+    slot_number: &i64,
+    relayed: &String,
+    root_cause: MissCategory,
+    evidence: &MissEvidence,
+) -> anyhow::Result<()> {
+    sqlx::query(
         r#"
-        INSERT INTO missed_slots (slot_number, relayed_block_hash, canonical_block_hash)
-        VALUES ($1, $2, $3)
+        UPDATE missed_slots
+        SET root_cause = $3, root_cause_evidence = $4
+        WHERE slot_number = $1 AND relayed_block_hash = $2
         "#,
-        slot_number,
-        relayed,
-        canonical
     )
+    .bind(slot_number)
+    .bind(relayed)
+    .bind(root_cause.to_string())
+    .bind(serde_json::to_value(evidence)?)
     .execute(mev_pool)
     .await
     .map(|_| ())
@@ -162,7 +246,130 @@ async fn check_is_adjustment_hash(pg_pool: &PgPool, block_hash: &str) -> anyhow:
     .context("failed to check if block hash is adjustment hash")
 }
 
-async fn report_missing_payload(
+/// The builder of a delivered payload. Candidates from the request intersection flow may not be
+/// in `payload_delivered`, these have no known builder.
+async fn get_payload_builder_id(
+    relay_pool: &PgPool,
+    slot: i64,
+    block_hash: &str,
+) -> anyhow::Result<Option<String>> {
+    sqlx::query_scalar(
+        "
+        SELECT b.builder_id
+        FROM payload_delivered pd
+        INNER JOIN builder b
+          ON pd.builder_pubkey = b.builder_pubkey
+        WHERE pd.slot = $1
+          AND pd.block_hash = $2
+        LIMIT 1
+        ",
+    )
+    .bind(slot)
+    .bind(block_hash)
+    .fetch_optional(relay_pool)
+    .await
+    .map(Option::flatten)
+    .context("failed to get builder id of delivered payload")
+}
+
+/// A miss recorded in `missed_slots` which wasn't reported yet, with everything needed to alert on
+/// it.
+struct RecordedMiss {
+    slot: i64,
+    geo: Geo,
+    relayed_block_hash: String,
+    canonical_block_hash: Option<String>,
+    root_cause: MissCategory,
+    evidence: MissEvidence,
+    publish_errors: Vec<String>,
+    proposer_meta: ProposerLabelMeta,
+    proposer_ip: Option<String>,
+    builder_id: Option<String>,
+}
+
+/// State shared by the checks of a single inclusion run.
+struct InclusionRun {
+    send_alerts: bool,
+    /// Payloads checked so far, so the intersection flow skips what the delivered flow checked.
+    processed: HashSet<(i64, String)>,
+    misses: Vec<RecordedMiss>,
+}
+
+impl InclusionRun {
+    fn new(send_alerts: bool) -> Self {
+        Self {
+            send_alerts,
+            processed: HashSet::new(),
+            misses: Vec::new(),
+        }
+    }
+
+    /// Misses are only marked as reported when alerts are sent, so a replay without alerts
+    /// leaves them to be reported later.
+    async fn add_miss(&mut self, mev_pool: &PgPool, miss: RecordedMiss) -> anyhow::Result<()> {
+        if self.send_alerts {
+            report_missed_slot(mev_pool, &miss).await?;
+        }
+        self.misses.push(miss);
+        Ok(())
+    }
+}
+
+/// Everything we look up about a miss besides its evidence, for alerting on it.
+#[derive(Default)]
+struct MissContext {
+    publish_errors: Vec<String>,
+    proposer_meta: ProposerLabelMeta,
+    proposer_ip: Option<String>,
+    builder_id: Option<String>,
+}
+
+async fn collect_miss_details(
+    loki_client: &LokiClient,
+    mev_pool: &PgPool,
+    relay_pool: &PgPool,
+    payload: &DeliveredPayload,
+    evidence: MissEvidence,
+) -> anyhow::Result<(MissEvidence, MissContext)> {
+    let slot = payload.slot;
+    let is_adjustment = check_is_adjustment_hash(relay_pool, &payload.block_hash).await?;
+    let published_stats = loki_client.published_stats(slot, &payload.geo).await?;
+    let publish_errors = loki_client.error_messages(slot).await?;
+    let late_call_stats = loki_client.late_call_stats(slot).await?;
+
+    let proposer_meta = proposer_label_meta(mev_pool, &payload.proposer_pubkey).await?;
+    let proposer_ip = get_proposer_ip(mev_pool, &payload.proposer_pubkey).await?;
+    let builder_id = get_payload_builder_id(relay_pool, slot, &payload.block_hash).await?;
+    let proposer_location = match &proposer_ip {
+        Some(proposer_ip) => proposer_location(mev_pool, proposer_ip).await?,
+        None => ProposerLocation::default(),
+    };
+
+    let evidence = MissEvidence {
+        is_adjustment,
+        late_call_stats,
+        proposer_city: proposer_location.city,
+        proposer_country: proposer_location.country,
+        publish_done_ms_into_slot: published_stats
+            .as_ref()
+            .map(PublishedPayloadStats::publish_done_ms_into_slot),
+        publish_error_count: publish_errors.len(),
+        published_stats,
+        ..evidence
+    };
+    let context = MissContext {
+        publish_errors,
+        proposer_meta,
+        proposer_ip,
+        builder_id,
+    };
+    Ok((evidence, context))
+}
+
+/// Store a missing payload, then collect evidence on it and classify it. The miss is stored even
+/// when collecting evidence fails, its root cause is unknown then. Returns None when the miss was
+/// already reported.
+async fn record_missing_payload(
     found_block_hash: Option<String>,
     loki_client: &LokiClient,
     mev_pool: &PgPool,
@@ -170,21 +377,140 @@ async fn report_missing_payload(
     relay_pool: &PgPool,
     is_attempted_reorg: bool,
     detection_method: DetectionMethod,
-) -> anyhow::Result<()> {
-    insert_missed_slot(
+) -> anyhow::Result<Option<RecordedMiss>> {
+    let slot = payload.slot;
+
+    // The same miss can be detected via multiple paths, e.g. the event driven monitor and the
+    // polling monitor, and again by a run retried after a failure. It is stored once, and only
+    // looked into again until it is reported.
+    let unreported = insert_missed_slot(
         mev_pool,
         &payload.slot,
         &payload.block_hash,
         found_block_hash.as_ref(),
     )
     .await?;
+    if !unreported {
+        debug!(
+            slot,
+            relayed_block_hash = payload.block_hash,
+            "missed slot already recorded, skipping report"
+        );
+        return Ok(None);
+    }
+
+    let known = || MissEvidence {
+        canonical_block_found: found_block_hash.is_some(),
+        detection_method,
+        is_attempted_reorg,
+        ..Default::default()
+    };
+    let (root_cause, evidence, context) =
+        match collect_miss_details(loki_client, mev_pool, relay_pool, payload, known()).await {
+            Ok((evidence, context)) => {
+                let root_cause = root_cause::classify(&evidence);
+                if let Err(err) = set_missed_slot_root_cause(
+                    mev_pool,
+                    &payload.slot,
+                    &payload.block_hash,
+                    root_cause,
+                    &evidence,
+                )
+                .await
+                {
+                    warn!(slot, "failed to store missed slot root cause: {:#}", err);
+                }
+                (root_cause, evidence, context)
+            }
+            Err(err) => {
+                warn!(
+                    slot,
+                    relayed_block_hash = payload.block_hash,
+                    "failed to collect missed slot evidence, root cause unknown: {:#}",
+                    err
+                );
+                (MissCategory::Unknown, known(), MissContext::default())
+            }
+        };
+
+    Ok(Some(RecordedMiss {
+        slot,
+        geo: payload.geo.clone(),
+        relayed_block_hash: payload.block_hash.clone(),
+        canonical_block_hash: found_block_hash,
+        root_cause,
+        evidence,
+        publish_errors: context.publish_errors,
+        proposer_meta: context.proposer_meta,
+        proposer_ip: context.proposer_ip,
+        builder_id: context.builder_id,
+    }))
+}
 
-    let explorer_url = APP_CONFIG.network.to_beacon_explorer_url();
+/// Builders get a short message, without the proposer details of our own alert.
+async fn notify_builder_of_miss(
+    mev_pool: &PgPool,
+    telegram_bot: &telegram::TelegramBot,
+    builder_id: &str,
+    miss: &RecordedMiss,
+) {
+    let explorer_url = &APP_CONFIG.network.explorer_url;
+    let slot = miss.slot;
+    let block_hash = &miss.relayed_block_hash;
+    let escaped_root_cause = telegram::escape_str(&miss.root_cause.to_string());
+    let message = formatdoc!(
+        "
+        *block delivered by the relay not found on chain*
 
-    let slot = payload.slot;
-    let geo = &payload.geo;
-    let payload_block_hash = &payload.block_hash;
-    let on_chain_block_hash = telegram::escape_str(found_block_hash.as_deref().unwrap_or("-"));
+        [beaconcha\\.in/slot/{slot}]({explorer_url}/slot/{slot})
+        slot: `{slot}`
+        block\\_hash: `{block_hash}`
+        root\\_cause: {escaped_root_cause}
+        "
+    );
+    builder_contacts::notify_builder(
+        mev_pool,
+        telegram_bot,
+        builder_id,
+        NotificationKind::MissedSlot,
+        &TelegramMessage::from_escaped_string(message),
+        None,
+    )
+    .await;
+}
+
+/// Alert on a miss, unless another check reported it first.
+async fn report_missed_slot(mev_pool: &PgPool, miss: &RecordedMiss) -> anyhow::Result<()> {
+    if claim_missed_slot_report(mev_pool, &miss.slot, &miss.relayed_block_hash).await? {
+        send_missed_slot_alert(mev_pool, miss).await;
+    }
+    Ok(())
+}
+
+async fn send_missed_slot_alert(mev_pool: &PgPool, miss: &RecordedMiss) {
+    let RecordedMiss {
+        slot,
+        geo,
+        relayed_block_hash: payload_block_hash,
+        canonical_block_hash,
+        root_cause,
+        evidence,
+        publish_errors,
+        proposer_meta,
+        proposer_ip,
+        builder_id,
+    } = miss;
+    let MissEvidence {
+        detection_method,
+        is_adjustment,
+        is_attempted_reorg,
+        ..
+    } = evidence;
+
+    let explorer_url = &APP_CONFIG.network.explorer_url;
+
+    let on_chain_block_hash = telegram::escape_str(canonical_block_hash.as_deref().unwrap_or("-"));
+    let escaped_root_cause = telegram::escape_str(&root_cause.to_string());
 
     let mut message = formatdoc!(
         "
@@ -195,6 +521,7 @@ async fn report_missing_payload(
         geo: {geo}
         payload\\_block\\_hash: {payload_block_hash}
         on\\_chain\\_block\\_hash: {on_chain_block_hash}
+        root\\_cause: {escaped_root_cause}
         "
     );
 
@@ -207,15 +534,13 @@ async fn report_missing_payload(
         }
     }
 
-    let is_adjustment_hash = check_is_adjustment_hash(relay_pool, &payload.block_hash).await?;
-    message.push_str(&format!("is\\_adjustment: {}", is_adjustment_hash));
+    message.push_str(&format!("is\\_adjustment: {}", is_adjustment));
     message.push_str("\n\n");
     message.push_str(&format!("is\\_attempted\\_reorg: {}", is_attempted_reorg));
 
     // Check if a publish was attempted, if yes, add publish stats.
-    let published_stats = loki_client.published_stats(payload.slot).await?;
-    match published_stats {
-        Some(ref payload_stats) => {
+    match &evidence.published_stats {
+        Some(payload_stats) => {
             let PublishedPayloadStats {
                 decoded_at_slot_age_ms,
                 pre_publish_duration_ms,
@@ -241,7 +566,6 @@ async fn report_missing_payload(
     }
 
     // Add proposer meta.
-    let proposer_meta = proposer_label_meta(mev_pool, &payload.proposer_pubkey).await?;
     let operator = {
         let label = proposer_meta.label.as_deref().unwrap_or("-");
         telegram::escape_str(label)
@@ -257,24 +581,17 @@ async fn report_missing_payload(
         telegram::escape_str(grafitti)
     };
 
-    let proposer_ip = get_proposer_ip(mev_pool, &payload.proposer_pubkey).await?;
     let proposer_ip_formatted = {
         let ip = proposer_ip.as_deref().unwrap_or("-");
         telegram::escape_str(ip)
     };
 
-    let proposer_location = {
-        match proposer_ip {
-            Some(proposer_ip) => proposer_location(mev_pool, &proposer_ip).await?,
-            None => ProposerLocation::default(),
-        }
-    };
     let proposer_country = {
-        let country = proposer_location.country.as_deref().unwrap_or("-");
+        let country = evidence.proposer_country.as_deref().unwrap_or("-");
         telegram::escape_str(country)
     };
     let proposer_city = {
-        let city = proposer_location.city.as_deref().unwrap_or("-");
+        let city = evidence.proposer_city.as_deref().unwrap_or("-");
         telegram::escape_str(city)
     };
 
@@ -292,7 +609,6 @@ async fn report_missing_payload(
     message.push('\n');
     message.push_str(&proposer_meta_message);
 
-    let publish_errors = loki_client.error_messages(slot).await?;
     if !publish_errors.is_empty() {
         message.push('\n');
         message.push_str("found publish errors");
@@ -316,8 +632,7 @@ async fn report_missing_payload(
         message.push_str("no publish errors found");
     }
 
-    let late_call_stats = loki_client.late_call_stats(slot).await?;
-    if let Some(late_call_stats) = &late_call_stats {
+    if let Some(late_call_stats) = &evidence.late_call_stats {
         let LatePayloadStats {
             decoded_at_slot_age_ms,
             request_download_duration_ms,
@@ -337,7 +652,10 @@ async fn report_missing_payload(
     }
 
     // Late call or attempted reorg, these are much less concerning.
-    if published_stats.is_none() && late_call_stats.is_some() || is_attempted_reorg {
+    if matches!(
+        root_cause,
+        MissCategory::LateProposerRequest | MissCategory::AttemptedReorg
+    ) {
         message.push_str("\n\n");
         message.push_str(
             "for this block 'no publish attempted and late call' or 'attempted reorg' these misses are less concerning",
@@ -351,52 +669,59 @@ async fn report_missing_payload(
         .send_message(&escaped_message, Channel::BlockNotFound)
         .await;
 
-    Ok(())
+    if let Some(builder_id) = builder_id {
+        notify_builder_of_miss(mev_pool, &telegram_bot, builder_id, miss).await;
+    }
 }
 
 /// If the previous slot contains a valid block with the same block_number as the payload
 /// we tried to deliver, then consider it a reorg attempt.
 async fn was_attempted_reorg(
-    beacon_api: &BeaconApi,
+    blocks: &BlockCache,
     delivered: &DeliveredPayload,
 ) -> anyhow::Result<bool> {
     let prev_slot = delivered.slot - 1;
-    let prev_payload = beacon_api.block_by_slot_any(prev_slot).await?;
-    Ok(prev_payload
-        .map(|p| p.block_number == delivered.block_number)
+    let prev_block = blocks.block_by_slot(prev_slot).await?;
+    Ok(prev_block
+        .map(|b| b.execution_payload.block_number == delivered.block_number)
         .unwrap_or(false))
 }
 
 async fn check_missing_payload(
-    beacon_api: &BeaconApi,
+    blocks: &BlockCache,
     loki_client: &LokiClient,
     mev_pool: &PgPool,
     payload: &DeliveredPayload,
     relay_pool: &PgPool,
-) -> anyhow::Result<()> {
-    let block = beacon_api.block_by_slot_any(payload.slot).await?;
+) -> anyhow::Result<Option<RecordedMiss>> {
+    let block = blocks.block_by_slot(payload.slot).await?;
 
     match block {
-        Some(ExecutionPayload { block_hash, .. }) => {
+        Some(block) => {
+            let block_hash = &block.execution_payload.block_hash;
             // payload.block_hash = hash from the delivered payload we relayed
             // block_hash = hash from the actual block that was included on-chain
-            if payload.block_hash == block_hash {
+            if &payload.block_hash == block_hash {
                 debug!(
                     slot = payload.slot,
                     block_hash = payload.block_hash,
                     "found matching block hash"
                 );
-                Ok(())
+                Ok(None)
             } else {
                 warn!(
                     slot = payload.slot,
                     block_hash_payload = payload.block_hash,
                     block_hash_on_chain = block_hash,
+                    proposer_index = block.proposer_index,
+                    graffiti = block
+                        .graffiti_text()
+                        .unwrap_or_else(|| block.graffiti.clone()),
                     "block hash on chain does not match payload"
                 );
 
-                report_missing_payload(
-                    Some(block_hash),
+                record_missing_payload(
+                    Some(block_hash.clone()),
                     loki_client,
                     mev_pool,
                     payload,
@@ -408,12 +733,12 @@ async fn check_missing_payload(
             }
         }
         None => {
-            let attempted_reorg = was_attempted_reorg(beacon_api, payload).await?;
+            let attempted_reorg = was_attempted_reorg(blocks, payload).await?;
             warn!(
                 attempted_reorg,
                 "delivered block not found for slot {}", payload.slot
             );
-            report_missing_payload(
+            record_missing_payload(
                 None,
                 loki_client,
                 mev_pool,
@@ -514,23 +839,26 @@ async fn get_payload_requests(
 }
 
 async fn check_missing_candidate(
-    beacon_api: &BeaconApi,
+    blocks: &BlockCache,
     loki_client: &LokiClient,
     mev_pool: &PgPool,
     relay_pool: &PgPool,
     candidate: &DeliveredPayload,
-) -> anyhow::Result<()> {
-    let block = beacon_api.block_by_slot_any(candidate.slot).await?;
+) -> anyhow::Result<Option<RecordedMiss>> {
+    let block = blocks.block_by_slot(candidate.slot).await?;
 
     match block {
-        Some(ExecutionPayload { block_hash, .. }) => {
+        Some(BeaconBlock {
+            execution_payload: ExecutionPayload { block_hash, .. },
+            ..
+        }) => {
             if candidate.block_hash == block_hash {
                 debug!(
                     slot = candidate.slot,
                     block_hash = candidate.block_hash,
                     "found matching block hash"
                 );
-                Ok(())
+                Ok(None)
             } else {
                 warn!(
                     slot = candidate.slot,
@@ -539,7 +867,7 @@ async fn check_missing_candidate(
                     "block hash on chain does not match payload (candidate)"
                 );
 
-                report_missing_payload(
+                record_missing_payload(
                     Some(block_hash),
                     loki_client,
                     mev_pool,
@@ -556,7 +884,7 @@ async fn check_missing_candidate(
                 "delivered block not found for slot {} (candidate)",
                 candidate.slot
             );
-            report_missing_payload(
+            record_missing_payload(
                 None,
                 loki_client,
                 mev_pool,
@@ -570,44 +898,51 @@ async fn check_missing_candidate(
     }
 }
 
-fn ms_into_slot(slot_number: i64, at: &DateTime<Utc>) -> i64 {
-    let slot_start: DateTime<Utc> = (&Slot(slot_number as i32)).into();
-    at.signed_duration_since(slot_start).num_milliseconds()
+/// Each check holds a database connection at a time, the ops pools are sized to fit.
+pub(super) fn check_concurrency() -> usize {
+    APP_CONFIG.inclusion_check_concurrency.max(1)
 }
 
 async fn get_or_init_inclusion_checkpoint(
-    relay_pool: &PgPool,
+    mev_pool: &PgPool,
     id: CheckpointId,
-) -> anyhow::Result<DateTime<Utc>> {
-    match checkpoint::get_checkpoint(relay_pool, id).await? {
-        Some(c) => {
-            debug!("found inclusion checkpoint: {}", c);
-            Ok(c)
-        }
-        None => {
-            info!("no checkpoint found, initializing");
-            let now = Utc::now();
-            checkpoint::put_checkpoint(relay_pool, id, &now).await?;
-            Ok(now)
-        }
-    }
+    now: DateTime<Utc>,
+) -> anyhow::Result<Checkpoint> {
+    let checkpoint = checkpoint::get_or_init(mev_pool, id, Position::Timestamp(now)).await?;
+    debug!(monitor = %id, position = %checkpoint.position, "found inclusion checkpoint");
+    Ok(checkpoint)
 }
 
 async fn process_delivered_payloads(
-    beacon_api: &BeaconApi,
+    blocks: &BlockCache,
     loki_client: &LokiClient,
     relay_pool: &PgPool,
     mev_pool: &PgPool,
     start: &DateTime<Utc>,
     end: &DateTime<Utc>,
-    processed: &mut std::collections::HashSet<(i64, String)>,
+    run: &mut InclusionRun,
 ) -> anyhow::Result<Option<i64>> {
     debug!("fetching delivered payloads between {} and {}", start, end);
     let payloads = get_delivered_payloads(relay_pool, start, end).await?;
     debug!("fetched {} delivered payloads", payloads.len());
     for payload in &payloads {
-        processed.insert((payload.slot, payload.block_hash.clone()));
-        check_missing_payload(beacon_api, loki_client, mev_pool, payload, relay_pool).await?;
+        run.processed
+            .insert((payload.slot, payload.block_hash.clone()));
+    }
+
+    // Checks run concurrently but results come back in order, so misses are handled in slot order
+    // and the first failure stops the run before the checkpoint moves.
+    let mut checks = stream::iter(&payloads)
+        .map(|payload| async move {
+            let result =
+                check_missing_payload(blocks, loki_client, mev_pool, payload, relay_pool).await;
+            (payload, result)
+        })
+        .buffered(check_concurrency());
+    while let Some((payload, result)) = checks.next().await {
+        if let Some(miss) = result? {
+            run.add_miss(mev_pool, miss).await?;
+        }
         debug!(
             slot = payload.slot,
             block_hash = payload.block_hash,
@@ -623,16 +958,16 @@ async fn process_delivered_payloads(
 }
 
 async fn process_header_payload_candidates(
-    beacon_api: &BeaconApi,
+    blocks: &BlockCache,
     loki_client: &LokiClient,
     relay_pool: &PgPool,
     mevdb_pool: &PgPool,
     start: &DateTime<Utc>,
     end: &DateTime<Utc>,
-    processed: &mut std::collections::HashSet<(i64, String)>,
+    run: &mut InclusionRun,
 ) -> anyhow::Result<Option<i64>> {
-    let start_slot = Slot::from_date_time_rounded_down(start).0 as i64;
-    let end_slot = Slot::from_date_time_rounded_down(end).0 as i64;
+    let start_slot = CHAIN_TIME.slot_at(start).0;
+    let end_slot = CHAIN_TIME.slot_at(end).0;
     debug!(
         "fetching header/payload requests between slots {} and {}",
         start_slot, end_slot
@@ -658,7 +993,7 @@ async fn process_header_payload_candidates(
         let key = (r.slot, r.block_hash.clone());
         if let Some(h) = header_by_key.get(&key) {
             // compute ms into slot for payload request
-            let ms_into_slot = ms_into_slot(r.slot, &r.received_at);
+            let ms_into_slot = CHAIN_TIME.ms_into_slot(Slot(r.slot), &r.received_at);
             if ms_into_slot > TIMELY_PAYLOAD_REQUEST_MS {
                 debug!(
                     slot = r.slot,
@@ -669,7 +1004,7 @@ async fn process_header_payload_candidates(
                 continue;
             }
 
-            if !processed.contains(&(h.slot, h.block_hash.clone())) {
+            if !run.processed.contains(&(h.slot, h.block_hash.clone())) {
                 candidates.push(DeliveredPayload {
                     block_hash: h.block_hash.clone(),
                     block_number: 0,
@@ -678,7 +1013,7 @@ async fn process_header_payload_candidates(
                     slot: h.slot,
                     geo: h.geo.clone(),
                 });
-                processed.insert((h.slot, h.block_hash.clone()));
+                run.processed.insert((h.slot, h.block_hash.clone()));
             }
         }
     }
@@ -687,8 +1022,18 @@ async fn process_header_payload_candidates(
     } else {
         info!("checking {} header/payload candidates", candidates.len());
     }
-    for candidate in &candidates {
-        check_missing_candidate(beacon_api, loki_client, mevdb_pool, relay_pool, candidate).await?;
+    let mut checks = stream::iter(&candidates)
+        .map(|candidate| async move {
+            let result =
+                check_missing_candidate(blocks, loki_client, mevdb_pool, relay_pool, candidate)
+                    .await;
+            (candidate, result)
+        })
+        .buffered(check_concurrency());
+    while let Some((candidate, result)) = checks.next().await {
+        if let Some(miss) = result? {
+            run.add_miss(mevdb_pool, miss).await?;
+        }
         debug!(
             slot = candidate.slot,
             block_hash = candidate.block_hash,
@@ -733,92 +1078,120 @@ async fn maybe_alert_recent_missed_slots(
 pub async fn run_inclusion_monitor(
     relay_pool: &PgPool,
     mev_pool: &PgPool,
+    now: DateTime<Utc>,
     canonical_horizon: &DateTime<Utc>,
     loki_client: &LokiClient,
 ) -> anyhow::Result<()> {
-    let beacon_api = BeaconApi::new(&APP_CONFIG.consensus_nodes);
+    let blocks = BlockCache::new(BeaconApi::new(&APP_CONFIG.consensus_nodes));
 
     // use separate checkpoints for the delivered and intersection flows
     let delivered_checkpoint =
-        get_or_init_inclusion_checkpoint(mev_pool, CheckpointId::InclusionDelivered).await?;
+        get_or_init_inclusion_checkpoint(mev_pool, CheckpointId::InclusionDelivered, now).await?;
     let payload_requests_checkpoint =
-        get_or_init_inclusion_checkpoint(mev_pool, CheckpointId::InclusionPayloadRequests).await?;
-
-    debug!(
-        "checking delivered between {} and {}",
-        &delivered_checkpoint, canonical_horizon
-    );
-    debug!(
-        "checking payload_requests between {} and {}",
-        &payload_requests_checkpoint, canonical_horizon
-    );
+        get_or_init_inclusion_checkpoint(mev_pool, CheckpointId::InclusionPayloadRequests, now)
+            .await?;
 
-    use std::collections::HashSet;
-    let mut processed: HashSet<(i64, String)> = HashSet::new();
+    let mut run = InclusionRun::new(true);
 
-    let last_from_delivered = process_delivered_payloads(
-        &beacon_api,
-        loki_client,
-        relay_pool,
-        mev_pool,
-        &delivered_checkpoint,
-        canonical_horizon,
-        &mut processed,
-    )
-    .await?;
+    let last_from_delivered = if delivered_checkpoint.paused {
+        debug!("delivered payload checks are paused");
+        None
+    } else {
+        let start = delivered_checkpoint.timestamp()?;
+        debug!(
+            "checking delivered between {} and {}",
+            &start, canonical_horizon
+        );
+        process_delivered_payloads(
+            &blocks,
+            loki_client,
+            relay_pool,
+            mev_pool,
+            &start,
+            canonical_horizon,
+            &mut run,
+        )
+        .await?
+    };
+    let delivered_count = run.processed.len();
 
-    let last_from_candidates = process_header_payload_candidates(
-        &beacon_api,
-        loki_client,
-        relay_pool,
-        mev_pool,
-        &payload_requests_checkpoint,
-        canonical_horizon,
-        &mut processed,
-    )
-    .await?;
+    let last_from_candidates = if payload_requests_checkpoint.paused {
+        debug!("payload request checks are paused");
+        None
+    } else {
+        let start = payload_requests_checkpoint.timestamp()?;
+        debug!(
+            "checking payload_requests between {} and {}",
+            &start, canonical_horizon
+        );
+        process_header_payload_candidates(
+            &blocks,
+            loki_client,
+            relay_pool,
+            mev_pool,
+            &start,
+            canonical_horizon,
+            &mut run,
+        )
+        .await?
+    };
+    let candidate_count = run.processed.len() - delivered_count;
 
     let last_slot_delivered = last_from_delivered.or(last_from_candidates);
 
     maybe_alert_recent_missed_slots(mev_pool, last_slot_delivered).await?;
 
-    checkpoint::put_checkpoint(
-        mev_pool,
-        CheckpointId::InclusionDelivered,
-        canonical_horizon,
-    )
-    .await?;
-    checkpoint::put_checkpoint(
-        mev_pool,
-        CheckpointId::InclusionPayloadRequests,
-        canonical_horizon,
-    )
-    .await?;
+    let end = Position::Timestamp(*canonical_horizon);
+    if !delivered_checkpoint.paused {
+        checkpoint::advance(mev_pool, &delivered_checkpoint, end, delivered_count).await?;
+    }
+    if !payload_requests_checkpoint.paused {
+        checkpoint::advance(mev_pool, &payload_requests_checkpoint, end, candidate_count).await?;
+    }
     info!("inclusion monitor run completed");
 
     Ok(())
 }
 
-// there seems to be an issue running tests for this module
-// temp removing the test module altogether.
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-//     use chrono::Duration;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beacon_api::mock::{BlockReply, MockBeaconNode};
+    use reqwest::StatusCode;
 
-//     #[test]
-//     fn test_ms_into_slot_calculation() {
-//         // choose a concrete slot, derive its start time via Slot -> DateTime
-//         let slot = Slot(1_234_567);
-//         let slot_start: DateTime<Utc> = (&slot).into();
+    fn delivered_payload(slot: i64, block_number: i64) -> DeliveredPayload {
+        DeliveredPayload {
+            block_hash: "0x9a".to_string(),
+            block_number,
+            inserted_at: Utc::now(),
+            proposer_pubkey: "0x76".to_string(),
+            slot,
+            geo: Geo::RBX,
+        }
+    }
 
-//         let within = slot_start + Duration::milliseconds(2_500);
-//         let late = slot_start + Duration::milliseconds(3_100);
+    #[tokio::test]
+    async fn test_was_attempted_reorg() {
+        let node = MockBeaconNode::start();
+        node.set_block(99, "0x01", 1000);
+        node.set_block(199, "0x02", 1999);
+        let blocks = BlockCache::new(BeaconApi::new(&[node.url()]));
 
-//         let ms_within = ms_into_slot(slot.0 as i64, &within);
-//         let ms_late = ms_into_slot(slot.0 as i64, &late);
+        // The previous slot already holds a block at the height we delivered for.
+        assert!(was_attempted_reorg(&blocks, &delivered_payload(100, 1000))
+            .await
+            .unwrap());
+        assert!(!was_attempted_reorg(&blocks, &delivered_payload(200, 2001))
+            .await
+            .unwrap());
+        // An empty previous slot is no reorg.
+        assert!(!was_attempted_reorg(&blocks, &delivered_payload(300, 3000))
+            .await
+            .unwrap());
 
-//         assert!(ms_within <= 3_000);
-//         assert!(ms_late > 3_000);
-//     }
-// }
+        node.set_block_reply(399, BlockReply::Error(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(was_attempted_reorg(&blocks, &delivered_payload(400, 4000))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,128 @@
+//! Records publish stats for every delivered payload, not just the missed ones. This gives us a
+//! baseline to compare misses against, and lets us spot publish latency regressions before they
+//! cause misses.
+
+use chrono::{DateTime, Duration, Utc};
+use futures::{stream, StreamExt};
+use sqlx::PgPool;
+use tracing::{debug, info, warn};
+
+use crate::phoenix::checkpoint::{self, CheckpointId, Position};
+
+use super::{
+    check_concurrency, get_delivered_payloads, get_or_init_inclusion_checkpoint, DeliveredPayload,
+    LokiClient, PublishedPayloadStats,
+};
+
+async fn insert_publish_stats(
+    mev_pool: &PgPool,
+    payload: &DeliveredPayload,
+    stats: &PublishedPayloadStats,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO payload_publish_stats (
+            slot_number,
+            geo,
+            block_hash,
+            delivered_at,
+            decoded_at_slot_age_ms,
+            pre_publish_duration_ms,
+            publish_duration_ms,
+            request_download_duration_ms,
+            publish_done_ms_into_slot
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        ON CONFLICT (slot_number, geo) DO NOTHING
+        "#,
+    )
+    .bind(payload.slot)
+    .bind(payload.geo.to_string())
+    .bind(&payload.block_hash)
+    .bind(payload.inserted_at)
+    .bind(stats.decoded_at_slot_age_ms)
+    .bind(stats.pre_publish_duration_ms)
+    .bind(stats.publish_duration_ms)
+    .bind(stats.request_download_duration_ms)
+    .bind(stats.publish_done_ms_into_slot())
+    .execute(mev_pool)
+    .await
+    .map(|_| ())
+    .map_err(Into::into)
+}
+
+pub async fn run_publish_stats_monitor(
+    relay_pool: &PgPool,
+    mev_pool: &PgPool,
+    now: DateTime<Utc>,
+    canonical_horizon: &DateTime<Utc>,
+    loki_client: &LokiClient,
+) -> anyhow::Result<()> {
+    let checkpoint =
+        get_or_init_inclusion_checkpoint(mev_pool, CheckpointId::PublishStats, now).await?;
+    if checkpoint.paused {
+        debug!("publish stats monitor is paused");
+        return Ok(());
+    }
+
+    let payloads =
+        get_delivered_payloads(relay_pool, &checkpoint.timestamp()?, canonical_horizon).await?;
+    debug!(
+        "fetching publish stats for {} delivered payloads",
+        payloads.len()
+    );
+
+    let mut lookups = stream::iter(&payloads)
+        .map(|payload| async move {
+            let stats = loki_client
+                .published_stats(payload.slot, &payload.geo)
+                .await;
+            (payload, stats)
+        })
+        .buffered(check_concurrency());
+
+    // A failed lookup stops the run just before the payload, so the next run tries it again.
+    // Payloads delivered at the same time are looked up again too, storing them is idempotent.
+    let mut checked_up_to = *canonical_horizon;
+    let mut checked_count = 0;
+    let mut stored_count = 0;
+    while let Some((payload, stats)) = lookups.next().await {
+        match stats {
+            Ok(Some(stats)) => {
+                insert_publish_stats(mev_pool, payload, &stats).await?;
+                stored_count += 1;
+            }
+            Ok(None) => debug!(
+                slot = payload.slot,
+                geo = %payload.geo,
+                "no publish log found for delivered payload"
+            ),
+            Err(err) => {
+                warn!(
+                    slot = payload.slot,
+                    geo = %payload.geo,
+                    "failed to get publish stats for delivered payload, retrying next run: {:#}",
+                    err
+                );
+                checked_up_to = payload.inserted_at - Duration::microseconds(1);
+                break;
+            }
+        }
+        checked_count += 1;
+    }
+
+    checkpoint::advance(
+        mev_pool,
+        &checkpoint,
+        Position::Timestamp(checked_up_to),
+        checked_count,
+    )
+    .await?;
+    info!(
+        "stored publish stats for {}/{} delivered payloads",
+        stored_count,
+        payloads.len()
+    );
+
+    Ok(())
+}
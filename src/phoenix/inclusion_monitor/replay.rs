@@ -0,0 +1,183 @@
+//! Re-run the inclusion checks over a past range, e.g. after an outage or after fixing a
+//! detection bug. Unlike the live monitor this never reads or moves the inclusion checkpoints.
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::postgres::PgPoolOptions;
+use tracing::info;
+
+use crate::{
+    beacon_api::BeaconApi,
+    chain_time::{ChainTime, Slot, SlotRange},
+    phoenix::env::{APP_CONFIG, CHAIN_TIME},
+};
+
+use super::{
+    process_delivered_payloads, process_header_payload_candidates, BlockCache, InclusionRun,
+    LokiClient,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayRange {
+    Slots(SlotRange),
+    /// Time range, exclusive start and inclusive end, like the live monitor windows.
+    Time(DateTime<Utc>, DateTime<Utc>),
+}
+
+impl ReplayRange {
+    /// Parse a range given as either two slot numbers or two RFC 3339 timestamps.
+    pub fn parse(start: &str, end: &str) -> anyhow::Result<Self> {
+        let range = match (start.parse::<Slot>(), end.parse::<Slot>()) {
+            (Ok(start), Ok(end)) => ReplayRange::Slots(SlotRange::new(start, end)),
+            _ => ReplayRange::Time(
+                start
+                    .parse()
+                    .with_context(|| format!("failed to parse {} as slot or timestamp", start))?,
+                end.parse()
+                    .with_context(|| format!("failed to parse {} as slot or timestamp", end))?,
+            ),
+        };
+
+        let is_empty = match range {
+            ReplayRange::Slots(slots) => slots.is_empty(),
+            ReplayRange::Time(start, end) => start >= end,
+        };
+        if is_empty {
+            bail!("replay range start must come before its end");
+        }
+
+        Ok(range)
+    }
+
+    /// Bounds to pass to the inclusion checks. For slots these are picked such that
+    /// the delivered payload window and the rounded down request window both cover exactly the
+    /// requested slots.
+    fn time_bounds(&self, chain_time: &ChainTime) -> (DateTime<Utc>, DateTime<Utc>) {
+        match *self {
+            ReplayRange::Slots(slots) => {
+                let start_slot = chain_time.slot_start(slots.start);
+                let end_slot = chain_time.slot_start(slots.end + 1);
+                (
+                    start_slot - Duration::milliseconds(1),
+                    end_slot - Duration::milliseconds(1),
+                )
+            }
+            ReplayRange::Time(start, end) => (start, end),
+        }
+    }
+}
+
+fn format_report(range: &ReplayRange, run: &InclusionRun) -> String {
+    let mut report = format!(
+        "inclusion replay over {:?}, checked {} payloads, found {} unreported missed slots\n",
+        range,
+        run.processed.len(),
+        run.misses.len()
+    );
+
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for miss in &run.misses {
+        *counts.entry(miss.root_cause.to_string()).or_default() += 1;
+        report.push_str(&format!(
+            "{} {} relayed={} canonical={} root_cause={}\n",
+            miss.slot,
+            miss.geo,
+            miss.relayed_block_hash,
+            miss.canonical_block_hash.as_deref().unwrap_or("-"),
+            miss.root_cause
+        ));
+    }
+
+    for (root_cause, count) in counts {
+        report.push_str(&format!("{}: {}\n", root_cause, count));
+    }
+
+    report
+}
+
+/// Check all payloads in `range` and print a report of the missed slots not reported yet. Misses
+/// are always stored in `missed_slots`, telegram alerts are only sent, and misses marked as
+/// reported, when `send_alerts` is set.
+pub async fn replay_inclusion(range: ReplayRange, send_alerts: bool) -> anyhow::Result<()> {
+    crate::log::init();
+
+    let relay_pool = PgPoolOptions::new()
+        .max_connections(3)
+        .connect(&APP_CONFIG.relay_database_url)
+        .await?;
+    let mev_pool = PgPoolOptions::new()
+        .max_connections(3)
+        .connect(&APP_CONFIG.database_url)
+        .await?;
+    let loki_client = LokiClient::from_config()?;
+    let blocks = BlockCache::new(BeaconApi::new(&APP_CONFIG.consensus_nodes));
+
+    let (start, end) = range.time_bounds(&CHAIN_TIME);
+    info!(%start, %end, send_alerts, "replaying inclusion monitor");
+
+    let mut run = InclusionRun::new(send_alerts);
+
+    process_delivered_payloads(
+        &blocks,
+        &loki_client,
+        &relay_pool,
+        &mev_pool,
+        &start,
+        &end,
+        &mut run,
+    )
+    .await?;
+
+    process_header_payload_candidates(
+        &blocks,
+        &loki_client,
+        &relay_pool,
+        &mev_pool,
+        &start,
+        &end,
+        &mut run,
+    )
+    .await?;
+
+    print!("{}", format_report(&range, &run));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::Network;
+
+    #[test]
+    fn test_parse_replay_range() {
+        assert_eq!(
+            ReplayRange::parse("100", "200").unwrap(),
+            ReplayRange::Slots(SlotRange::new(Slot(100), Slot(200)))
+        );
+        assert_eq!(
+            ReplayRange::parse("2024-06-01T00:00:00Z", "2024-06-02T00:00:00Z").unwrap(),
+            ReplayRange::Time(
+                "2024-06-01T00:00:00Z".parse().unwrap(),
+                "2024-06-02T00:00:00Z".parse().unwrap()
+            )
+        );
+        assert!(ReplayRange::parse("200", "100").is_err());
+        assert!(ReplayRange::parse("100", "tomorrow").is_err());
+    }
+
+    #[test]
+    fn test_slot_range_time_bounds() {
+        let chain_time = ChainTime::for_network(&Network::mainnet());
+        let range = ReplayRange::Slots(SlotRange::new(Slot(100), Slot(200)));
+        let (start, end) = range.time_bounds(&chain_time);
+        // Header requests are selected with slot > start slot and slot <= end slot.
+        assert_eq!(chain_time.slot_at(&start), Slot(99));
+        assert_eq!(chain_time.slot_at(&end), Slot(200));
+        // Delivered payloads are selected with inserted_at > start and inserted_at <= end.
+        assert!(start < chain_time.slot_start(Slot(100)));
+        assert!(end < chain_time.slot_start(Slot(201)));
+    }
+}
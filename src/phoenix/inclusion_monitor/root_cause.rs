@@ -0,0 +1,145 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use super::{
+    loki_client::{LatePayloadStats, PublishedPayloadStats},
+    DetectionMethod,
+};
+
+/// Publishing completing later than this many ms into the slot leaves too little time for the
+/// block to propagate before the attestation deadline.
+const LATE_PUBLISH_MS_INTO_SLOT: i64 = 4000;
+
+/// Most likely reason a payload we delivered did not make it on-chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissCategory {
+    /// The proposer built on the parent of the previous block, trying to reorg it out.
+    AttemptedReorg,
+    /// The proposer asked for the payload too late for us to publish it.
+    LateProposerRequest,
+    /// We did not publish, or publishing failed, without the proposer being late.
+    RelayFault,
+    /// We published, but too late into the slot for the block to propagate.
+    SlowPublish,
+    /// We published in time and without errors, yet the block did not become canonical.
+    ProposerMissed,
+    /// The evidence could not be collected.
+    Unknown,
+}
+
+impl fmt::Display for MissCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MissCategory::AttemptedReorg => write!(f, "attempted_reorg"),
+            MissCategory::LateProposerRequest => write!(f, "late_proposer_request"),
+            MissCategory::RelayFault => write!(f, "relay_fault"),
+            MissCategory::SlowPublish => write!(f, "slow_publish"),
+            MissCategory::ProposerMissed => write!(f, "proposer_missed"),
+            MissCategory::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Signals collected for a missed slot, stored alongside the category so the classification can
+/// be audited and revisited.
+#[derive(Debug, Default, Serialize)]
+pub struct MissEvidence {
+    pub canonical_block_found: bool,
+    pub detection_method: DetectionMethod,
+    pub is_adjustment: bool,
+    pub is_attempted_reorg: bool,
+    pub late_call_stats: Option<LatePayloadStats>,
+    pub proposer_city: Option<String>,
+    pub proposer_country: Option<String>,
+    /// None when we found no log of publishing the block.
+    pub publish_done_ms_into_slot: Option<i64>,
+    pub publish_error_count: usize,
+    pub published_stats: Option<PublishedPayloadStats>,
+}
+
+pub fn classify(evidence: &MissEvidence) -> MissCategory {
+    if evidence.is_attempted_reorg {
+        return MissCategory::AttemptedReorg;
+    }
+
+    match evidence.publish_done_ms_into_slot {
+        None if evidence.late_call_stats.is_some() => MissCategory::LateProposerRequest,
+        None => MissCategory::RelayFault,
+        Some(_) if evidence.publish_error_count > 0 => MissCategory::RelayFault,
+        Some(ms) if ms > LATE_PUBLISH_MS_INTO_SLOT => MissCategory::SlowPublish,
+        Some(_) => MissCategory::ProposerMissed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn published(decoded_at_slot_age_ms: i64) -> MissEvidence {
+        let stats = PublishedPayloadStats {
+            decoded_at_slot_age_ms,
+            pre_publish_duration_ms: 100,
+            publish_duration_ms: 300,
+            request_download_duration_ms: 50,
+        };
+        MissEvidence {
+            publish_done_ms_into_slot: Some(stats.publish_done_ms_into_slot()),
+            published_stats: Some(stats),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_attempted_reorg_takes_precedence() {
+        let evidence = MissEvidence {
+            is_attempted_reorg: true,
+            publish_error_count: 2,
+            ..Default::default()
+        };
+        assert_eq!(classify(&evidence), MissCategory::AttemptedReorg);
+    }
+
+    #[test]
+    fn test_late_proposer_request() {
+        let evidence = MissEvidence {
+            late_call_stats: Some(LatePayloadStats {
+                decoded_at_slot_age_ms: 4200,
+                request_download_duration_ms: 40,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(classify(&evidence), MissCategory::LateProposerRequest);
+    }
+
+    #[test]
+    fn test_relay_fault() {
+        assert_eq!(classify(&MissEvidence::default()), MissCategory::RelayFault);
+
+        let evidence = MissEvidence {
+            publish_error_count: 1,
+            ..published(1000)
+        };
+        assert_eq!(classify(&evidence), MissCategory::RelayFault);
+    }
+
+    #[test]
+    fn test_evidence_includes_publish_done() {
+        let json = serde_json::to_value(published(3800)).unwrap();
+        assert_eq!(json["publish_done_ms_into_slot"], 4150);
+        let json = serde_json::to_value(MissEvidence::default()).unwrap();
+        assert!(json["publish_done_ms_into_slot"].is_null());
+    }
+
+    #[test]
+    fn test_slow_publish() {
+        let evidence = published(3800);
+        assert_eq!(evidence.publish_done_ms_into_slot, Some(4150));
+        assert_eq!(classify(&evidence), MissCategory::SlowPublish);
+    }
+
+    #[test]
+    fn test_proposer_missed() {
+        assert_eq!(classify(&published(1000)), MissCategory::ProposerMissed);
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashSet, sync::LazyLock};
+use std::{collections::HashSet, fmt};
 
 use anyhow::Result;
 use chrono::{DateTime, Utc};
@@ -8,9 +8,13 @@ use tracing::{debug, info, warn};
 
 use super::{
     alerts::telegram::{Channel, TelegramBot, TelegramMessage},
-    checkpoint::{self, CheckpointId},
+    builder_contacts::{self, NotificationKind},
+    checkpoint::{self, CheckpointId, Position},
     demotion_monitor::{get_builder_demotions, BuilderDemotion},
     env::APP_CONFIG,
+    error_rules::{self, ErrorRules},
+    promotion_policy::{self, BuilderRisk, PolicyViolation},
+    promotion_tokens,
 };
 
 async fn get_missed_slots(mev_pool: &PgPool, start: &DateTime<Utc>) -> Result<Vec<i64>> {
@@ -74,44 +78,13 @@ fn format_builder_list(builders: &Vec<(String, String)>) -> String {
         })
 }
 
-/// Demotion errors eligible for re-promotion if no slot was missed
-const PROMOTABLE_ERRORS: &[&str] = &[
-    "HTTP status server error (500 Internal Server Error) for url (http://prio-load-balancer/)",
-    "Post \"http://prio-load-balancer:80\": context deadline exceeded (Client.Timeout exceeded while awaiting headers)",
-    "json error: request timeout hit before processing",
-    "simulation failed: unknown ancestor",
-    "simulation failed: parent block not found",
-    "simulation failed: incorrect gas limit set",
-    "simulation queue timed out",
-    "parent_beacon_block_root is None for V3 request"
-];
-
-static PROMOTABLE_TRUSTED_BUILDER_ERRORS: LazyLock<HashSet<String>> = LazyLock::new(|| {
-    vec![
-        "simulation failed: could not apply tx".to_string(),
-        "simulation failed: invalid merkle root".to_string(),
-    ]
-    .into_iter()
-    .collect()
-});
-
-pub fn is_promotable_error(error: &str) -> bool {
-    PROMOTABLE_ERRORS
-        .iter()
-        // Use starts_with to account for additional info in gas limit error
-        .any(|promotable_error| error.starts_with(promotable_error))
-}
-
 fn is_promotable_trusted_builder_error(
+    rules: &ErrorRules,
     trusted_builders: &HashSet<String>,
     builder_id: &String,
     error: &str,
 ) -> bool {
-    trusted_builders.contains(builder_id)
-        && PROMOTABLE_TRUSTED_BUILDER_ERRORS
-            .iter()
-            // Use starts_with to account for additional info in gas limit error
-            .any(|e| error.starts_with(e))
+    trusted_builders.contains(builder_id) && rules.is_trusted_promotable(error)
 }
 
 fn filter_known_builder_demotions(
@@ -130,30 +103,181 @@ fn filter_known_builder_demotions(
         .collect()
 }
 
-fn check_eligibility(
+/// The rule which allowed a builder to be promoted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PromotionRule {
+    /// Every demotion was for a promotable error.
+    PromotableErrors,
+    /// Some demotions were for errors only promotable for trusted builders.
+    TrustedBuilderErrors,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rejection {
+    MissedSlot,
+    NonPromotableError,
+    Policy(PolicyViolation),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PromotionDecision {
+    Eligible(PromotionRule),
+    Rejected(Rejection),
+}
+
+impl PromotionDecision {
+    fn is_eligible(&self) -> bool {
+        matches!(self, PromotionDecision::Eligible(_))
+    }
+}
+
+impl fmt::Display for PromotionRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PromotionRule::PromotableErrors => write!(f, "promotable_errors"),
+            PromotionRule::TrustedBuilderErrors => write!(f, "trusted_builder_errors"),
+        }
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rejection::MissedSlot => write!(f, "missed_slot"),
+            Rejection::NonPromotableError => write!(f, "non_promotable_error"),
+            Rejection::Policy(violation) => write!(f, "{}", violation),
+        }
+    }
+}
+
+/// What came of a promotion decision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PromotionOutcome {
+    Promoted,
+    /// Eligible, but the builder wasn't updated, it had no collateral or was already optimistic.
+    NotUpdated,
+    /// Eligible, but shadow mode is on.
+    Shadow,
+    Rejected,
+}
+
+impl fmt::Display for PromotionOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PromotionOutcome::Promoted => write!(f, "promoted"),
+            PromotionOutcome::NotUpdated => write!(f, "not_updated"),
+            PromotionOutcome::Shadow => write!(f, "shadow"),
+            PromotionOutcome::Rejected => write!(f, "rejected"),
+        }
+    }
+}
+
+fn decide_promotion(
+    rules: &ErrorRules,
     trusted_builders: &HashSet<String>,
     builder_id: &String,
     demotions: &[BuilderDemotion],
     missed_slots: &[i64],
-) -> bool {
-    let no_missed_slots = demotions.iter().all(|d| !missed_slots.contains(&d.slot));
+) -> PromotionDecision {
+    if demotions.iter().any(|d| missed_slots.contains(&d.slot)) {
+        return PromotionDecision::Rejected(Rejection::MissedSlot);
+    }
+
+    if demotions.iter().all(|d| rules.is_promotable(&d.sim_error)) {
+        return PromotionDecision::Eligible(PromotionRule::PromotableErrors);
+    }
+
     let all_eligible_errors = demotions.iter().all(|d| {
-        is_promotable_error(&d.sim_error)
-            || is_promotable_trusted_builder_error(trusted_builders, builder_id, &d.sim_error)
+        rules.is_promotable(&d.sim_error)
+            || is_promotable_trusted_builder_error(
+                rules,
+                trusted_builders,
+                builder_id,
+                &d.sim_error,
+            )
     });
-    no_missed_slots && all_eligible_errors
+    if all_eligible_errors {
+        PromotionDecision::Eligible(PromotionRule::TrustedBuilderErrors)
+    } else {
+        PromotionDecision::Rejected(Rejection::NonPromotableError)
+    }
+}
+
+struct PromotionRecord<'a> {
+    builder_id: &'a str,
+    demotions: &'a [BuilderDemotion],
+    missed_slots: &'a [i64],
+    decision: PromotionDecision,
+    /// Only looked up for builders whose errors allowed a promotion.
+    risk: Option<&'a BuilderRisk>,
+    outcome: PromotionOutcome,
+    shadow: bool,
+}
+
+async fn insert_promotion_decision(
+    mev_pool: &PgPool,
+    rules: &ErrorRules,
+    record: &PromotionRecord<'_>,
+) -> Result<()> {
+    let demotions = record
+        .demotions
+        .iter()
+        .map(|d| {
+            serde_json::json!({
+                "slot": d.slot,
+                "geo": d.geo.to_string(),
+                "block_hash": d.block_hash,
+                "builder_pubkey": d.builder_pubkey,
+                "sim_error": d.sim_error,
+                "category": rules.classify(&d.sim_error).to_string(),
+            })
+        })
+        .collect_vec();
+    let (rule, rejection) = match record.decision {
+        PromotionDecision::Eligible(rule) => (Some(rule.to_string()), None),
+        PromotionDecision::Rejected(rejection) => (None, Some(rejection.to_string())),
+    };
+
+    sqlx::query(
+        "
+        INSERT INTO promotion_decisions (
+            builder_id,
+            demotions,
+            missed_slots_checked,
+            rule,
+            rejection,
+            risk,
+            outcome,
+            shadow
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ",
+    )
+    .bind(record.builder_id)
+    .bind(serde_json::Value::Array(demotions))
+    .bind(record.missed_slots)
+    .bind(rule)
+    .bind(rejection)
+    .bind(record.risk.map(serde_json::to_value).transpose()?)
+    .bind(record.outcome.to_string())
+    .bind(record.shadow)
+    .execute(mev_pool)
+    .await
+    .map(|_| ())
+    .map_err(Into::into)
 }
 
 async fn send_trusted_promotion_messages(
+    mev_pool: &PgPool,
+    rules: &ErrorRules,
     trusted_builders: &HashSet<String>,
     telegram_bot: &TelegramBot,
     builder_id: &String,
     demotions: &[BuilderDemotion],
 ) {
-    if demotions
-        .iter()
-        .any(|d| is_promotable_trusted_builder_error(trusted_builders, builder_id, &d.sim_error))
-    {
+    if demotions.iter().any(|d| {
+        is_promotable_trusted_builder_error(rules, trusted_builders, builder_id, &d.sim_error)
+    }) {
         let message = format!(
             "automatically repromoting builder `{}` for error which may result in missed slot",
             builder_id
@@ -165,35 +289,43 @@ async fn send_trusted_promotion_messages(
             .send_message(&message, Channel::Demotions)
             .await;
 
-        // Send to builder
-        telegram_bot
-            .send_message_to_builder(&message, builder_id, None)
-            .await;
+        builder_contacts::notify_builder(
+            mev_pool,
+            telegram_bot,
+            builder_id,
+            NotificationKind::Promotion,
+            &message,
+            None,
+        )
+        .await;
     }
 }
 
 pub async fn run_promotion_monitor(
     relay_pool: &PgPool,
     mev_pool: &PgPool,
+    now: DateTime<Utc>,
     canonical_horizon: &DateTime<Utc>,
 ) -> Result<()> {
-    let checkpoint = match checkpoint::get_checkpoint(mev_pool, CheckpointId::Promotion).await? {
-        Some(c) => c,
-        None => {
-            info!("no checkpoint found, initializing");
-            let now = Utc::now();
-            checkpoint::put_checkpoint(mev_pool, CheckpointId::Promotion, &now).await?;
-            now
-        }
-    };
+    let checkpoint =
+        checkpoint::get_or_init(mev_pool, CheckpointId::Promotion, Position::Timestamp(now))
+            .await?;
+    if checkpoint.paused {
+        debug!("promotion monitor is paused");
+        return Ok(());
+    }
+    let start = checkpoint.timestamp()?;
 
     debug!(
         "checking promotions between {} and {}",
-        &checkpoint, &canonical_horizon
+        &start, &canonical_horizon
     );
 
-    let demotions = get_builder_demotions(relay_pool, &checkpoint, canonical_horizon).await?;
-    let missed_slots = get_missed_slots(mev_pool, &checkpoint).await?;
+    let demotions = get_builder_demotions(relay_pool, &start, canonical_horizon).await?;
+    let demotion_count = demotions.len();
+    let missed_slots = get_missed_slots(mev_pool, &start).await?;
+    let rules = error_rules::load_rules(mev_pool).await?;
+    let policies = promotion_policy::get_policies(mev_pool).await?;
 
     debug!(
         "scanning for promotable demotions, demotions: {:?}, missed_slots {:?}",
@@ -204,31 +336,113 @@ pub async fn run_promotion_monitor(
     let grouped_demotions = demotions_with_ids.into_iter().into_group_map();
 
     let mut eligible_builders = Vec::new();
+    let mut decisions = Vec::new();
     let telegram_bot = TelegramBot::new();
     let trusted_builders = &APP_CONFIG.trusted_builder_ids;
+    let shadow = APP_CONFIG.ff_promotion_shadow_mode;
 
     for (builder_id, demotions) in grouped_demotions {
-        if check_eligibility(trusted_builders, &builder_id, &demotions, &missed_slots) {
-            eligible_builders.push(builder_id.clone());
-            send_trusted_promotion_messages(
-                trusted_builders,
-                &telegram_bot,
+        let mut decision = decide_promotion(
+            &rules,
+            trusted_builders,
+            &builder_id,
+            &demotions,
+            &missed_slots,
+        );
+
+        // The errors allow a promotion, check the builder's recent behaviour allows it too.
+        let mut risk = None;
+        if decision.is_eligible() {
+            let policy = policies.get(&builder_id).cloned().unwrap_or_default();
+            let builder_risk = promotion_policy::get_builder_risk(
+                relay_pool,
+                mev_pool,
                 &builder_id,
-                &demotions,
+                &policy,
+                start,
+                now,
             )
-            .await;
+            .await?;
+            if let Some(violation) = policy.check(&builder_risk) {
+                info!(builder_id, %violation, ?builder_risk, "promotion policy violated");
+                decision = PromotionDecision::Rejected(Rejection::Policy(violation));
+            }
+            risk = Some(builder_risk);
         }
+
+        if decision.is_eligible() {
+            if shadow {
+                info!(builder_id, ?decision, "shadow mode, not promoting builder");
+            } else {
+                eligible_builders.push(builder_id.clone());
+                send_trusted_promotion_messages(
+                    mev_pool,
+                    &rules,
+                    trusted_builders,
+                    &telegram_bot,
+                    &builder_id,
+                    &demotions,
+                )
+                .await;
+            }
+        }
+        decisions.push((builder_id, demotions, decision, risk));
     }
 
-    if !eligible_builders.is_empty() {
+    let promoted_builder_ids: HashSet<String> = if eligible_builders.is_empty() {
+        HashSet::new()
+    } else {
         info!(
             "found builder ids eligible for promotion: {:?}",
             &eligible_builders
         );
-        promote_builder_ids(relay_pool, &eligible_builders).await?;
+        promote_builder_ids(relay_pool, &eligible_builders)
+            .await?
+            .into_iter()
+            .map(|(builder_id, _)| builder_id)
+            .collect()
+    };
+
+    // Builders promoted automatically have no use for the tokens of their demotion alerts.
+    for builder_id in &promoted_builder_ids {
+        promotion_tokens::revoke_builder_tokens(
+            relay_pool,
+            mev_pool,
+            builder_id,
+            "auto_promoted",
+            now,
+        )
+        .await?;
     }
 
-    checkpoint::put_checkpoint(mev_pool, CheckpointId::Promotion, canonical_horizon).await?;
+    for (builder_id, demotions, decision, risk) in &decisions {
+        let outcome = match decision {
+            PromotionDecision::Rejected(_) => PromotionOutcome::Rejected,
+            PromotionDecision::Eligible(_) if shadow => PromotionOutcome::Shadow,
+            PromotionDecision::Eligible(_) if promoted_builder_ids.contains(builder_id) => {
+                PromotionOutcome::Promoted
+            }
+            PromotionDecision::Eligible(_) => PromotionOutcome::NotUpdated,
+        };
+        let record = PromotionRecord {
+            builder_id,
+            demotions,
+            missed_slots: &missed_slots,
+            decision: *decision,
+            risk: risk.as_ref(),
+            outcome,
+            shadow,
+        };
+        insert_promotion_decision(mev_pool, &rules, &record).await?;
+    }
+
+    checkpoint::advance(
+        mev_pool,
+        &checkpoint,
+        Position::Timestamp(*canonical_horizon),
+        demotion_count,
+    )
+    .await?;
 
     Ok(())
 }
@@ -249,7 +463,15 @@ mod tests {
 
         let mut eligible_builders = Vec::new();
         for (builder_id, demotions) in grouped_demotions {
-            if check_eligibility(trusted_builders, &builder_id, &demotions, &missed_slots) {
+            if decide_promotion(
+                &ErrorRules::seeded(),
+                trusted_builders,
+                &builder_id,
+                &demotions,
+                &missed_slots,
+            )
+            .is_eligible()
+            {
                 eligible_builders.push(builder_id.clone());
             }
         }
@@ -458,6 +680,44 @@ mod tests {
         assert_eq!(result.len(), 0);
     }
 
+    #[test]
+    fn test_decide_promotion_rule() {
+        let demotion = |sim_error: &str| BuilderDemotion {
+            geo: Geo::RBX,
+            block_hash: "block_hash1".to_string(),
+            builder_pubkey: "pubkey1".to_string(),
+            sim_error: sim_error.to_string(),
+            slot: 1,
+            builder_id: Some("builder1".to_string()),
+        };
+        let rules = ErrorRules::seeded();
+        let trusted_builders = HashSet::from_iter(vec!["builder1".to_string()]);
+        let builder_id = "builder1".to_string();
+
+        let demotions = [demotion("simulation failed: unknown ancestor")];
+        assert_eq!(
+            decide_promotion(&rules, &trusted_builders, &builder_id, &demotions, &[]),
+            PromotionDecision::Eligible(PromotionRule::PromotableErrors)
+        );
+        assert_eq!(
+            decide_promotion(&rules, &trusted_builders, &builder_id, &demotions, &[1]),
+            PromotionDecision::Rejected(Rejection::MissedSlot)
+        );
+
+        let demotions = [
+            demotion("simulation failed: unknown ancestor"),
+            demotion("simulation failed: invalid merkle root"),
+        ];
+        assert_eq!(
+            decide_promotion(&rules, &trusted_builders, &builder_id, &demotions, &[]),
+            PromotionDecision::Eligible(PromotionRule::TrustedBuilderErrors)
+        );
+        assert_eq!(
+            decide_promotion(&rules, &HashSet::new(), &builder_id, &demotions, &[]),
+            PromotionDecision::Rejected(Rejection::NonPromotableError)
+        );
+    }
+
     #[test]
     fn test_format_builder_list() {
         let builder_ids = vec![
@@ -0,0 +1,264 @@
+//! Limits on re-promoting builders beyond their sim errors: how their recent bids compare with
+//! their collateral, how many of their blocks we missed and how often they were demoted lately.
+//! By default no limits apply, rows in `builder_promotion_policies` set them per builder.
+
+use std::{collections::HashMap, fmt};
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::{PgPool, Row};
+
+/// How far back we look at delivered payloads and missed slots.
+const HISTORY_WINDOW: Duration = Duration::days(7);
+
+/// Limits are None when they don't apply.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromotionPolicy {
+    /// Largest share of recently delivered payloads whose value may exceed the collateral.
+    pub max_over_collateral_share: Option<f64>,
+    /// Most missed slots with one of the builder's blocks within the history window.
+    pub max_missed_slots: Option<i64>,
+    /// Builders demoted for this many slots within the cooldown before the demotions under
+    /// evaluation stay demoted.
+    pub cooldown_demotions: Option<i64>,
+    pub cooldown: Duration,
+}
+
+impl Default for PromotionPolicy {
+    fn default() -> Self {
+        Self {
+            max_over_collateral_share: None,
+            max_missed_slots: None,
+            cooldown_demotions: None,
+            cooldown: Duration::hours(6),
+        }
+    }
+}
+
+/// What we know about a builder's recent behaviour.
+#[derive(Debug, Default, Serialize)]
+pub struct BuilderRisk {
+    pub delivered_count: i64,
+    pub over_collateral_count: i64,
+    pub missed_slot_count: i64,
+    pub cooldown_demotion_count: i64,
+}
+
+impl BuilderRisk {
+    fn over_collateral_share(&self) -> f64 {
+        if self.delivered_count == 0 {
+            0.0
+        } else {
+            self.over_collateral_count as f64 / self.delivered_count as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyViolation {
+    OverCollateral,
+    MissHistory,
+    Cooldown,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolicyViolation::OverCollateral => write!(f, "over_collateral"),
+            PolicyViolation::MissHistory => write!(f, "miss_history"),
+            PolicyViolation::Cooldown => write!(f, "cooldown"),
+        }
+    }
+}
+
+impl PromotionPolicy {
+    pub fn check(&self, risk: &BuilderRisk) -> Option<PolicyViolation> {
+        if self
+            .max_over_collateral_share
+            .is_some_and(|max| risk.over_collateral_share() > max)
+        {
+            Some(PolicyViolation::OverCollateral)
+        } else if self
+            .max_missed_slots
+            .is_some_and(|max| risk.missed_slot_count > max)
+        {
+            Some(PolicyViolation::MissHistory)
+        } else if self
+            .cooldown_demotions
+            .is_some_and(|max| risk.cooldown_demotion_count >= max)
+        {
+            Some(PolicyViolation::Cooldown)
+        } else {
+            None
+        }
+    }
+}
+
+/// Per builder policies, null columns leave that limit out.
+pub async fn get_policies(mev_pool: &PgPool) -> Result<HashMap<String, PromotionPolicy>> {
+    let rows = sqlx::query(
+        "
+        SELECT
+            builder_id,
+            max_over_collateral_share,
+            max_missed_slots,
+            cooldown_demotions,
+            cooldown_minutes
+        FROM builder_promotion_policies
+        ",
+    )
+    .fetch_all(mev_pool)
+    .await?;
+
+    let default = PromotionPolicy::default();
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let policy = PromotionPolicy {
+                max_over_collateral_share: row.get("max_over_collateral_share"),
+                max_missed_slots: row
+                    .get::<Option<i32>, _>("max_missed_slots")
+                    .map(Into::into),
+                cooldown_demotions: row
+                    .get::<Option<i32>, _>("cooldown_demotions")
+                    .map(Into::into),
+                cooldown: row
+                    .get::<Option<i32>, _>("cooldown_minutes")
+                    .map_or(default.cooldown, |minutes| {
+                        Duration::minutes(minutes.into())
+                    }),
+            };
+            (row.get("builder_id"), policy)
+        })
+        .collect())
+}
+
+/// `evaluation_start` is the start of the window whose demotions are being evaluated, the cooldown
+/// only counts demotions before it.
+pub async fn get_builder_risk(
+    relay_pool: &PgPool,
+    mev_pool: &PgPool,
+    builder_id: &str,
+    policy: &PromotionPolicy,
+    evaluation_start: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<BuilderRisk> {
+    let history_start = now - HISTORY_WINDOW;
+
+    let delivered = sqlx::query(
+        "
+        SELECT
+            COUNT(*) AS delivered_count,
+            COUNT(*) FILTER (WHERE pd.value > b.collateral) AS over_collateral_count
+        FROM payload_delivered pd
+        INNER JOIN builder b
+          ON pd.builder_pubkey = b.builder_pubkey
+        WHERE b.builder_id = $1
+          AND pd.inserted_at > $2
+        ",
+    )
+    .bind(builder_id)
+    .bind(history_start)
+    .fetch_one(relay_pool)
+    .await?;
+
+    // Missed slots live in our database, the builders of their blocks in the relay database.
+    let missed_block_hashes: Vec<String> = sqlx::query_scalar(
+        "
+        SELECT relayed_block_hash
+        FROM missed_slots
+        WHERE inserted_at > $1
+        ",
+    )
+    .bind(history_start)
+    .fetch_all(mev_pool)
+    .await?;
+
+    let missed_slot_count: i64 = sqlx::query_scalar(
+        "
+        SELECT COUNT(DISTINCT pd.slot)
+        FROM payload_delivered pd
+        INNER JOIN builder b
+          ON pd.builder_pubkey = b.builder_pubkey
+        WHERE b.builder_id = $1
+          AND pd.block_hash = ANY($2)
+        ",
+    )
+    .bind(builder_id)
+    .bind(&missed_block_hashes)
+    .fetch_one(relay_pool)
+    .await?;
+
+    let cooldown_demotion_count: i64 = sqlx::query_scalar(
+        "
+        SELECT COUNT(DISTINCT bd.slot)
+        FROM builder_demotions bd
+        INNER JOIN builder b
+          ON bd.builder_pubkey = b.builder_pubkey
+        WHERE b.builder_id = $1
+          AND bd.inserted_at > $2
+          AND bd.inserted_at <= $3
+        ",
+    )
+    .bind(builder_id)
+    .bind(evaluation_start - policy.cooldown)
+    .bind(evaluation_start)
+    .fetch_one(relay_pool)
+    .await?;
+
+    Ok(BuilderRisk {
+        delivered_count: delivered.get("delivered_count"),
+        over_collateral_count: delivered.get("over_collateral_count"),
+        missed_slot_count,
+        cooldown_demotion_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_policy_has_no_limits() {
+        let risk = BuilderRisk {
+            delivered_count: 10,
+            over_collateral_count: 10,
+            missed_slot_count: 100,
+            cooldown_demotion_count: 100,
+        };
+        assert_eq!(PromotionPolicy::default().check(&risk), None);
+    }
+
+    #[test]
+    fn test_check_policy() {
+        let policy = PromotionPolicy {
+            max_over_collateral_share: Some(0.05),
+            max_missed_slots: Some(2),
+            cooldown_demotions: Some(3),
+            cooldown: Duration::hours(6),
+        };
+        assert_eq!(policy.check(&BuilderRisk::default()), None);
+
+        let risk = BuilderRisk {
+            delivered_count: 10,
+            over_collateral_count: 1,
+            ..Default::default()
+        };
+        assert_eq!(policy.check(&risk), Some(PolicyViolation::OverCollateral));
+
+        let risk = BuilderRisk {
+            delivered_count: 100,
+            over_collateral_count: 1,
+            missed_slot_count: 3,
+            ..Default::default()
+        };
+        assert_eq!(policy.check(&risk), Some(PolicyViolation::MissHistory));
+
+        let risk = BuilderRisk {
+            cooldown_demotion_count: 3,
+            ..Default::default()
+        };
+        assert_eq!(policy.check(&risk), Some(PolicyViolation::Cooldown));
+    }
+}
@@ -0,0 +1,457 @@
+//! Promotion tokens let builders re-promote themselves through the button on demotion alerts.
+//! Tokens are checked by the relay against `promotion_tokens` in the relay database, which removes
+//! a token once it is used. We keep their lifecycle in `promotion_token_log`: when they were
+//! issued, revoked or used. A token which leaves the relay database before it expires or is
+//! revoked was used, `detect_used_tokens` records that. Who used it is only known when the promote
+//! endpoint reports it to the admin route. The admin routes list tokens by id, token values are
+//! only ever sent to the builder they are for.
+
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use sqlx::{postgres::PgRow, PgPool, Row};
+use tracing::info;
+
+use super::ServerState;
+
+const TOKEN_TTL: Duration = Duration::days(7);
+/// How long the log keeps tokens after they expired, were revoked or used.
+const TOKEN_LOG_RETENTION: Duration = Duration::days(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum TokenStatus {
+    Active,
+    Expired,
+    Revoked,
+    Used,
+}
+
+#[derive(Debug, Clone)]
+struct TokenLifecycle {
+    expires_at: DateTime<Utc>,
+    revoked_at: Option<DateTime<Utc>>,
+    used_at: Option<DateTime<Utc>>,
+}
+
+impl TokenLifecycle {
+    fn from_row(row: &PgRow) -> Self {
+        Self {
+            expires_at: row.get("expires_at"),
+            revoked_at: row.get("revoked_at"),
+            used_at: row.get("used_at"),
+        }
+    }
+
+    /// Tokens can be used once, before they expire or are revoked.
+    fn status(&self, now: DateTime<Utc>) -> TokenStatus {
+        if self.used_at.is_some() {
+            TokenStatus::Used
+        } else if self.revoked_at.is_some() {
+            TokenStatus::Revoked
+        } else if self.expires_at <= now {
+            TokenStatus::Expired
+        } else {
+            TokenStatus::Active
+        }
+    }
+}
+
+pub async fn create_token(
+    relay_pool: &PgPool,
+    mev_pool: &PgPool,
+    builder_id: &str,
+    now: DateTime<Utc>,
+) -> Result<String> {
+    let expires_at = now + TOKEN_TTL;
+    let mut token: String;
+
+    loop {
+        token = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(16)
+            .map(char::from)
+            .collect();
+
+        let token_exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM promotion_tokens WHERE token = $1)",
+        )
+        .bind(&token)
+        .fetch_one(relay_pool)
+        .await?;
+
+        if !token_exists {
+            break;
+        }
+    }
+
+    sqlx::query(
+        "
+        INSERT INTO promotion_tokens (
+            builder_id,
+            token,
+            expires_at
+        )
+        VALUES ($1, $2, $3)
+        ",
+    )
+    .bind(builder_id)
+    .bind(&token)
+    .bind(expires_at)
+    .execute(relay_pool)
+    .await?;
+
+    let logged = sqlx::query(
+        "
+        INSERT INTO promotion_token_log (
+            token,
+            builder_id,
+            issued_at,
+            expires_at
+        )
+        VALUES ($1, $2, $3, $4)
+        ",
+    )
+    .bind(&token)
+    .bind(builder_id)
+    .bind(now)
+    .bind(expires_at)
+    .execute(mev_pool)
+    .await;
+
+    // A token we don't log is never revoked or pruned, take it out of the relay again.
+    if let Err(err) = logged {
+        sqlx::query("DELETE FROM promotion_tokens WHERE token = $1")
+            .bind(&token)
+            .execute(relay_pool)
+            .await
+            .context("failed to remove promotion token which couldn't be logged")?;
+        return Err(err.into());
+    }
+
+    Ok(token)
+}
+
+/// Revoke the outstanding tokens of a builder, e.g. because it was promoted some other way. They
+/// are logged as revoked before they leave the relay, so they aren't taken for used tokens.
+pub async fn revoke_builder_tokens(
+    relay_pool: &PgPool,
+    mev_pool: &PgPool,
+    builder_id: &str,
+    reason: &str,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let revoked = sqlx::query(
+        "
+        UPDATE promotion_token_log
+        SET revoked_at = $3, revoke_reason = $2
+        WHERE builder_id = $1
+          AND revoked_at IS NULL
+          AND used_at IS NULL
+          AND expires_at > $3
+        ",
+    )
+    .bind(builder_id)
+    .bind(reason)
+    .bind(now)
+    .execute(mev_pool)
+    .await?
+    .rows_affected();
+
+    sqlx::query("DELETE FROM promotion_tokens WHERE builder_id = $1")
+        .bind(builder_id)
+        .execute(relay_pool)
+        .await?;
+
+    if revoked > 0 {
+        info!(builder_id, reason, revoked, "revoked promotion tokens");
+    }
+
+    Ok(())
+}
+
+/// Remove expired tokens from the relay, and tokens past retention from the log.
+pub async fn prune_tokens(
+    relay_pool: &PgPool,
+    mev_pool: &PgPool,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let expired = sqlx::query("DELETE FROM promotion_tokens WHERE expires_at <= $1")
+        .bind(now)
+        .execute(relay_pool)
+        .await?
+        .rows_affected();
+
+    // The log keeps tokens for the retention after the last thing that happened to them.
+    let pruned = sqlx::query(
+        "
+        DELETE FROM promotion_token_log
+        WHERE GREATEST(expires_at, revoked_at, used_at) < $1
+        ",
+    )
+    .bind(now - TOKEN_LOG_RETENTION)
+    .execute(mev_pool)
+    .await?
+    .rows_affected();
+
+    if expired > 0 || pruned > 0 {
+        info!(expired, pruned, "pruned promotion tokens");
+    }
+
+    Ok(())
+}
+
+/// Log tokens which left the relay database while they could still be used as used.
+pub async fn detect_used_tokens(
+    relay_pool: &PgPool,
+    mev_pool: &PgPool,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let active: Vec<String> = sqlx::query_scalar(
+        "
+        SELECT token
+        FROM promotion_token_log
+        WHERE revoked_at IS NULL
+          AND used_at IS NULL
+          AND expires_at > $1
+        ",
+    )
+    .bind(now)
+    .fetch_all(mev_pool)
+    .await?;
+    if active.is_empty() {
+        return Ok(());
+    }
+
+    let remaining: HashSet<String> =
+        sqlx::query_scalar("SELECT token FROM promotion_tokens WHERE token = ANY($1)")
+            .bind(&active)
+            .fetch_all(relay_pool)
+            .await?
+            .into_iter()
+            .collect();
+    let used = active
+        .into_iter()
+        .filter(|token| !remaining.contains(token))
+        .collect::<Vec<_>>();
+    if used.is_empty() {
+        return Ok(());
+    }
+
+    let recorded = sqlx::query(
+        "
+        UPDATE promotion_token_log
+        SET used_at = $2
+        WHERE token = ANY($1)
+          AND revoked_at IS NULL
+          AND used_at IS NULL
+        ",
+    )
+    .bind(&used)
+    .bind(now)
+    .execute(mev_pool)
+    .await?
+    .rows_affected();
+    info!(recorded, "promotion tokens used");
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct ActiveTokensParams {
+    builder_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ActiveToken {
+    id: i64,
+    issued_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+/// Tokens which can still be used, per builder.
+pub async fn active_tokens(
+    State(state): State<ServerState>,
+    Query(params): Query<ActiveTokensParams>,
+) -> Result<Json<BTreeMap<String, Vec<ActiveToken>>>, (StatusCode, String)> {
+    let rows = sqlx::query(
+        "
+        SELECT id, builder_id, issued_at, expires_at, revoked_at, used_at
+        FROM promotion_token_log
+        WHERE revoked_at IS NULL
+          AND used_at IS NULL
+          AND ($1::text IS NULL OR builder_id = $1)
+        ORDER BY issued_at ASC
+        ",
+    )
+    .bind(params.builder_id)
+    .fetch_all(&state.mev_pool)
+    .await
+    .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+    let now = Utc::now();
+    let mut tokens: BTreeMap<String, Vec<ActiveToken>> = BTreeMap::new();
+    for row in rows {
+        if TokenLifecycle::from_row(&row).status(now) != TokenStatus::Active {
+            continue;
+        }
+        tokens
+            .entry(row.get("builder_id"))
+            .or_default()
+            .push(ActiveToken {
+                id: row.get("id"),
+                issued_at: row.get("issued_at"),
+                expires_at: row.get("expires_at"),
+            });
+    }
+
+    Ok(Json(tokens))
+}
+
+#[derive(Deserialize)]
+pub struct TokenUse {
+    token: String,
+    used_by: String,
+}
+
+/// Whether a reported token use can be recorded. Tokens `detect_used_tokens` found used are only
+/// missing who used them.
+fn can_record_use(lifecycle: &TokenLifecycle, used_by: Option<&str>, now: DateTime<Utc>) -> bool {
+    match lifecycle.status(now) {
+        TokenStatus::Active => true,
+        TokenStatus::Used => used_by.is_none(),
+        TokenStatus::Expired | TokenStatus::Revoked => false,
+    }
+}
+
+/// For the promote endpoint to report who used a token, with `{"token", "used_by"}` as body and
+/// the admin token as bearer token. When it was used is recorded either way.
+pub async fn record_token_use(
+    State(state): State<ServerState>,
+    Json(token_use): Json<TokenUse>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let internal_error = |err: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string());
+    let now = Utc::now();
+
+    let mut tx = state.mev_pool.begin().await.map_err(internal_error)?;
+    let row = sqlx::query(
+        "
+        SELECT expires_at, revoked_at, used_at, used_by
+        FROM promotion_token_log
+        WHERE token = $1
+        FOR UPDATE
+        ",
+    )
+    .bind(&token_use.token)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(internal_error)?
+    .ok_or((StatusCode::NOT_FOUND, "token not found".to_string()))?;
+
+    let lifecycle = TokenLifecycle::from_row(&row);
+    let used_by: Option<String> = row.get("used_by");
+    if !can_record_use(&lifecycle, used_by.as_deref(), now) {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("token can't be used: {:?}", lifecycle.status(now)),
+        ));
+    }
+
+    sqlx::query(
+        "
+        UPDATE promotion_token_log
+        SET used_at = COALESCE(used_at, $2), used_by = $3
+        WHERE token = $1
+        ",
+    )
+    .bind(&token_use.token)
+    .bind(now)
+    .bind(&token_use.used_by)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+    tx.commit().await.map_err(internal_error)?;
+
+    info!(used_by = token_use.used_by, "promotion token used");
+    Ok(StatusCode::OK)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn issued_at() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap()
+    }
+
+    fn token() -> TokenLifecycle {
+        TokenLifecycle {
+            expires_at: issued_at() + TOKEN_TTL,
+            revoked_at: None,
+            used_at: None,
+        }
+    }
+
+    #[test]
+    fn test_token_expiry() {
+        let token = token();
+        assert_eq!(token.status(issued_at()), TokenStatus::Active);
+        assert_eq!(
+            token.status(token.expires_at - Duration::seconds(1)),
+            TokenStatus::Active
+        );
+        assert_eq!(token.status(token.expires_at), TokenStatus::Expired);
+    }
+
+    #[test]
+    fn test_token_single_use() {
+        let used = TokenLifecycle {
+            used_at: Some(issued_at() + Duration::hours(1)),
+            ..token()
+        };
+        assert_eq!(
+            used.status(issued_at() + Duration::hours(2)),
+            TokenStatus::Used
+        );
+
+        let revoked = TokenLifecycle {
+            revoked_at: Some(issued_at() + Duration::hours(1)),
+            ..token()
+        };
+        assert_eq!(
+            revoked.status(issued_at() + Duration::hours(2)),
+            TokenStatus::Revoked
+        );
+    }
+
+    #[test]
+    fn test_record_use() {
+        let now = issued_at() + Duration::hours(2);
+        assert!(can_record_use(&token(), None, now));
+
+        // A use the relay side already noticed only gets who used it, once.
+        let used = TokenLifecycle {
+            used_at: Some(issued_at() + Duration::hours(1)),
+            ..token()
+        };
+        assert!(can_record_use(&used, None, now));
+        assert!(!can_record_use(&used, Some("ops"), now));
+
+        let revoked = TokenLifecycle {
+            revoked_at: Some(issued_at() + Duration::hours(1)),
+            ..token()
+        };
+        assert!(!can_record_use(&revoked, None, now));
+        assert!(!can_record_use(&token(), None, token().expires_at));
+    }
+}
@@ -0,0 +1,133 @@
+//! Watches the share of next epoch slots whose proposer registered with us. Coverage moves a little
+//! from epoch to epoch, a sharp drop compared to recent epochs usually means a large operator
+//! stopped registering, or registrations stopped reaching the relay.
+
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use sqlx::PgPool;
+use tracing::debug;
+
+use crate::{
+    beacon_api::BeaconApi,
+    chain_time::Epoch,
+    proposers::{get_upcoming_proposers, EpochCoverage},
+};
+
+use super::{
+    env::{APP_CONFIG, CHAIN_TIME},
+    Alarm, AlarmType,
+};
+
+/// Number of epochs the coverage is compared against, about two and a half hours.
+const BASELINE_EPOCHS: usize = 24;
+/// Fewer epochs than this make for a baseline too noisy to alert on.
+const MIN_BASELINE_EPOCHS: usize = 4;
+
+/// Coverage of the most recent epochs, each recorded once.
+#[derive(Default)]
+pub struct CoverageHistory {
+    epochs: VecDeque<(Epoch, f64)>,
+}
+
+impl CoverageHistory {
+    fn baseline(&self) -> Option<f64> {
+        if self.epochs.len() < MIN_BASELINE_EPOCHS {
+            return None;
+        }
+        let total: f64 = self.epochs.iter().map(|(_, coverage)| coverage).sum();
+        Some(total / self.epochs.len() as f64)
+    }
+
+    fn contains(&self, epoch: Epoch) -> bool {
+        self.epochs.iter().any(|(recorded, _)| *recorded == epoch)
+    }
+
+    fn record(&mut self, coverage: &EpochCoverage) {
+        self.epochs.push_back((coverage.epoch, coverage.coverage));
+        while self.epochs.len() > BASELINE_EPOCHS {
+            self.epochs.pop_front();
+        }
+    }
+
+    /// Record the coverage of a new epoch. Returns the baseline if the coverage dropped more than
+    /// `max_drop` below it.
+    fn check(&mut self, coverage: &EpochCoverage, max_drop: f64) -> Option<f64> {
+        if self.contains(coverage.epoch) {
+            return None;
+        }
+        let baseline = self.baseline();
+        self.record(coverage);
+        baseline.filter(|baseline| baseline - coverage.coverage > max_drop)
+    }
+}
+
+pub async fn run_proposer_coverage_monitor(
+    relay_pool: &PgPool,
+    beacon_api: &BeaconApi,
+    history: &mut CoverageHistory,
+    alarm: &mut Alarm,
+) -> Result<()> {
+    let upcoming = get_upcoming_proposers(beacon_api, relay_pool, &CHAIN_TIME).await?;
+    let coverage = upcoming.next_epoch_coverage;
+    debug!(
+        epoch = %coverage.epoch,
+        coverage = coverage.coverage,
+        "checked proposer coverage of next epoch"
+    );
+
+    if let Some(baseline) = history.check(&coverage, APP_CONFIG.proposer_coverage_max_drop) {
+        let message = format!(
+            "Proposer coverage for epoch {} dropped to {:.0}% ({}/{} slots), from {:.0}% over recent epochs",
+            coverage.epoch,
+            coverage.coverage * 100.0,
+            coverage.registered_slots,
+            coverage.slots,
+            baseline * 100.0
+        );
+        alarm.fire(&message, &AlarmType::Telegram).await;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coverage(epoch: i64, coverage: f64) -> EpochCoverage {
+        EpochCoverage {
+            epoch: Epoch(epoch),
+            slots: 32,
+            registered_slots: (coverage * 32.0) as usize,
+            coverage,
+        }
+    }
+
+    #[test]
+    fn test_coverage_drop() {
+        let mut history = CoverageHistory::default();
+        // No baseline to compare against yet, even for a drop.
+        assert_eq!(history.check(&coverage(1, 0.8), 0.2), None);
+        assert_eq!(history.check(&coverage(2, 0.9), 0.2), None);
+        assert_eq!(history.check(&coverage(3, 0.8), 0.2), None);
+        assert_eq!(history.check(&coverage(4, 0.1), 0.2), None);
+
+        // Small changes are fine.
+        assert_eq!(history.check(&coverage(5, 0.5), 0.2), None);
+        // Sharp drops are not, each epoch is only checked once.
+        let baseline = history.check(&coverage(6, 0.3), 0.2).unwrap();
+        assert!((baseline - 0.62).abs() < 1e-9);
+        assert_eq!(history.check(&coverage(6, 0.3), 0.2), None);
+    }
+
+    #[test]
+    fn test_history_keeps_recent_epochs() {
+        let mut history = CoverageHistory::default();
+        for epoch in 0..100 {
+            history.check(&coverage(epoch, 0.5), 0.2);
+        }
+        assert_eq!(history.epochs.len(), BASELINE_EPOCHS);
+        assert_eq!(history.epochs.front().unwrap().0, Epoch(76));
+    }
+}
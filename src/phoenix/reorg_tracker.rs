@@ -0,0 +1,306 @@
+//! Records the chain reorgs our beacon nodes report, with the slots they replaced. For each slot we
+//! keep the block that is canonical after the reorg and the payloads we delivered, so it shows
+//! which of our payloads a reorg took off the chain.
+//!
+//! The canonical blocks are found by walking back from the new head by parent root. Looking them
+//! up by slot right as the event arrives would ask nodes which may not have followed the reorg yet,
+//! and they'd answer with the orphaned blocks.
+
+use std::{collections::HashMap, ops::RangeInclusive};
+
+use anyhow::{anyhow, Context, Result};
+use sqlx::{PgPool, Row};
+use tracing::{error, info, warn};
+
+use crate::{
+    beacon_api::{BeaconApi, BeaconEvent},
+    phoenix::env::APP_CONFIG,
+};
+
+/// Deeper reorgs are recorded, but only this many of their slots.
+const MAX_TRACKED_DEPTH: i64 = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ChainReorg {
+    slot: i64,
+    depth: i64,
+    old_head_block: String,
+    new_head_block: String,
+}
+
+impl ChainReorg {
+    fn affected_slots(&self) -> RangeInclusive<i64> {
+        (self.slot - self.depth.clamp(1, MAX_TRACKED_DEPTH) + 1)..=self.slot
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AffectedSlot {
+    slot: i64,
+    canonical_block_hash: Option<String>,
+    delivered_block_hashes: Vec<String>,
+}
+
+impl AffectedSlot {
+    /// Payloads we delivered for the slot which didn't end up on chain.
+    fn orphaned_payloads(&self) -> Vec<&String> {
+        self.delivered_block_hashes
+            .iter()
+            .filter(|block_hash| self.canonical_block_hash.as_ref() != Some(*block_hash))
+            .collect()
+    }
+}
+
+async fn get_delivered_block_hashes(
+    relay_pool: &PgPool,
+    slots: &RangeInclusive<i64>,
+) -> Result<HashMap<i64, Vec<String>>> {
+    let rows = sqlx::query(
+        "
+        SELECT slot, ARRAY_AGG(DISTINCT block_hash) AS block_hashes
+        FROM payload_delivered
+        WHERE slot BETWEEN $1 AND $2
+        GROUP BY slot
+        ",
+    )
+    .bind(slots.start())
+    .bind(slots.end())
+    .fetch_all(relay_pool)
+    .await?;
+    Ok(rows
+        .iter()
+        .map(|row| (row.get("slot"), row.get("block_hashes")))
+        .collect())
+}
+
+/// Execution block hashes of the affected slots on the chain of the new head. Slots the walk
+/// steps over have no block on that chain. Fails when a block on the way can't be looked up, as
+/// stopping early would leave the remaining slots looking empty and mark every payload we
+/// delivered for them as orphaned.
+async fn get_canonical_block_hashes(
+    beacon_api: &BeaconApi,
+    reorg: &ChainReorg,
+) -> Result<HashMap<i64, String>> {
+    let slots = reorg.affected_slots();
+    let mut block_hashes = HashMap::new();
+    let mut root = reorg.new_head_block.clone();
+    loop {
+        let (header, block) = beacon_api
+            .block_by_root(&root)
+            .await
+            .with_context(|| format!("failed to fetch block {} on the new chain", root))?;
+        if header.slot < *slots.start() {
+            break;
+        }
+        if slots.contains(&header.slot) {
+            block_hashes.insert(header.slot, block.execution_payload.block_hash);
+        }
+        root = header.parent_root;
+    }
+    Ok(block_hashes)
+}
+
+async fn get_affected_slots(
+    relay_pool: &PgPool,
+    beacon_api: &BeaconApi,
+    reorg: &ChainReorg,
+) -> Result<Vec<AffectedSlot>> {
+    let slots = reorg.affected_slots();
+    let mut delivered = get_delivered_block_hashes(relay_pool, &slots).await?;
+    let mut canonical = get_canonical_block_hashes(beacon_api, reorg).await?;
+
+    Ok(slots
+        .map(|slot| AffectedSlot {
+            slot,
+            canonical_block_hash: canonical.remove(&slot),
+            delivered_block_hashes: delivered.remove(&slot).unwrap_or_default(),
+        })
+        .collect())
+}
+
+async fn is_reorg_recorded(mev_pool: &PgPool, reorg: &ChainReorg) -> Result<bool> {
+    let recorded = sqlx::query_scalar(
+        "
+        SELECT EXISTS (
+            SELECT 1
+            FROM chain_reorgs
+            WHERE slot = $1 AND old_head_block = $2 AND new_head_block = $3
+        )
+        ",
+    )
+    .bind(reorg.slot)
+    .bind(&reorg.old_head_block)
+    .bind(&reorg.new_head_block)
+    .fetch_one(mev_pool)
+    .await?;
+    Ok(recorded)
+}
+
+/// Store a reorg and its slots. Returns false if another node already reported it. Nothing is
+/// stored on failure, so the same event from another node tries again.
+async fn record_reorg(
+    relay_pool: &PgPool,
+    mev_pool: &PgPool,
+    beacon_api: &BeaconApi,
+    reorg: &ChainReorg,
+) -> Result<bool> {
+    if is_reorg_recorded(mev_pool, reorg).await? {
+        return Ok(false);
+    }
+
+    // Looked up before the transaction starts, so it isn't held open while we wait on the beacon
+    // nodes.
+    let affected_slots = get_affected_slots(relay_pool, beacon_api, reorg).await?;
+
+    let mut tx = mev_pool.begin().await?;
+    let reorg_id: Option<i64> = sqlx::query_scalar(
+        "
+        INSERT INTO chain_reorgs (slot, depth, old_head_block, new_head_block)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT DO NOTHING
+        RETURNING id
+        ",
+    )
+    .bind(reorg.slot)
+    .bind(reorg.depth)
+    .bind(&reorg.old_head_block)
+    .bind(&reorg.new_head_block)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(reorg_id) = reorg_id else {
+        return Ok(false);
+    };
+
+    for affected in affected_slots {
+        let orphaned = affected.orphaned_payloads();
+        if !orphaned.is_empty() {
+            warn!(
+                slot = affected.slot,
+                ?orphaned,
+                "chain reorg replaced payloads we delivered"
+            );
+        }
+
+        sqlx::query(
+            "
+            INSERT INTO chain_reorg_slots (
+                reorg_id,
+                slot,
+                canonical_block_hash,
+                delivered_block_hashes
+            )
+            VALUES ($1, $2, $3, $4)
+            ",
+        )
+        .bind(reorg_id)
+        .bind(affected.slot)
+        .bind(&affected.canonical_block_hash)
+        .bind(&affected.delivered_block_hashes)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+pub async fn run_reorg_tracker(relay_pool: &PgPool, mev_pool: &PgPool) -> Result<()> {
+    let beacon_api = BeaconApi::new(&APP_CONFIG.consensus_nodes);
+    let mut receiver = beacon_api.subscribe_all(&["chain_reorg"]);
+
+    info!("started chain reorg tracker");
+
+    while let Some(event) = receiver.recv().await {
+        let BeaconEvent::ChainReorg {
+            slot,
+            depth,
+            old_head_block,
+            new_head_block,
+        } = event
+        else {
+            continue;
+        };
+        let reorg = ChainReorg {
+            slot,
+            depth,
+            old_head_block,
+            new_head_block,
+        };
+
+        match record_reorg(relay_pool, mev_pool, &beacon_api, &reorg).await {
+            Ok(true) => info!(slot, depth, "recorded chain reorg"),
+            Ok(false) => {}
+            Err(err) => error!(slot, depth, "failed to record chain reorg: {:#}", err),
+        }
+    }
+
+    Err(anyhow!("all beacon event subscriptions ended"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beacon_api::mock::MockBeaconNode;
+
+    fn reorg(slot: i64, depth: i64) -> ChainReorg {
+        ChainReorg {
+            slot,
+            depth,
+            old_head_block: "0x9a".to_string(),
+            new_head_block: "0x76".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_affected_slots() {
+        assert_eq!(reorg(200, 1).affected_slots(), 200..=200);
+        assert_eq!(reorg(200, 3).affected_slots(), 198..=200);
+        // Nodes report depth 0 when only the head moved to a sibling.
+        assert_eq!(reorg(200, 0).affected_slots(), 200..=200);
+        assert_eq!(reorg(200, 1000).affected_slots().count(), 64);
+    }
+
+    #[test]
+    fn test_orphaned_payloads() {
+        let slot = AffectedSlot {
+            slot: 200,
+            canonical_block_hash: Some("0xaa".to_string()),
+            delivered_block_hashes: vec!["0xaa".to_string(), "0xbb".to_string()],
+        };
+        assert_eq!(slot.orphaned_payloads(), vec!["0xbb"]);
+
+        // When the slot ended up empty, nothing we delivered made it.
+        let missed = AffectedSlot {
+            canonical_block_hash: None,
+            ..slot
+        };
+        assert_eq!(missed.orphaned_payloads().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_canonical_block_hashes_follow_the_new_head() {
+        let node = MockBeaconNode::start();
+        // The node still serves the orphaned blocks by slot.
+        node.set_block(199, "0xold199", 199);
+        node.set_block(200, "0xold200", 200);
+        // New chain: 197 <- 199 <- 200, slot 198 is empty.
+        node.set_block_by_root("0x76", 200, "0x75", "0xaa");
+        node.set_block_by_root("0x75", 199, "0x74", "0xbb");
+        node.set_block_by_root("0x74", 197, "0x73", "0xcc");
+        let beacon_api = BeaconApi::new(&[node.url()]);
+
+        let block_hashes = get_canonical_block_hashes(&beacon_api, &reorg(200, 3))
+            .await
+            .unwrap();
+        assert_eq!(
+            block_hashes,
+            HashMap::from([(200, "0xaa".to_string()), (199, "0xbb".to_string())])
+        );
+
+        // A gap in the walk fails rather than leaving the older slots empty.
+        assert!(get_canonical_block_hashes(&beacon_api, &reorg(200, 5))
+            .await
+            .is_err());
+    }
+}
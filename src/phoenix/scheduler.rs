@@ -0,0 +1,123 @@
+//! Runs tasks on their own interval. Tasks are handed out one at a time in the order they come
+//! due, tasks due at the same time in the order they were added. A task which runs late is next due
+//! one interval after it ran, missed runs are not made up.
+
+use chrono::{DateTime, Duration, Utc};
+
+use super::clock::SharedClock;
+
+struct ScheduledTask<T> {
+    task: T,
+    interval: Duration,
+    next_run: DateTime<Utc>,
+}
+
+pub struct Scheduler<T> {
+    clock: SharedClock,
+    tasks: Vec<ScheduledTask<T>>,
+}
+
+impl<T: Copy> Scheduler<T> {
+    pub fn new(clock: SharedClock) -> Self {
+        Self {
+            clock,
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Add a task, it is due right away.
+    pub fn add(&mut self, task: T, interval: Duration) {
+        let next_run = self.clock.now();
+        self.tasks.push(ScheduledTask {
+            task,
+            interval,
+            next_run,
+        });
+    }
+
+    /// Wait for the next task to come due. Returns it with the time it is run at.
+    pub async fn next(&mut self) -> (T, DateTime<Utc>) {
+        let (index, due) = self
+            .tasks
+            .iter()
+            .enumerate()
+            .map(|(index, task)| (index, task.next_run))
+            .min_by_key(|(index, next_run)| (*next_run, *index))
+            .expect("scheduler has no tasks");
+
+        let wait = due - self.clock.now();
+        if wait > Duration::zero() {
+            self.clock.sleep(wait).await;
+        }
+
+        let now = self.clock.now();
+        let scheduled = &mut self.tasks[index];
+        scheduled.next_run = due.max(now) + scheduled.interval;
+        (scheduled.task, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use crate::phoenix::clock::SimulatedClock;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tasks_run_on_their_interval() {
+        let start = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let clock = SimulatedClock::new(start);
+        let mut scheduler = Scheduler::new(clock.clone());
+        scheduler.add('a', Duration::minutes(1));
+        scheduler.add('b', Duration::minutes(1));
+        scheduler.add('c', Duration::minutes(5));
+
+        let mut runs = Vec::new();
+        for _ in 0..13 {
+            let (task, now) = scheduler.next().await;
+            runs.push((task, (now - start).num_minutes()));
+        }
+
+        assert_eq!(
+            runs,
+            vec![
+                ('a', 0),
+                ('b', 0),
+                ('c', 0),
+                ('a', 1),
+                ('b', 1),
+                ('a', 2),
+                ('b', 2),
+                ('a', 3),
+                ('b', 3),
+                ('a', 4),
+                ('b', 4),
+                ('a', 5),
+                ('b', 5),
+            ]
+        );
+        assert_eq!(scheduler.next().await, ('c', start + Duration::minutes(5)));
+    }
+
+    #[tokio::test]
+    async fn test_late_task_skips_missed_runs() {
+        let start = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let clock = SimulatedClock::new(start);
+        let mut scheduler = Scheduler::new(clock.clone());
+        scheduler.add('a', Duration::minutes(1));
+        scheduler.add('b', Duration::hours(1));
+
+        assert_eq!(scheduler.next().await, ('a', start));
+        // The task ran for three and a half minutes.
+        clock.advance(Duration::seconds(210));
+        assert_eq!(
+            scheduler.next().await,
+            ('b', start + Duration::seconds(210))
+        );
+        let (task, now) = scheduler.next().await;
+        assert_eq!((task, now), ('a', start + Duration::seconds(210)));
+        assert_eq!(scheduler.next().await.1, now + Duration::minutes(1));
+    }
+}
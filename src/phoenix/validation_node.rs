@@ -1,27 +1,136 @@
+use anyhow::Context;
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
-use serde::Deserialize;
+use chrono::{DateTime, TimeZone, Utc};
+use futures::future::join_all;
+use reqwest::Url;
+use serde::{de::DeserializeOwned, Deserialize};
 use serde_json::json;
 use std::time::Duration;
 use tokio::time::sleep;
 use tracing::{debug, error, info};
 
-use super::{env::APP_CONFIG, PhoenixMonitor};
+use super::{
+    clock::SharedClock, env::APP_CONFIG, redact_credentials, PhoenixMonitor, UnhealthyNode,
+};
 
 #[derive(Deserialize)]
-struct SyncResponse {
-    result: bool,
+struct RpcResponse<T> {
+    result: T,
 }
 
-async fn get_sync_status(client: &reqwest::Client, url: String) -> reqwest::Result<SyncResponse> {
+#[derive(Deserialize)]
+struct LatestBlock {
+    number: String,
+    timestamp: String,
+}
+
+async fn rpc_call<T: DeserializeOwned>(
+    client: &reqwest::Client,
+    url: &Url,
+    method: &str,
+    params: serde_json::Value,
+) -> anyhow::Result<T> {
     client
-        .post(url)
-        .json(&json!({"jsonrpc": "2.0", "method": "eth_syncing", "params": [], "id": 1}))
+        .post(url.clone())
+        .json(&json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1}))
         .send()
         .await?
         .error_for_status()?
-        .json::<SyncResponse>()
+        .json::<RpcResponse<T>>()
         .await
+        .map(|body| body.result)
+        .with_context(|| format!("failed to call {}", method))
+}
+
+fn parse_hex_u64(hex: &str) -> anyhow::Result<u64> {
+    u64::from_str_radix(hex.trim_start_matches("0x"), 16)
+        .with_context(|| format!("failed to parse {} as hex quantity", hex))
+}
+
+/// Everything we learn about a validation node in a single round of requests.
+#[derive(Debug, Clone)]
+struct NodeProbe {
+    url: Url,
+    is_syncing: bool,
+    block_number: u64,
+    block_timestamp: DateTime<Utc>,
+    peer_count: u64,
+}
+
+async fn probe_node(client: &reqwest::Client, url: &Url) -> anyhow::Result<NodeProbe> {
+    let (sync_status, latest_block, peer_count) = tokio::try_join!(
+        // Either false, or an object describing sync progress.
+        rpc_call::<serde_json::Value>(client, url, "eth_syncing", json!([])),
+        rpc_call::<LatestBlock>(
+            client,
+            url,
+            "eth_getBlockByNumber",
+            json!(["latest", false])
+        ),
+        rpc_call::<String>(client, url, "net_peerCount", json!([])),
+    )?;
+
+    let block_timestamp = parse_hex_u64(&latest_block.timestamp)?;
+
+    Ok(NodeProbe {
+        url: url.clone(),
+        is_syncing: sync_status != serde_json::Value::Bool(false),
+        block_number: parse_hex_u64(&latest_block.number)?,
+        block_timestamp: Utc
+            .timestamp_opt(block_timestamp as i64, 0)
+            .single()
+            .context("latest block timestamp out of range")?,
+        peer_count: parse_hex_u64(&peer_count)?,
+    })
+}
+
+struct ProbeLimits {
+    max_block_lag: u64,
+    max_block_age: chrono::Duration,
+    min_peer_count: u64,
+}
+
+/// Check each probe on its own, and its block number against the highest in the cluster.
+fn find_unhealthy_nodes(
+    probes: &[NodeProbe],
+    now: DateTime<Utc>,
+    limits: &ProbeLimits,
+) -> Vec<UnhealthyNode> {
+    let max_block_number = probes.iter().map(|p| p.block_number).max().unwrap_or(0);
+
+    probes
+        .iter()
+        .filter_map(|probe| {
+            let mut failed_checks = Vec::new();
+
+            if probe.is_syncing {
+                failed_checks.push("syncing".to_string());
+            }
+
+            let block_lag = max_block_number - probe.block_number;
+            if block_lag > limits.max_block_lag {
+                failed_checks.push(format!("{} blocks behind", block_lag));
+            }
+
+            let block_age = now - probe.block_timestamp;
+            if block_age > limits.max_block_age {
+                failed_checks.push(format!(
+                    "latest block is {} seconds old",
+                    block_age.num_seconds()
+                ));
+            }
+
+            if probe.peer_count < limits.min_peer_count {
+                failed_checks.push(format!("{} peers", probe.peer_count));
+            }
+
+            if failed_checks.is_empty() {
+                None
+            } else {
+                Some(UnhealthyNode::new(&probe.url, failed_checks.join(", ")))
+            }
+        })
+        .collect()
 }
 
 pub struct ValidationNodeMonitor {
@@ -35,62 +144,144 @@ impl ValidationNodeMonitor {
         }
     }
 
-    async fn check_nodes_once(&self) -> Vec<bool> {
-        let mut results = Vec::new();
+    async fn check_nodes_once(&self, clock: &SharedClock) -> Vec<UnhealthyNode> {
+        let futures = APP_CONFIG
+            .validation_nodes
+            .iter()
+            .map(|url| probe_node(&self.client, url));
+        let results = join_all(futures).await;
 
-        for url in &APP_CONFIG.validation_nodes {
-            let status = get_sync_status(&self.client, url.to_string()).await;
+        let mut unhealthy_nodes = Vec::new();
+        let mut probes = Vec::new();
 
-            match status {
-                Ok(s) => results.push(!s.result),
+        for (url, result) in APP_CONFIG.validation_nodes.iter().zip(results) {
+            match result {
+                Ok(probe) => probes.push(probe),
                 Err(err) => {
-                    error!("error getting validation node status: {}", err);
-                    results.push(false)
+                    error!(url = %redact_credentials(url), "error getting validation node status: {:#}", err);
+                    unhealthy_nodes.push(UnhealthyNode::new(url, "unreachable"));
                 }
             }
         }
 
-        results
+        let limits = ProbeLimits {
+            max_block_lag: APP_CONFIG.validation_node_max_block_lag,
+            max_block_age: chrono::Duration::seconds(
+                APP_CONFIG.validation_node_max_block_age_seconds,
+            ),
+            min_peer_count: APP_CONFIG.validation_node_min_peer_count,
+        };
+        unhealthy_nodes.extend(find_unhealthy_nodes(&probes, clock.now(), &limits));
+
+        unhealthy_nodes
     }
 
-    pub async fn num_unsynced_nodes(&self) -> usize {
+    pub async fn unhealthy_nodes(&self, clock: &SharedClock) -> Vec<UnhealthyNode> {
         // First attempt
-        let mut results = self.check_nodes_once().await;
-        let mut offline_nodes = results.iter().filter(|is_synced| !**is_synced).count();
+        let mut unhealthy_nodes = self.check_nodes_once(clock).await;
 
-        // If any nodes are offline, retry after 3 seconds
-        if offline_nodes > 0 {
+        // If any nodes are unhealthy, retry after 3 seconds
+        if !unhealthy_nodes.is_empty() {
             info!(
-                "found {} offline validation nodes, retrying in 3s",
-                offline_nodes
+                "found {} unhealthy validation nodes, retrying in 3s",
+                unhealthy_nodes.len()
             );
             sleep(Duration::from_secs(3)).await;
 
-            results = self.check_nodes_once().await;
-            offline_nodes = results.iter().filter(|is_synced| !**is_synced).count();
+            unhealthy_nodes = self.check_nodes_once(clock).await;
 
-            // If still offline, try one last time
-            if offline_nodes > 0 {
+            // If still unhealthy, try one last time
+            if !unhealthy_nodes.is_empty() {
                 info!(
-                    "still found {} offline validation nodes, final retry in 3s",
-                    offline_nodes
+                    "still found {} unhealthy validation nodes, final retry in 3s",
+                    unhealthy_nodes.len()
                 );
                 sleep(Duration::from_secs(3)).await;
-                results = self.check_nodes_once().await;
+                unhealthy_nodes = self.check_nodes_once(clock).await;
             }
         }
 
-        let synced: Vec<&bool> = results.iter().filter(|is_synced| **is_synced).collect();
-        debug!("{}/{} validation nodes synced", synced.len(), results.len());
+        let node_count = APP_CONFIG.validation_nodes.len();
+        debug!(
+            "{}/{} validation nodes healthy",
+            node_count - unhealthy_nodes.len(),
+            node_count
+        );
 
-        results.len() - synced.len()
+        unhealthy_nodes
     }
 }
 
 #[async_trait]
 impl PhoenixMonitor for ValidationNodeMonitor {
-    async fn refresh(&self) -> (DateTime<Utc>, usize) {
-        let num_unsynced_nodes = self.num_unsynced_nodes().await;
-        (Utc::now(), num_unsynced_nodes)
+    async fn refresh(&self, clock: &SharedClock) -> Vec<UnhealthyNode> {
+        self.unhealthy_nodes(clock).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: ProbeLimits = ProbeLimits {
+        max_block_lag: 2,
+        max_block_age: chrono::Duration::seconds(60),
+        min_peer_count: 1,
+    };
+
+    fn now() -> DateTime<Utc> {
+        "2024-06-01T12:00:00Z".parse().unwrap()
+    }
+
+    fn probe(host: &str, block_number: u64, block_age_seconds: i64, peer_count: u64) -> NodeProbe {
+        NodeProbe {
+            url: Url::parse(&format!("http://{}:8545/", host)).unwrap(),
+            is_syncing: false,
+            block_number,
+            block_timestamp: now() - chrono::Duration::seconds(block_age_seconds),
+            peer_count,
+        }
+    }
+
+    #[test]
+    fn test_parse_hex_u64() {
+        assert_eq!(parse_hex_u64("0x0").unwrap(), 0);
+        assert_eq!(parse_hex_u64("0x1312d00").unwrap(), 20_000_000);
+        assert!(parse_hex_u64("0xzz").is_err());
+    }
+
+    #[test]
+    fn test_healthy_nodes() {
+        let probes = vec![probe("a", 100, 4, 50), probe("b", 99, 16, 50)];
+        assert_eq!(find_unhealthy_nodes(&probes, now(), &LIMITS), vec![]);
+    }
+
+    #[test]
+    fn test_lagging_node() {
+        let probes = vec![probe("a", 100, 4, 50), probe("b", 97, 40, 50)];
+        assert_eq!(
+            find_unhealthy_nodes(&probes, now(), &LIMITS),
+            vec![UnhealthyNode::new(
+                &Url::parse("http://b:8545/").unwrap(),
+                "3 blocks behind"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_stale_node_without_peers() {
+        let mut syncing = probe("b", 100, 4, 50);
+        syncing.is_syncing = true;
+        let probes = vec![probe("a", 100, 120, 0), syncing];
+        assert_eq!(
+            find_unhealthy_nodes(&probes, now(), &LIMITS),
+            vec![
+                UnhealthyNode::new(
+                    &Url::parse("http://a:8545/").unwrap(),
+                    "latest block is 120 seconds old, 0 peers"
+                ),
+                UnhealthyNode::new(&Url::parse("http://b:8545/").unwrap(), "syncing"),
+            ]
+        );
     }
 }
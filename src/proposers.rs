@@ -0,0 +1,178 @@
+//! Upcoming block proposers and whether they registered with the relay. Only registered proposers
+//! can get their blocks from us, so the share of registered proposers in an epoch is the share of
+//! its slots we can serve.
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::{
+    beacon_api::{BeaconApi, ProposerDuty},
+    chain_time::{ChainTime, Epoch, Slot},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpcomingProposer {
+    pub slot: Slot,
+    pub validator_index: i64,
+    pub pubkey: String,
+    pub registered: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpochCoverage {
+    pub epoch: Epoch,
+    pub slots: usize,
+    pub registered_slots: usize,
+    /// Share of the slots whose proposer registered with us, 0 for an epoch without duties.
+    pub coverage: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpcomingProposers {
+    pub current_slot: Slot,
+    pub next_epoch_coverage: EpochCoverage,
+    /// Proposers from the current slot up to the end of the next epoch.
+    pub proposers: Vec<UpcomingProposer>,
+}
+
+async fn get_registered_pubkeys(
+    relay_pool: &PgPool,
+    pubkeys: &[String],
+) -> Result<HashSet<String>> {
+    let registered: Vec<String> = sqlx::query_scalar(
+        "
+        SELECT DISTINCT pubkey
+        FROM validator_registration
+        WHERE pubkey = ANY($1)
+        ",
+    )
+    .bind(pubkeys)
+    .fetch_all(relay_pool)
+    .await?;
+    Ok(registered.into_iter().collect())
+}
+
+fn to_upcoming_proposers(
+    duties: Vec<ProposerDuty>,
+    registered: &HashSet<String>,
+) -> Vec<UpcomingProposer> {
+    let mut proposers = duties
+        .into_iter()
+        .map(|duty| UpcomingProposer {
+            slot: Slot(duty.slot),
+            validator_index: duty.validator_index,
+            registered: registered.contains(&duty.pubkey),
+            pubkey: duty.pubkey,
+        })
+        .collect::<Vec<_>>();
+    proposers.sort_by_key(|proposer| proposer.slot);
+    proposers
+}
+
+pub fn epoch_coverage(epoch: Epoch, proposers: &[UpcomingProposer]) -> EpochCoverage {
+    let slots = epoch.slots();
+    let in_epoch = proposers
+        .iter()
+        .filter(|proposer| slots.contains(proposer.slot))
+        .collect::<Vec<_>>();
+    let registered_slots = in_epoch
+        .iter()
+        .filter(|proposer| proposer.registered)
+        .count();
+    let coverage = if in_epoch.is_empty() {
+        0.0
+    } else {
+        registered_slots as f64 / in_epoch.len() as f64
+    };
+    EpochCoverage {
+        epoch,
+        slots: in_epoch.len(),
+        registered_slots,
+        coverage,
+    }
+}
+
+/// Fetch the proposers of the current and next epoch and check which of them registered.
+pub async fn get_upcoming_proposers(
+    beacon_api: &BeaconApi,
+    relay_pool: &PgPool,
+    chain_time: &ChainTime,
+) -> Result<UpcomingProposers> {
+    let current_slot = chain_time.current_slot();
+    let current_epoch = current_slot.epoch();
+    let next_epoch = Epoch(current_epoch.0 + 1);
+
+    let (current_duties, next_duties) = tokio::try_join!(
+        beacon_api.proposer_duties(current_epoch.0),
+        beacon_api.proposer_duties(next_epoch.0)
+    )?;
+    let duties = current_duties
+        .into_iter()
+        .chain(next_duties)
+        .filter(|duty| duty.slot >= current_slot.0)
+        .collect::<Vec<_>>();
+
+    let pubkeys = duties
+        .iter()
+        .map(|duty| duty.pubkey.clone())
+        .collect::<Vec<_>>();
+    let registered = get_registered_pubkeys(relay_pool, &pubkeys).await?;
+    let proposers = to_upcoming_proposers(duties, &registered);
+
+    Ok(UpcomingProposers {
+        current_slot,
+        next_epoch_coverage: epoch_coverage(next_epoch, &proposers),
+        proposers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn duty(slot: i64, pubkey: &str) -> ProposerDuty {
+        ProposerDuty {
+            pubkey: pubkey.to_string(),
+            validator_index: slot * 10,
+            slot,
+        }
+    }
+
+    #[test]
+    fn test_epoch_coverage() {
+        let registered = HashSet::from(["0xa1".to_string(), "0xb2".to_string()]);
+        // Epoch 2 spans slots 64 to 95, a quarter of them are proposed by registered validators.
+        let duties = (64..96)
+            .rev()
+            .map(|slot| match slot % 8 {
+                0 => duty(slot, "0xa1"),
+                1 => duty(slot, "0xb2"),
+                _ => duty(slot, "0xc3"),
+            })
+            .chain([duty(63, "0xa1")])
+            .collect();
+
+        let proposers = to_upcoming_proposers(duties, &registered);
+        assert_eq!(proposers[0].slot, Slot(63));
+        assert!(proposers[1].registered);
+        assert_eq!(proposers[1].validator_index, 640);
+        assert!(!proposers[3].registered);
+
+        assert_eq!(
+            epoch_coverage(Epoch(2), &proposers),
+            EpochCoverage {
+                epoch: Epoch(2),
+                slots: 32,
+                registered_slots: 8,
+                coverage: 0.25,
+            }
+        );
+        assert_eq!(epoch_coverage(Epoch(3), &proposers).coverage, 0.0);
+    }
+}
@@ -3,7 +3,9 @@ mod builder;
 mod censorship;
 mod env;
 mod payload;
+mod proposers;
 mod relay_redis;
+mod reorgs;
 mod timeframe;
 mod validator;
 
@@ -27,6 +29,10 @@ use tracing::info;
 use crate::{beacon_api::BeaconApi, log};
 use env::APP_CONFIG;
 
+/// How often the sync status of the consensus nodes is checked, it decides which node is asked
+/// first.
+const SYNC_STATUS_INTERVAL: Duration = Duration::from_secs(60);
+
 #[derive(Clone)]
 pub struct AppState {
     mev_db_pool: PgPool,
@@ -70,6 +76,14 @@ pub async fn start_server() -> Result<()> {
 
     let beacon_api = BeaconApi::new(&APP_CONFIG.consensus_nodes);
 
+    let sync_status_api = beacon_api.clone();
+    tokio::spawn(async move {
+        loop {
+            sync_status_api.refresh_sync_status().await;
+            tokio::time::sleep(SYNC_STATUS_INTERVAL).await;
+        }
+    });
+
     let shared_state = AppState {
         mev_db_pool,
         global_db_pool,
@@ -86,9 +100,15 @@ pub async fn start_server() -> Result<()> {
             "/api/validators/:pubkey",
             get(validator::check_validator_registration),
         )
+        .route(
+            "/api/proposers/upcoming",
+            get(proposers::upcoming_proposers),
+        )
+        .route("/api/reorgs", get(reorgs::reorgs))
         .route("/api/payloads", get(payload::delivered_payloads))
         .route("/api/payloads/stats", get(payload::payload_stats))
         .route("/api/payloads/top", get(payload::top_payloads))
+        .route("/api/payloads/publish-stats", get(payload::publish_stats))
         .route("/api/builders/top", get(builder::top_builders))
         .route("/api/censorship/operators", get(censorship::operators))
         .route("/api/censorship/builders", get(censorship::builders))
@@ -4,6 +4,8 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 
+use crate::chain_time::Slot;
+
 use super::{
     internal_error,
     timeframe::{Timeframe, Timeframed},
@@ -13,8 +15,8 @@ use super::{
 #[derive(Deserialize)]
 #[allow(dead_code)]
 pub struct Cursor {
-    start_slot: i64,
-    end_slot: i64,
+    start_slot: Slot,
+    end_slot: Slot,
 }
 
 #[derive(Serialize)]
@@ -51,8 +53,8 @@ pub async fn block_production(
         ORDER BY slot_number ASC
         LIMIT 200
         "#,
-        cursor.start_slot,
-        cursor.end_slot
+        cursor.start_slot.0,
+        cursor.end_slot.0
     )
     .fetch_all(&state.mev_db_pool)
     .await
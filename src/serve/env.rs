@@ -3,11 +3,14 @@ use std::sync::LazyLock;
 use reqwest::Url;
 use serde::Deserialize;
 
-use crate::env::{deserialize_urls, get_app_config, Env};
+use crate::chain_time::ChainTime;
+use crate::env::{deserialize_network_or_legacy_env, deserialize_urls, get_app_config, Network};
 
 #[derive(Deserialize)]
 pub struct AppConfig {
-    pub env: Env,
+    /// Deployments from before `NETWORK` set `ENV` to stag or prod, which is still accepted.
+    #[serde(alias = "env", deserialize_with = "deserialize_network_or_legacy_env")]
+    pub network: Network,
     pub port: u16,
     pub database_url: String,
     pub global_database_url: String,
@@ -18,3 +21,6 @@ pub struct AppConfig {
 }
 
 pub static APP_CONFIG: LazyLock<AppConfig> = LazyLock::new(get_app_config);
+
+pub static CHAIN_TIME: LazyLock<ChainTime> =
+    LazyLock::new(|| ChainTime::for_network(&APP_CONFIG.network));
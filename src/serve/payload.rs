@@ -1,7 +1,10 @@
-use axum::{extract::State, Json};
+use axum::{
+    extract::{Query, State},
+    Json,
+};
 use chrono::{DateTime, TimeZone, Utc};
-use serde::Serialize;
-use sqlx::Row;
+use serde::{Deserialize, Serialize};
+use sqlx::{postgres::PgRow, Row};
 
 use super::{internal_error, ApiResponse, AppState};
 
@@ -26,6 +29,43 @@ pub struct PayloadStatsBody {
     first_payload_at: DateTime<Utc>,
 }
 
+const DEFAULT_PUBLISH_STATS_DAYS: i32 = 7;
+const MAX_PUBLISH_STATS_DAYS: i32 = 90;
+
+#[derive(Deserialize)]
+pub struct PublishStatsParams {
+    days: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct Percentiles {
+    p50: f64,
+    p90: f64,
+    p99: f64,
+}
+
+impl Percentiles {
+    fn from_row(row: &PgRow, column: &str) -> Self {
+        let values: Vec<f64> = row.get(column);
+        Self {
+            p50: values[0],
+            p90: values[1],
+            p99: values[2],
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishStats {
+    day: DateTime<Utc>,
+    geo: String,
+    count: i64,
+    decoded_at_slot_age_ms: Percentiles,
+    publish_duration_ms: Percentiles,
+    publish_done_ms_into_slot: Percentiles,
+}
+
 pub async fn delivered_payloads(State(state): State<AppState>) -> ApiResponse<PayloadsBody> {
     let query = "
         select inserted_at, block_number, (value / 10^18) as value
@@ -100,3 +140,54 @@ pub async fn top_payloads(State(state): State<AppState>) -> ApiResponse<Payloads
         })
         .map_err(internal_error)
 }
+
+/// Publish latency percentiles of delivered payloads per geo and per day.
+pub async fn publish_stats(
+    params: Query<PublishStatsParams>,
+    State(state): State<AppState>,
+) -> ApiResponse<Vec<PublishStats>> {
+    let days = params
+        .days
+        .unwrap_or(DEFAULT_PUBLISH_STATS_DAYS)
+        .clamp(1, MAX_PUBLISH_STATS_DAYS);
+
+    let query = "
+        select date_trunc('day', delivered_at) as day,
+        geo,
+        count(*) as count,
+        percentile_cont(array[0.5, 0.9, 0.99]) within group (order by decoded_at_slot_age_ms)
+            as decoded_at_slot_age_ms,
+        percentile_cont(array[0.5, 0.9, 0.99]) within group (order by publish_duration_ms)
+            as publish_duration_ms,
+        percentile_cont(array[0.5, 0.9, 0.99]) within group (order by publish_done_ms_into_slot)
+            as publish_done_ms_into_slot
+        from payload_publish_stats
+        where delivered_at >= date_trunc('day', now()) - make_interval(days => $1)
+        group by day, geo
+        order by day desc, geo
+    ";
+
+    sqlx::query(query)
+        .bind(days)
+        .fetch_all(&state.mev_db_pool)
+        .await
+        .map(|rows| {
+            let stats = rows
+                .iter()
+                .map(|row| PublishStats {
+                    day: row.get("day"),
+                    geo: row.get("geo"),
+                    count: row.get("count"),
+                    decoded_at_slot_age_ms: Percentiles::from_row(row, "decoded_at_slot_age_ms"),
+                    publish_duration_ms: Percentiles::from_row(row, "publish_duration_ms"),
+                    publish_done_ms_into_slot: Percentiles::from_row(
+                        row,
+                        "publish_done_ms_into_slot",
+                    ),
+                })
+                .collect();
+
+            Json(stats)
+        })
+        .map_err(internal_error)
+}
@@ -0,0 +1,14 @@
+use axum::{extract::State, Json};
+
+use crate::proposers::{self, UpcomingProposers};
+
+use super::{env::CHAIN_TIME, internal_error, ApiResponse, AppState};
+
+/// Proposers of the rest of the current epoch and of the next one, and whether they registered
+/// with us.
+pub async fn upcoming_proposers(State(state): State<AppState>) -> ApiResponse<UpcomingProposers> {
+    proposers::get_upcoming_proposers(&state.beacon_api, &state.global_db_pool, &CHAIN_TIME)
+        .await
+        .map(Json)
+        .map_err(internal_error)
+}
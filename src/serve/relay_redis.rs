@@ -1,14 +1,9 @@
-use crate::env::ToNetwork;
-
 use super::APP_CONFIG;
 
 pub async fn get_known_validator_count(client: &redis::Client) -> anyhow::Result<i64> {
     let mut conn = client.get_multiplexed_async_connection().await?;
 
-    let key = format!(
-        "boost-relay/{}:stats",
-        &APP_CONFIG.env.to_network().to_string()
-    );
+    let key = format!("{}:stats", APP_CONFIG.network.redis_key_prefix);
     let count: Option<i64> = redis::cmd("HGET")
         .arg(key)
         .arg("validators-total")
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+
+use super::{internal_error, ApiResponse, AppState};
+
+const DEFAULT_REORG_LIMIT: i64 = 50;
+const MAX_REORG_LIMIT: i64 = 500;
+
+#[derive(Deserialize)]
+pub struct ReorgsParams {
+    limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorgedSlot {
+    slot: i64,
+    canonical_block_hash: Option<String>,
+    delivered_block_hashes: Vec<String>,
+    /// Whether a payload we delivered for this slot was taken off the chain.
+    payload_orphaned: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reorg {
+    slot: i64,
+    depth: i64,
+    old_head_block: String,
+    new_head_block: String,
+    detected_at: DateTime<Utc>,
+    slots: Vec<ReorgedSlot>,
+}
+
+#[derive(Serialize)]
+pub struct ReorgsBody {
+    reorgs: Vec<Reorg>,
+}
+
+/// Most recent chain reorgs first, with the slots they replaced.
+pub async fn reorgs(
+    State(state): State<AppState>,
+    Query(params): Query<ReorgsParams>,
+) -> ApiResponse<ReorgsBody> {
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_REORG_LIMIT)
+        .clamp(1, MAX_REORG_LIMIT);
+
+    let reorg_rows = sqlx::query(
+        "
+        select id, slot, depth, old_head_block, new_head_block, detected_at
+        from chain_reorgs
+        order by detected_at desc
+        limit $1
+        ",
+    )
+    .bind(limit)
+    .fetch_all(&state.mev_db_pool)
+    .await
+    .map_err(internal_error)?;
+
+    let ids: Vec<i64> = reorg_rows.iter().map(|row| row.get("id")).collect();
+    let slot_rows = sqlx::query(
+        "
+        select reorg_id, slot, canonical_block_hash, delivered_block_hashes
+        from chain_reorg_slots
+        where reorg_id = any($1)
+        order by slot asc
+        ",
+    )
+    .bind(&ids)
+    .fetch_all(&state.mev_db_pool)
+    .await
+    .map_err(internal_error)?;
+
+    let mut slots: HashMap<i64, Vec<ReorgedSlot>> = HashMap::new();
+    for row in slot_rows {
+        let canonical_block_hash: Option<String> = row.get("canonical_block_hash");
+        let delivered_block_hashes: Vec<String> = row.get("delivered_block_hashes");
+        let payload_orphaned = delivered_block_hashes
+            .iter()
+            .any(|block_hash| canonical_block_hash.as_ref() != Some(block_hash));
+        slots
+            .entry(row.get("reorg_id"))
+            .or_default()
+            .push(ReorgedSlot {
+                slot: row.get("slot"),
+                canonical_block_hash,
+                delivered_block_hashes,
+                payload_orphaned,
+            });
+    }
+
+    let reorgs = reorg_rows
+        .iter()
+        .map(|row| Reorg {
+            slot: row.get("slot"),
+            depth: row.get("depth"),
+            old_head_block: row.get("old_head_block"),
+            new_head_block: row.get("new_head_block"),
+            detected_at: row.get("detected_at"),
+            slots: slots.remove(&row.get::<i64, _>("id")).unwrap_or_default(),
+        })
+        .collect();
+
+    Ok(Json(ReorgsBody { reorgs }))
+}